@@ -0,0 +1,66 @@
+//! Transport buffers under connection churn: thrift's buffered transports allocate two buffers
+//! per connection, the pooled ones reuse them. The allocations per cycle are printed
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    io,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use thrift::transport::{TBufferedReadTransport, TBufferedWriteTransport};
+use thrift_pool::{FromRead, FromWrite, PooledBufferedReadTransport, PooledBufferedWriteTransport};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocations_per_cycle(cycle: impl Fn()) -> f64 {
+    const CYCLES: u64 = 10_000;
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..CYCLES {
+        cycle();
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / CYCLES as f64
+}
+
+fn thrift_buffers() {
+    let read = TBufferedReadTransport::new(io::empty());
+    let write = TBufferedWriteTransport::new(io::sink());
+    black_box((read, write));
+}
+
+fn pooled_buffers() {
+    let read = PooledBufferedReadTransport::from_read(io::empty());
+    let write = PooledBufferedWriteTransport::from_write(io::sink());
+    black_box((read, write));
+}
+
+fn churn(c: &mut Criterion) {
+    println!(
+        "allocations per connect/disconnect: thrift buffers {}, pooled buffers {}",
+        allocations_per_cycle(thrift_buffers),
+        allocations_per_cycle(pooled_buffers),
+    );
+
+    c.bench_function("thrift buffers", |b| b.iter(thrift_buffers));
+    c.bench_function("pooled buffers", |b| b.iter(pooled_buffers));
+}
+
+criterion_group!(benches, churn);
+criterion_main!(benches);
@@ -0,0 +1,106 @@
+//! A batch of small requests, made one by one or coalesced: thrift writes every request to the
+//! socket on its own, the coalescing layer writes the batch at once. The writes reaching the
+//! socket per batch are printed
+
+use std::{
+    hint::black_box,
+    io::{self, Write},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use thrift::protocol::{TBinaryInputProtocol, TBinaryOutputProtocol, TOutputProtocol};
+use thrift::transport::TBufferedWriteTransport;
+use thrift_pool::{CoalescingConnection, CoalescingWrite, FromProtocol, ProtocolAccess};
+
+const BATCH: usize = 100;
+
+static WRITES: AtomicU64 = AtomicU64::new(0);
+
+/// A socket counting the write syscalls it would make
+struct Socket;
+
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        WRITES.fetch_add(1, Ordering::Relaxed);
+        Ok(black_box(buf).len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+type OutputProtocol<W> = TBinaryOutputProtocol<TBufferedWriteTransport<W>>;
+
+struct Client<W: Write> {
+    i_prot: TBinaryInputProtocol<io::Empty>,
+    o_prot: OutputProtocol<W>,
+}
+
+impl<W: Write> FromProtocol for Client<W> {
+    type InputProtocol = TBinaryInputProtocol<io::Empty>;
+    type OutputProtocol = OutputProtocol<W>;
+    fn from_protocol(i_prot: Self::InputProtocol, o_prot: Self::OutputProtocol) -> Self {
+        Client { i_prot, o_prot }
+    }
+}
+
+impl<W: Write> ProtocolAccess for Client<W> {
+    fn input_protocol_mut(&mut self) -> &mut Self::InputProtocol {
+        &mut self.i_prot
+    }
+    fn output_protocol_mut(&mut self) -> &mut Self::OutputProtocol {
+        &mut self.o_prot
+    }
+}
+
+fn client<W: Write>(write: W) -> Client<W> {
+    Client::from_protocol(
+        TBinaryInputProtocol::new(io::empty(), true),
+        TBinaryOutputProtocol::new(TBufferedWriteTransport::new(write), true),
+    )
+}
+
+/// A small oneway request
+fn request<W: Write>(client: &mut Client<W>) -> thrift::Result<()> {
+    client.o_prot.write_i64(42)?;
+    client.o_prot.flush()
+}
+
+fn one_by_one(client: &mut Client<Socket>) {
+    for _ in 0..BATCH {
+        request(client).unwrap();
+    }
+}
+
+fn coalesced(conn: &mut CoalescingConnection<Client<CoalescingWrite<Socket>>>) {
+    conn.coalesce(|client| (0..BATCH).try_for_each(|_| request(client)))
+        .unwrap();
+}
+
+fn writes_per_batch(mut batch: impl FnMut()) -> f64 {
+    const BATCHES: u64 = 1_000;
+    let before = WRITES.load(Ordering::Relaxed);
+    for _ in 0..BATCHES {
+        batch();
+    }
+    (WRITES.load(Ordering::Relaxed) - before) as f64 / BATCHES as f64
+}
+
+fn batch(c: &mut Criterion) {
+    let mut plain = client(Socket);
+    let mut coalescing = CoalescingConnection::new(client(CoalescingWrite::new(Socket)));
+
+    println!(
+        "writes per batch of {BATCH} requests: one by one {}, coalesced {}",
+        writes_per_batch(|| one_by_one(&mut plain)),
+        writes_per_batch(|| coalesced(&mut coalescing)),
+    );
+
+    c.bench_function("one by one", |b| b.iter(|| one_by_one(&mut plain)));
+    c.bench_function("coalesced", |b| b.iter(|| coalesced(&mut coalescing)));
+}
+
+criterion_group!(benches, batch);
+criterion_main!(benches);
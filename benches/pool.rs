@@ -0,0 +1,50 @@
+//! Pool churn: the cost of checking out and returning a connection, without a server
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use thrift_pool::{testing::NoopMaker, ThriftConnectionManager};
+
+#[cfg(feature = "impl-r2d2")]
+fn r2d2_churn(c: &mut Criterion) {
+    let pool = r2d2::Pool::builder()
+        .max_size(4)
+        .build(ThriftConnectionManager::new(NoopMaker))
+        .unwrap();
+
+    c.bench_function("r2d2 get", |b| b.iter(|| drop(pool.get().unwrap())));
+
+    let pool = r2d2::Pool::builder()
+        .max_size(4)
+        .test_on_check_out(false)
+        .build(ThriftConnectionManager::new(NoopMaker))
+        .unwrap();
+
+    c.bench_function("r2d2 get (no checkout test)", |b| {
+        b.iter(|| drop(pool.get().unwrap()))
+    });
+}
+
+#[cfg(feature = "impl-bb8")]
+fn bb8_churn(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let pool = runtime
+        .block_on(
+            bb8::Pool::builder()
+                .max_size(4)
+                .build(ThriftConnectionManager::new(NoopMaker)),
+        )
+        .unwrap();
+
+    c.bench_function("bb8 get", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { drop(pool.get().await.unwrap()) })
+    });
+}
+
+#[cfg(not(feature = "impl-r2d2"))]
+fn r2d2_churn(_: &mut Criterion) {}
+
+#[cfg(not(feature = "impl-bb8"))]
+fn bb8_churn(_: &mut Criterion) {}
+
+criterion_group!(benches, r2d2_churn, bb8_churn);
+criterion_main!(benches);
@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    ops::Deref,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{
+    ConnectionId, MakeThriftConnection, ThriftConnection, ThriftConnectionManager, TracedConnection,
+};
+
+/// A map from keys to the connections they last used, to check out the same connection
+/// (and so the same backend) for related operations, like the calls of a scanner
+///
+/// Connections are identified by their [`ConnectionId`], so the pool's connections have to be
+/// [`TracedConnection`]s. The affinity is best-effort: if the connection associated with a key
+/// is checked out elsewhere, or was dropped by the pool, any other connection is returned and
+/// becomes associated with the key. To find the connection, idle connections are checked out
+/// one by one until it shows up (see [`validate_all_r2d2`](crate::validate_all_r2d2)
+/// for what that implies), so this is meant for small pools
+///
+/// ```
+/// # use std::sync::atomic::{AtomicU32, Ordering};
+/// # use thrift_pool::{Affinity, MakeThriftConnection, ThriftConnection, ThriftConnectionManager, TracedConnection};
+/// // a connection to one of several backends
+/// struct Conn {
+///     backend: u32,
+/// }
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # #[derive(Default)]
+/// # struct Maker(AtomicU32);
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = TracedConnection<Conn>;
+/// #     fn make_thrift_connection(&self) -> Result<Self::Output, thrift::Error> {
+/// #         let backend = self.0.fetch_add(1, Ordering::SeqCst);
+/// #         Ok(TracedConnection::new(Conn { backend }))
+/// #     }
+/// # }
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = r2d2::Pool::builder()
+///     .max_size(3)
+///     .build(ThriftConnectionManager::new(Maker::default()))?;
+/// let affinity = Affinity::new();
+///
+/// let backend = affinity.get_affine_r2d2(&pool, "scanner-1")?.backend;
+/// for _ in 0..5 {
+///     let conn = affinity.get_affine_r2d2(&pool, "scanner-1")?;
+///     assert_eq!(conn.backend, backend);
+///     // unrelated checkouts don't break the affinity
+///     drop((pool.get()?, pool.get()?));
+/// }
+///
+/// // while the connection is used elsewhere, another one is returned
+/// let held = affinity.get_affine_r2d2(&pool, "scanner-1")?;
+/// assert_ne!(affinity.get_affine_r2d2(&pool, "scanner-1")?.backend, held.backend);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Affinity<K> {
+    connections: Mutex<HashMap<K, ConnectionId>>,
+}
+
+impl<K> Default for Affinity<K> {
+    fn default() -> Self {
+        Self {
+            connections: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Hash + Eq> Affinity<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn connections(&self) -> MutexGuard<'_, HashMap<K, ConnectionId>> {
+        self.connections.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The connection `key` is associated with, if any
+    pub fn connection_id(&self, key: &K) -> Option<ConnectionId> {
+        self.connections().get(key).copied()
+    }
+
+    /// Stop associating `key` with a connection
+    pub fn forget(&self, key: &K) {
+        self.connections().remove(key);
+    }
+
+    /// Take the connection `key` is associated with out of `conns`, returning the others to the pool
+    fn find<P, C>(&self, key: &K, conns: Vec<P>) -> Option<P>
+    where
+        P: Deref<Target = TracedConnection<C>>,
+    {
+        let id = self.connection_id(key)?;
+        conns.into_iter().find(|conn| conn.connection_id() == id)
+    }
+
+    /// Check out the connection `key` is associated with from `pool` if it is idle,
+    /// otherwise any connection (which becomes associated with `key`)
+    ///
+    /// # Errors
+    ///
+    /// See [`r2d2::Pool::get`]
+    #[cfg(feature = "impl-r2d2")]
+    pub fn get_affine_r2d2<T, C>(
+        &self,
+        pool: &r2d2::Pool<ThriftConnectionManager<T>>,
+        key: K,
+    ) -> Result<r2d2::PooledConnection<ThriftConnectionManager<T>>, r2d2::Error>
+    where
+        T: MakeThriftConnection<Output = TracedConnection<C>> + Send + Sync + 'static,
+        T::Error: std::error::Error + 'static,
+        C: ThriftConnection<Error = T::Error> + Send + 'static,
+    {
+        if self.connection_id(&key).is_some() {
+            let idle = pool.state().idle_connections;
+            let conns = (0..idle).map_while(|_| pool.try_get()).collect();
+            if let Some(conn) = self.find(&key, conns) {
+                return Ok(conn);
+            }
+        }
+        let conn = pool.get()?;
+        self.connections().insert(key, conn.connection_id());
+        Ok(conn)
+    }
+
+    /// Check out the connection `key` is associated with from `pool` if it is idle,
+    /// otherwise any connection (which becomes associated with `key`)
+    ///
+    /// This works like [`Affinity::get_affine_r2d2`], except that idle connections are checked
+    /// out with [`bb8::Pool::get`] (see [`validate_all_bb8`](crate::validate_all_bb8))
+    ///
+    /// # Errors
+    ///
+    /// See [`bb8::Pool::get`]
+    #[cfg(feature = "impl-bb8")]
+    pub async fn get_affine_bb8<'a, T, C>(
+        &self,
+        pool: &'a bb8::Pool<ThriftConnectionManager<T>>,
+        key: K,
+    ) -> Result<bb8::PooledConnection<'a, ThriftConnectionManager<T>>, bb8::RunError<T::Error>>
+    where
+        T: MakeThriftConnection<Output = TracedConnection<C>> + Send + Sync + 'static,
+        T::Error: Send + std::fmt::Debug + 'static,
+        C: ThriftConnection<Error = T::Error> + Send + 'static,
+    {
+        if self.connection_id(&key).is_some() {
+            let idle = pool.state().idle_connections;
+            let mut conns = Vec::new();
+            for _ in 0..idle {
+                match pool.get().await {
+                    Ok(conn) => conns.push(conn),
+                    Err(_) => break,
+                }
+            }
+            if let Some(conn) = self.find(&key, conns) {
+                return Ok(conn);
+            }
+        }
+        let conn = pool.get().await?;
+        self.connections().insert(key, conn.connection_id());
+        Ok(conn)
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    ops::{Deref, DerefMut},
+    time::{Duration, Instant},
+};
+
+use crate::{rng::Rng, MakeThriftConnection, ThriftConnection, TransportInfo, TransportKind};
+
+/// A [`ThriftConnection`] that reports itself as broken once its deadline has passed,
+/// so the pool drops it when it is returned. Created by [`AgingMaker`]
+///
+/// It derefs to `C`
+#[derive(Debug)]
+pub struct AgingConnection<C> {
+    conn: C,
+    deadline: Instant,
+}
+
+impl<C> AgingConnection<C> {
+    pub fn new(conn: C, deadline: Instant) -> Self {
+        Self { conn, deadline }
+    }
+
+    /// When the connection expires
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+impl<C> Deref for AgingConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<C> DerefMut for AgingConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<C: ThriftConnection> ThriftConnection for AgingConnection<C> {
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.conn.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        Instant::now() >= self.deadline || self.conn.has_broken()
+    }
+}
+
+/// A [`MakeThriftConnection`] wrapping the connections of the inner maker `M`
+/// in [`AgingConnection`]s that expire after `max_age`
+///
+/// With a fixed age, connections created together (e.g. when the pool is filled) all
+/// expire together, and are all reopened at once. [`AgingMaker::with_max_age_jitter`]
+/// spreads the deadlines: each connection's lifetime is shortened by an exponentially
+/// distributed duration, truncated to `max_age_jitter` (which is also its scale), so lifetimes
+/// fall between `max_age - max_age_jitter` and `max_age`. Most connections live close to
+/// `max_age`, and fewer and fewer expire earlier, which desynchronizes them with less churn
+/// than a uniform jitter. No connection outlives `max_age`.
+/// Clones share the same random number generator, which can be seeded with
+/// [`AgingMaker::with_seed`] for reproducible deadlines
+///
+/// ```
+/// # use std::time::Duration;
+/// # use thrift_pool::{AgingMaker, MakeThriftConnection};
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = ();
+/// #     fn make_thrift_connection(&self) -> Result<(), thrift::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// let max_age = Duration::from_secs(100);
+/// let jitter = Duration::from_secs(50);
+/// let maker = AgingMaker::new(Maker, max_age)
+///     .with_max_age_jitter(jitter)
+///     .with_seed(42);
+///
+/// let start = std::time::Instant::now();
+/// let deadlines = (0..100)
+///     .map(|_| Ok(maker.make_thrift_connection()?.deadline()))
+///     .collect::<Result<Vec<_>, thrift::Error>>()?;
+/// let end = std::time::Instant::now();
+///
+/// let earliest = *deadlines.iter().min().unwrap();
+/// let latest = *deadlines.iter().max().unwrap();
+/// assert!(earliest >= start + max_age - jitter);
+/// assert!(latest <= end + max_age);
+/// // the deadlines cover most of the band
+/// assert!(latest - earliest > Duration::from_secs(40));
+/// // but most are in its later half
+/// let late = deadlines.iter().filter(|&&d| d >= start + max_age - jitter / 2).count();
+/// assert!(late > 55);
+/// # Ok::<(), thrift::Error>(())
+/// ```
+pub struct AgingMaker<M> {
+    maker: M,
+    max_age: Duration,
+    max_age_jitter: Duration,
+    rng: Rng,
+}
+
+impl<M> AgingMaker<M> {
+    pub fn new(maker: M, max_age: Duration) -> Self {
+        Self {
+            maker,
+            max_age,
+            max_age_jitter: Duration::ZERO,
+            rng: Rng::new(None),
+        }
+    }
+
+    /// Shorten the lifetime of each connection by an exponentially distributed duration
+    /// of up to `max_age_jitter` (defaults to zero, capped at `max_age`)
+    pub fn with_max_age_jitter(mut self, max_age_jitter: Duration) -> Self {
+        self.max_age_jitter = max_age_jitter.min(self.max_age);
+        self
+    }
+
+    /// Seed the random number generator used for the jitter (defaults to a random seed)
+    ///
+    /// This is meant for tests
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::new(Some(seed));
+        self
+    }
+
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    pub fn max_age_jitter(&self) -> Duration {
+        self.max_age_jitter
+    }
+
+    fn lifetime(&self) -> Duration {
+        if self.max_age_jitter.is_zero() {
+            return self.max_age;
+        }
+        // inverse transform sampling of an exponential distribution of scale 1, truncated to
+        // [0, 1): the density decreases from 1 to 1/e across the band
+        let unit = -(-self.rng.next_unit() * (1.0 - (-1.0f64).exp())).ln_1p();
+        self.max_age - self.max_age_jitter.mul_f64(unit.min(1.0))
+    }
+}
+
+impl<M: Clone> Clone for AgingMaker<M> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            max_age: self.max_age,
+            max_age_jitter: self.max_age_jitter,
+            rng: self.rng.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for AgingMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgingMaker")
+            .field("maker", &self.maker)
+            .field("max_age", &self.max_age)
+            .field("max_age_jitter", &self.max_age_jitter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M: MakeThriftConnection> MakeThriftConnection for AgingMaker<M> {
+    type Error = M::Error;
+
+    type Output = AgingConnection<M::Output>;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let conn = self.maker.make_thrift_connection()?;
+        Ok(AgingConnection::new(conn, Instant::now() + self.lifetime()))
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for AgingMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
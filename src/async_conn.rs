@@ -0,0 +1,216 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::{MakeThriftConnection, ThriftConnection, TransportInfo, TransportKind};
+
+/// Like [`ThriftConnection`], but with an `async` [`AsyncThriftConnection::is_valid`]
+///
+/// [`ThriftConnectionManager`](crate::ThriftConnectionManager) calls the synchronous
+/// [`ThriftConnection::is_valid`] from `bb8`, which blocks the runtime's thread if
+/// validation does a real RPC. Implement this instead (for clients whose validation can be
+/// awaited) and use [`AsyncThriftConnectionManager`].
+/// Connections whose validation is only synchronous can be wrapped in [`SpawnBlocking`]
+#[async_trait::async_trait]
+pub trait AsyncThriftConnection: Send {
+    type Error;
+
+    /// See [`ThriftConnection::is_valid`]
+    async fn is_valid(&mut self) -> Result<(), Self::Error>;
+
+    /// See [`ThriftConnection::has_broken`]
+    fn has_broken(&mut self) -> bool {
+        false
+    }
+}
+
+/// An [`AsyncThriftConnection`] validating the [`ThriftConnection`] `C`
+/// on a thread dedicated to blocking operations (see [`tokio::task::spawn_blocking`])
+///
+/// The connection is moved to that thread during validation. If the validation is cancelled
+/// (e.g. the pool gave up waiting for it) the connection is lost: the wrapper then reports
+/// itself as broken, so the pool drops it, and dereferencing it panics.
+/// A panic in [`ThriftConnection::is_valid`] is propagated
+///
+/// ```
+/// # use thrift_pool::{AsyncThriftConnection, SpawnBlocking, ThriftConnection};
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         // a blocking RPC
+/// #         std::thread::sleep(std::time::Duration::from_millis(10));
+/// #         Ok(())
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut conn = SpawnBlocking::new(Conn);
+/// AsyncThriftConnection::is_valid(&mut conn).await?;
+/// assert!(!AsyncThriftConnection::has_broken(&mut conn));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SpawnBlocking<C>(Option<C>);
+
+impl<C> SpawnBlocking<C> {
+    pub fn new(conn: C) -> Self {
+        Self(Some(conn))
+    }
+
+    /// The connection, unless it was lost in a cancelled validation
+    pub fn into_inner(self) -> Option<C> {
+        self.0
+    }
+}
+
+impl<C> Deref for SpawnBlocking<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+            .as_ref()
+            .expect("the connection was lost in a cancelled validation")
+    }
+}
+
+impl<C> DerefMut for SpawnBlocking<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0
+            .as_mut()
+            .expect("the connection was lost in a cancelled validation")
+    }
+}
+
+#[async_trait::async_trait]
+impl<C> AsyncThriftConnection for SpawnBlocking<C>
+where
+    C: ThriftConnection + Send + 'static,
+    C::Error: Send,
+{
+    type Error = C::Error;
+
+    async fn is_valid(&mut self) -> Result<(), Self::Error> {
+        let mut conn = self
+            .0
+            .take()
+            .expect("the connection was lost in a cancelled validation");
+        let (conn, res) = tokio::task::spawn_blocking(move || {
+            let res = conn.is_valid();
+            (conn, res)
+        })
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        self.0 = Some(conn);
+        res
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.0.as_mut().is_none_or(ThriftConnection::has_broken)
+    }
+}
+
+/// An implementor of [`bb8::ManageConnection`] for connections that are
+/// [`AsyncThriftConnection`]s, see [`ThriftConnectionManager`](crate::ThriftConnectionManager)
+///
+/// ```
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// # use thrift_pool::{AsyncThriftConnection, AsyncThriftConnectionManager, MakeThriftConnection};
+/// // a connection whose validation is an async RPC
+/// struct Conn {
+///     validations: Arc<AtomicUsize>,
+/// }
+///
+/// #[async_trait::async_trait]
+/// impl AsyncThriftConnection for Conn {
+///     type Error = thrift::Error;
+///     async fn is_valid(&mut self) -> Result<(), Self::Error> {
+///         tokio::time::sleep(Duration::from_millis(10)).await;
+///         self.validations.fetch_add(1, Ordering::SeqCst);
+///         Ok(())
+///     }
+/// }
+/// # struct Maker(Arc<AtomicUsize>);
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn { validations: self.0.clone() })
+/// #     }
+/// # }
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let validations = Arc::new(AtomicUsize::new(0));
+/// let pool = bb8::Pool::builder()
+///     .max_size(1)
+///     .build(AsyncThriftConnectionManager::new(Maker(validations.clone())))
+///     .await?;
+///
+/// drop(pool.get().await?);
+/// drop(pool.get().await?);
+/// assert_eq!(validations.load(Ordering::SeqCst), 2);
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncThriftConnectionManager<T>(T);
+
+impl<T: Clone> Clone for AsyncThriftConnectionManager<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for AsyncThriftConnectionManager<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("AsyncThriftConnectionManager")
+            .field(&self.0)
+            .finish()
+    }
+}
+
+impl<T> AsyncThriftConnectionManager<T> {
+    pub fn new(make_thrift_connection: T) -> Self {
+        Self(make_thrift_connection)
+    }
+
+    /// The underlying [`MakeThriftConnection`]
+    pub fn inner(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+#[async_trait::async_trait]
+impl<
+        E: Send + std::fmt::Debug + 'static,
+        C: AsyncThriftConnection<Error = E> + 'static,
+        T: MakeThriftConnection<Output = C, Error = E> + Send + Sync + 'static,
+    > bb8::ManageConnection for AsyncThriftConnectionManager<T>
+{
+    type Connection = C;
+
+    type Error = E;
+
+    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        self.0.make_thrift_connection()
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        conn.has_broken()
+    }
+
+    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        conn.is_valid().await
+    }
+}
+
+impl<T: TransportInfo> TransportInfo for AsyncThriftConnectionManager<T> {
+    fn transport_kind(&self) -> TransportKind {
+        self.0.transport_kind()
+    }
+}
@@ -0,0 +1,296 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+};
+
+use crate::{MakeThriftConnection, ThriftConnection, TransportInfo, TransportKind};
+
+/// The error returned by [`BackgroundConnection::is_valid`](ThriftConnection::is_valid)
+/// when its background connect failed, and that of a connect that panicked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackgroundConnectFailed {
+    /// The error of the connect
+    pub message: String,
+}
+
+impl std::fmt::Display for BackgroundConnectFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the background connect failed: {}", self.message)
+    }
+}
+
+impl std::error::Error for BackgroundConnectFailed {}
+
+impl From<BackgroundConnectFailed> for thrift::Error {
+    fn from(e: BackgroundConnectFailed) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// The outcome of a background connect, set once by its thread
+struct Outcome<C, E> {
+    result: Mutex<Option<Result<C, E>>>,
+    done: Condvar,
+}
+
+impl<C, E> Outcome<C, E> {
+    fn result(&self) -> MutexGuard<'_, Option<Result<C, E>>> {
+        self.result.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[derive(Debug)]
+enum State<C, E> {
+    Connecting,
+    Ready(C),
+    Failed(E),
+}
+
+/// A [`ThriftConnection`] whose underlying connection `C` is made on a background thread,
+/// created by [`BackgroundConnectMaker`]
+///
+/// While connecting, the connection is reported valid and not broken, so it can be checked out
+/// right away. Once the connect has failed, it is reported broken (and invalid), so the pool
+/// drops it. [`BackgroundConnection::wait`] gives access to `C`, once connected
+pub struct BackgroundConnection<C, E> {
+    state: State<C, E>,
+    outcome: Arc<Outcome<C, E>>,
+}
+
+impl<C, E> BackgroundConnection<C, E> {
+    /// Take the outcome of the connect, if it is done
+    fn poll(&mut self) {
+        if let State::Connecting = self.state {
+            if let Some(result) = self.outcome.result().take() {
+                self.state = Self::state_of(result);
+            }
+        }
+    }
+
+    fn state_of(result: Result<C, E>) -> State<C, E> {
+        match result {
+            Ok(conn) => State::Ready(conn),
+            Err(e) => State::Failed(e),
+        }
+    }
+
+    /// Whether the connect is still running
+    pub fn is_connecting(&mut self) -> bool {
+        self.poll();
+        matches!(self.state, State::Connecting)
+    }
+
+    /// Block until the connect is done, returning the connection or its error
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the connect
+    pub fn wait(&mut self) -> Result<&mut C, &E> {
+        if let State::Connecting = self.state {
+            let mut result = self
+                .outcome
+                .done
+                .wait_while(self.outcome.result(), |result| result.is_none())
+                .unwrap_or_else(|e| e.into_inner());
+            if let Some(result) = result.take() {
+                self.state = Self::state_of(result);
+            }
+        }
+        match &mut self.state {
+            State::Ready(conn) => Ok(conn),
+            State::Failed(e) => Err(e),
+            State::Connecting => unreachable!("the connect is done"),
+        }
+    }
+}
+
+impl<C: std::fmt::Debug, E: std::fmt::Debug> std::fmt::Debug for BackgroundConnection<C, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackgroundConnection")
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C, E> ThriftConnection for BackgroundConnection<C, E>
+where
+    C: ThriftConnection,
+    C::Error: From<BackgroundConnectFailed>,
+    E: std::fmt::Display,
+{
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.poll();
+        match &mut self.state {
+            State::Connecting => Ok(()),
+            State::Ready(conn) => conn.is_valid(),
+            State::Failed(e) => Err(BackgroundConnectFailed {
+                message: e.to_string(),
+            }
+            .into()),
+        }
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.poll();
+        match &mut self.state {
+            State::Connecting => false,
+            State::Ready(conn) => conn.has_broken(),
+            State::Failed(_) => true,
+        }
+    }
+}
+
+/// A [`MakeThriftConnection`] returning [`BackgroundConnection`]s right away,
+/// while the inner maker `M` connects them on a background thread
+///
+/// This keeps checkouts fast when connects are slow, while still finding out about a backend
+/// that can't be reached: the pool drops connections whose connect failed the next time it
+/// checks them, as broken when they're returned or as invalid on checkout. In exchange,
+/// a call on a connection checked out before it is connected waits for it
+/// (see [`BackgroundConnection::wait`]), and fails if the connect does.
+/// A connect that panics fails with a [`BackgroundConnectFailed`] error
+///
+/// ```
+/// # use std::time::{Duration, Instant};
+/// # use thrift_pool::{BackgroundConnectMaker, MakeThriftConnection, ThriftConnection};
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// // a backend that takes 50ms to answer, if it's up
+/// struct Maker {
+///     up: bool,
+/// }
+///
+/// impl MakeThriftConnection for Maker {
+///     type Error = thrift::Error;
+///     type Output = Conn;
+///     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+///         std::thread::sleep(Duration::from_millis(50));
+///         if self.up {
+///             Ok(Conn)
+///         } else {
+///             Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into())
+///         }
+///     }
+/// }
+///
+/// let start = Instant::now();
+/// let mut conn = BackgroundConnectMaker::new(Maker { up: false }).make_thrift_connection()?;
+/// assert!(start.elapsed() < Duration::from_millis(50));
+/// assert!(!conn.has_broken());
+///
+/// // the connect fails in the background
+/// std::thread::sleep(Duration::from_millis(100));
+/// assert!(conn.has_broken());
+/// assert!(conn.is_valid().is_err());
+///
+/// let mut conn = BackgroundConnectMaker::new(Maker { up: true }).make_thrift_connection()?;
+/// assert!(conn.wait().is_ok());
+/// assert!(!conn.has_broken());
+///
+/// // a panicking connect fails too, rather than leaving the connection waiting
+/// struct Panicking;
+///
+/// impl MakeThriftConnection for Panicking {
+///     type Error = thrift::Error;
+///     type Output = Conn;
+///     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+///         panic!("out of file descriptors")
+///     }
+/// }
+///
+/// let mut conn = BackgroundConnectMaker::new(Panicking).make_thrift_connection()?;
+/// let message = conn.wait().err().map(ToString::to_string);
+/// assert!(message.is_some_and(|message| message.contains("out of file descriptors")));
+/// assert!(conn.has_broken());
+/// # Ok::<(), thrift::Error>(())
+/// ```
+pub struct BackgroundConnectMaker<M> {
+    maker: Arc<M>,
+}
+
+impl<M> BackgroundConnectMaker<M> {
+    pub fn new(maker: M) -> Self {
+        Self {
+            maker: Arc::new(maker),
+        }
+    }
+
+    pub fn maker(&self) -> &M {
+        &self.maker
+    }
+}
+
+impl<M> Clone for BackgroundConnectMaker<M> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for BackgroundConnectMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackgroundConnectMaker")
+            .field("maker", &self.maker)
+            .finish()
+    }
+}
+
+impl<M> MakeThriftConnection for BackgroundConnectMaker<M>
+where
+    M: MakeThriftConnection + Send + Sync + 'static,
+    M::Output: Send + 'static,
+    M::Error: From<BackgroundConnectFailed> + Send + 'static,
+{
+    type Error = M::Error;
+
+    type Output = BackgroundConnection<M::Output, M::Error>;
+
+    /// Start connecting on a new thread: this never fails, the connection reports a failed connect
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let outcome = Arc::new(Outcome {
+            result: Mutex::new(None),
+            done: Condvar::new(),
+        });
+        thread::spawn({
+            let maker = self.maker.clone();
+            let outcome = outcome.clone();
+            move || {
+                // a panic would leave the connection connecting for good
+                let result =
+                    panic::catch_unwind(AssertUnwindSafe(|| maker.make_thrift_connection()))
+                        .unwrap_or_else(|panic| {
+                            let message = match panic
+                                .downcast_ref::<&str>()
+                                .map(|message| message.to_string())
+                                .or_else(|| panic.downcast_ref::<String>().cloned())
+                            {
+                                Some(message) => format!("the maker panicked: {message}"),
+                                None => "the maker panicked".to_owned(),
+                            };
+                            Err(BackgroundConnectFailed { message }.into())
+                        });
+                *outcome.result() = Some(result);
+                outcome.done.notify_all();
+            }
+        });
+        Ok(BackgroundConnection {
+            state: State::Connecting,
+            outcome,
+        })
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for BackgroundConnectMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,827 @@
+#[cfg(feature = "serde")]
+use std::io;
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, OnceLock,
+    },
+    time::Duration,
+};
+
+use crate::{
+    rng::Rng, weighted::pick_weighted, ConnectObserver, Endpoint, MakeThriftConnection,
+    ThriftConnection, TransportInfo, TransportKind,
+};
+
+#[cfg(feature = "serde")]
+use crate::Snapshot;
+
+/// The error returned by [`MakeThriftConnectionBalanced`] when its [`LoadBalancer`]
+/// picked no endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoEndpointPicked;
+
+impl std::fmt::Display for NoEndpointPicked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the load balancer picked no endpoint")
+    }
+}
+
+impl std::error::Error for NoEndpointPicked {}
+
+impl From<NoEndpointPicked> for thrift::Error {
+    fn from(e: NoEndpointPicked) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// Picks the endpoint of each connect of a [`MakeThriftConnectionBalanced`]
+///
+/// It is given the same endpoints, in the same order, on every call, so it may keep state
+/// indexed by their position. That state lives behind `&self`, as the clones of the maker
+/// share their balancer
+pub trait LoadBalancer {
+    /// The position in `endpoints` of the endpoint to connect to, `None` if there is none
+    fn pick<M>(&self, endpoints: &[Endpoint<M>]) -> Option<usize>;
+
+    /// Called after each connect, with the position of its endpoint and whether it succeeded
+    fn on_connect(&self, _endpoint: usize, _succeeded: bool) {}
+
+    /// Called when a connection is dropped (e.g. closed by the pool),
+    /// with the position of its endpoint
+    fn on_release(&self, _endpoint: usize) {}
+}
+
+/// A [`LoadBalancer`] picking each endpoint in turn, the policy of
+/// [`RoundRobinMaker`](crate::RoundRobinMaker)
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    next: AtomicUsize,
+}
+
+impl RoundRobin {
+    /// The position of the next pick, before taking it modulo the count of endpoints
+    pub(crate) fn advance(&self) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl LoadBalancer for RoundRobin {
+    fn pick<M>(&self, endpoints: &[Endpoint<M>]) -> Option<usize> {
+        self.advance().checked_rem(endpoints.len())
+    }
+}
+
+/// A [`LoadBalancer`] picking endpoints at random in proportion to their weight,
+/// the policy of [`WeightedRandomMaker`](crate::WeightedRandomMaker)
+///
+/// The `i`-th weight is that of the `i`-th endpoint, and endpoints without a weight are never
+/// picked (nor are those of weight 0). Clones share the same random number generator
+#[derive(Debug, Clone)]
+pub struct WeightedRandom {
+    weights: Vec<u32>,
+    rng: Rng,
+}
+
+impl WeightedRandom {
+    pub fn new(weights: Vec<u32>) -> Self {
+        Self {
+            weights,
+            rng: Rng::new(None),
+        }
+    }
+
+    /// Seed the random number generator used to pick endpoints (defaults to a random seed)
+    ///
+    /// This is meant for tests
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::new(Some(seed));
+        self
+    }
+
+    pub fn weights(&self) -> &[u32] {
+        &self.weights
+    }
+}
+
+impl LoadBalancer for WeightedRandom {
+    fn pick<M>(&self, endpoints: &[Endpoint<M>]) -> Option<usize> {
+        let weights = (0..endpoints.len()).map(|i| self.weights.get(i).copied().unwrap_or(0));
+        pick_weighted(&self.rng, weights)
+    }
+}
+
+/// A [`LoadBalancer`] picking the first endpoint whose last connect didn't fail,
+/// the policy of [`FailoverMaker`](crate::FailoverMaker)
+///
+/// A connect is a single attempt: the endpoint that failed it is skipped from the next connect
+/// on. Once every endpoint failed, they are all tried again from the first one. So a recovered
+/// endpoint is only used again once the ones after it fail. [`FailoverMaker`](crate::FailoverMaker)
+/// retries a failed connect with this policy, until every endpoint was tried
+///
+/// ```
+/// # use std::sync::atomic::{AtomicBool, Ordering};
+/// # use std::sync::Arc;
+/// # use thrift_pool::{Endpoint, Failover, MakeThriftConnection, MakeThriftConnectionBalanced};
+/// // a backend that can go down
+/// struct Backend {
+///     name: &'static str,
+///     up: Arc<AtomicBool>,
+/// }
+///
+/// impl MakeThriftConnection for Backend {
+///     type Error = thrift::Error;
+///     type Output = &'static str;
+///     fn make_thrift_connection(&self) -> Result<&'static str, thrift::Error> {
+///         if self.up.load(Ordering::SeqCst) {
+///             Ok(self.name)
+///         } else {
+///             Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into())
+///         }
+///     }
+/// }
+///
+/// let up = [(); 2].map(|_| Arc::new(AtomicBool::new(true)));
+/// let backend = |name, i: usize| Endpoint::new(Backend { name, up: up[i].clone() });
+/// let maker = MakeThriftConnectionBalanced::new(
+///     vec![backend("primary", 0), backend("backup", 1)],
+///     Failover::default(),
+/// );
+/// assert_eq!(*maker.make_thrift_connection()?, "primary");
+///
+/// // the failed connect isn't retried, the next one goes to the backup
+/// up[0].store(false, Ordering::SeqCst);
+/// assert!(maker.make_thrift_connection().is_err());
+/// assert_eq!(*maker.make_thrift_connection()?, "backup");
+///
+/// // which is kept until it fails too
+/// up[0].store(true, Ordering::SeqCst);
+/// assert_eq!(*maker.make_thrift_connection()?, "backup");
+/// up[1].store(false, Ordering::SeqCst);
+/// assert!(maker.make_thrift_connection().is_err());
+/// assert_eq!(*maker.make_thrift_connection()?, "primary");
+/// # Ok::<(), thrift::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct Failover {
+    /// Whether the last connect to each endpoint failed
+    failed: Mutex<Vec<bool>>,
+}
+
+impl Failover {
+    fn failed(&self, endpoints: usize) -> MutexGuard<'_, Vec<bool>> {
+        let mut failed = self.failed.lock().unwrap_or_else(|e| e.into_inner());
+        if failed.len() < endpoints {
+            failed.resize(endpoints, false);
+        }
+        failed
+    }
+}
+
+impl LoadBalancer for Failover {
+    fn pick<M>(&self, endpoints: &[Endpoint<M>]) -> Option<usize> {
+        if endpoints.is_empty() {
+            return None;
+        }
+        let mut failed = self.failed(endpoints.len());
+        let endpoint = failed[..endpoints.len()].iter().position(|failed| !failed);
+        if endpoint.is_none() {
+            failed.fill(false);
+        }
+        Some(endpoint.unwrap_or(0))
+    }
+
+    fn on_connect(&self, endpoint: usize, succeeded: bool) {
+        self.failed(endpoint + 1)[endpoint] = !succeeded;
+    }
+}
+
+/// A [`LoadBalancer`] picking the endpoint with the fewest outstanding connections
+///
+/// This spreads the load better than [`RoundRobin`] when backends have uneven capacity, as a
+/// slower one keeps its connections longer (e.g. when they are closed after some use, see
+/// [`AgingMaker`](crate::AgingMaker)). A connection is outstanding from the moment its endpoint
+/// is picked, so that concurrent connects spread too, until its connect fails or it is dropped:
+/// idle connections of the pool count. Endpoints tied for the fewest are picked in turn
+///
+/// ```
+/// # use thrift_pool::{Endpoint, LeastConnections, MakeThriftConnection, MakeThriftConnectionBalanced};
+/// # struct Maker(&'static str);
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = &'static str;
+/// #     fn make_thrift_connection(&self) -> Result<&'static str, thrift::Error> {
+/// #         Ok(self.0)
+/// #     }
+/// # }
+/// let maker = MakeThriftConnectionBalanced::new(
+///     vec![Endpoint::new(Maker("a")), Endpoint::new(Maker("b")), Endpoint::new(Maker("c"))],
+///     LeastConnections::default(),
+/// );
+/// let mut conns = (0..6)
+///     .map(|_| maker.make_thrift_connection())
+///     .collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(maker.balancer().outstanding(), [2, 2, 2]);
+///
+/// // close both connections to "b", and one to "c"
+/// conns.retain(|conn| **conn != "b");
+/// let c = conns.iter().position(|conn| **conn == "c").unwrap();
+/// conns.remove(c);
+/// assert_eq!(maker.balancer().outstanding(), [2, 0, 1]);
+///
+/// // the least loaded endpoint gets the new connections, until it catches up
+/// conns.push(maker.make_thrift_connection()?);
+/// assert_eq!(*conns[conns.len() - 1], "b");
+/// assert_eq!(maker.balancer().outstanding(), [2, 1, 1]);
+/// # Ok::<(), thrift::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct LeastConnections {
+    /// The outstanding connections to each endpoint, once one was picked
+    outstanding: OnceLock<Box<[AtomicUsize]>>,
+    next: AtomicUsize,
+}
+
+impl LeastConnections {
+    /// The outstanding connections to each endpoint (empty before the first pick)
+    pub fn outstanding(&self) -> Vec<usize> {
+        self.outstanding
+            .get()
+            .map(|outstanding| {
+                outstanding
+                    .iter()
+                    .map(|count| count.load(Ordering::Relaxed))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn release(&self, endpoint: usize) {
+        if let Some(count) = self
+            .outstanding
+            .get()
+            .and_then(|counts| counts.get(endpoint))
+        {
+            count.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl LoadBalancer for LeastConnections {
+    fn pick<M>(&self, endpoints: &[Endpoint<M>]) -> Option<usize> {
+        let counts = self
+            .outstanding
+            .get_or_init(|| endpoints.iter().map(|_| AtomicUsize::new(0)).collect());
+        let len = endpoints.len().min(counts.len());
+        let start = self.next.fetch_add(1, Ordering::Relaxed).checked_rem(len)?;
+        let endpoint = (start..len)
+            .chain(0..start)
+            .min_by_key(|i| counts[*i].load(Ordering::Relaxed))?;
+        counts[endpoint].fetch_add(1, Ordering::Relaxed);
+        Some(endpoint)
+    }
+
+    fn on_connect(&self, endpoint: usize, succeeded: bool) {
+        if !succeeded {
+            self.release(endpoint);
+        }
+    }
+
+    fn on_release(&self, endpoint: usize) {
+        self.release(endpoint);
+    }
+}
+
+/// A [`LoadBalancer`] biased toward the fastest endpoints: each one is picked at random,
+/// in inverse proportion to its latency
+///
+/// The latency of an endpoint is an exponentially weighted moving average of its samples:
+/// each sample moves it by `1 - decay` of the way (see [`LatencyAware::with_decay`]). The
+/// samples are the connect durations reported by [`LatencyAware::observer`] through an
+/// [`ObservedMaker`](crate::ObservedMaker), and any other latency passed to
+/// [`LatencyAware::record`] (e.g. that of calls). A failed connect is a sample too, so an
+/// endpoint failing fast looks fast: combine with
+/// [`CircuitBreakerMaker`](crate::CircuitBreakerMaker) to keep it out. Endpoints without
+/// a sample yet are treated as the fastest, so that they get measured.
+/// Clones share the same latencies and random number generator
+///
+/// ```
+/// # use std::time::Duration;
+/// # use thrift_pool::{
+/// #     Endpoint, LatencyAware, MakeThriftConnection, MakeThriftConnectionBalanced, ObservedMaker,
+/// # };
+/// // a backend taking `delay` to connect to
+/// struct Backend {
+///     delay: Duration,
+/// }
+///
+/// impl MakeThriftConnection for Backend {
+///     type Error = thrift::Error;
+///     type Output = ();
+///     fn make_thrift_connection(&self) -> Result<(), thrift::Error> {
+///         std::thread::sleep(self.delay);
+///         Ok(())
+///     }
+/// }
+///
+/// let balancer = LatencyAware::default().with_decay(0.5).with_seed(42);
+/// let endpoint = |i, delay| Endpoint::new(ObservedMaker::new(Backend { delay }, balancer.observer(i)));
+/// let maker = MakeThriftConnectionBalanced::new(
+///     vec![endpoint(0, Duration::from_millis(20)), endpoint(1, Duration::ZERO)],
+///     balancer.clone(),
+/// );
+///
+/// let mut picks = [0; 2];
+/// for _ in 0..40 {
+///     picks[maker.make_thrift_connection()?.endpoint()] += 1;
+/// }
+/// // the slow endpoint got a few connects, until both were measured
+/// assert!(picks[0] <= 5, "{picks:?}");
+/// let latencies = balancer.latencies();
+/// assert!(latencies[0] > latencies[1]);
+/// # Ok::<(), thrift::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct LatencyAware {
+    /// The latency of each endpoint sampled so far, in seconds
+    latencies: Arc<Mutex<Vec<Option<f64>>>>,
+    decay: f64,
+    rng: Rng,
+}
+
+impl Default for LatencyAware {
+    fn default() -> Self {
+        Self {
+            latencies: Arc::default(),
+            decay: 0.8,
+            rng: Rng::new(None),
+        }
+    }
+}
+
+impl LatencyAware {
+    /// Keep `decay` of the latency of an endpoint on each sample (defaults to 0.8): the closer
+    /// to 1, the smoother the latency, and the slower it follows changes
+    ///
+    /// As the observers copy it, set it before creating them
+    ///
+    /// # Panics
+    ///
+    /// Panics if `decay` isn't in `[0, 1)`
+    pub fn with_decay(mut self, decay: f64) -> Self {
+        assert!((0.0..1.0).contains(&decay), "decay must be in [0, 1)");
+        self.decay = decay;
+        self
+    }
+
+    /// Seed the random number generator used to pick endpoints (defaults to a random seed)
+    ///
+    /// This is meant for tests
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::new(Some(seed));
+        self
+    }
+
+    pub fn decay(&self) -> f64 {
+        self.decay
+    }
+
+    /// A [`ConnectObserver`] recording the connect durations of the `endpoint`-th endpoint,
+    /// for the [`ObservedMaker`](crate::ObservedMaker) wrapping its maker
+    pub fn observer(&self, endpoint: usize) -> LatencyObserver {
+        LatencyObserver {
+            balancer: self.clone(),
+            endpoint,
+        }
+    }
+
+    /// Move the latency of the `endpoint`-th endpoint toward `latency`
+    pub fn record(&self, endpoint: usize, latency: Duration) {
+        let mut latencies = self.samples();
+        if latencies.len() <= endpoint {
+            latencies.resize(endpoint + 1, None);
+        }
+        let sample = latency.as_secs_f64();
+        let average = &mut latencies[endpoint];
+        *average = Some(match *average {
+            Some(average) => self.decay * average + (1.0 - self.decay) * sample,
+            None => sample,
+        });
+    }
+
+    /// The latency of each endpoint, `None` for those without a sample yet
+    pub fn latencies(&self) -> Vec<Option<Duration>> {
+        self.samples()
+            .iter()
+            .map(|latency| latency.map(Duration::from_secs_f64))
+            .collect()
+    }
+
+    fn samples(&self) -> MutexGuard<'_, Vec<Option<f64>>> {
+        self.latencies.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl LoadBalancer for LatencyAware {
+    fn pick<M>(&self, endpoints: &[Endpoint<M>]) -> Option<usize> {
+        // a latency of 0 would take every pick
+        const MIN_LATENCY: f64 = 1e-6;
+        let latencies = self.samples();
+        let latency = |i: usize| latencies.get(i).copied().flatten();
+        let fastest = (0..endpoints.len())
+            .filter_map(latency)
+            .fold(f64::INFINITY, f64::min);
+        let fastest = if fastest.is_finite() { fastest } else { 1.0 };
+        let weights: Vec<f64> = (0..endpoints.len())
+            .map(|i| 1.0 / latency(i).unwrap_or(fastest).max(MIN_LATENCY))
+            .collect();
+        drop(latencies);
+
+        let mut target = self.rng.next_unit() * weights.iter().sum::<f64>();
+        for (i, weight) in weights.iter().enumerate() {
+            if target < *weight {
+                return Some(i);
+            }
+            target -= weight;
+        }
+        // only reached through rounding
+        endpoints.len().checked_sub(1)
+    }
+}
+
+/// The [`ConnectObserver`] of an endpoint of a [`LatencyAware`] balancer,
+/// see [`LatencyAware::observer`]
+#[derive(Debug, Clone)]
+pub struct LatencyObserver {
+    balancer: LatencyAware,
+    endpoint: usize,
+}
+
+impl ConnectObserver for LatencyObserver {
+    fn on_connect_duration(&self, duration: Duration) {
+        self.balancer.record(self.endpoint, duration);
+    }
+}
+
+/// A connection `C` made by [`MakeThriftConnectionBalanced`] to one of its endpoints,
+/// telling the [`LoadBalancer`] `L` when it is dropped (see [`LoadBalancer::on_release`])
+///
+/// It derefs to `C`
+pub struct BalancedConnection<C, L: LoadBalancer> {
+    conn: C,
+    endpoint: usize,
+    balancer: Arc<L>,
+}
+
+impl<C, L: LoadBalancer> BalancedConnection<C, L> {
+    /// The position of the endpoint of the connection
+    /// in [`MakeThriftConnectionBalanced::endpoints`]
+    pub fn endpoint(&self) -> usize {
+        self.endpoint
+    }
+}
+
+impl<C, L: LoadBalancer> Drop for BalancedConnection<C, L> {
+    fn drop(&mut self) {
+        self.balancer.on_release(self.endpoint);
+    }
+}
+
+impl<C: std::fmt::Debug, L: LoadBalancer> std::fmt::Debug for BalancedConnection<C, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BalancedConnection")
+            .field("conn", &self.conn)
+            .field("endpoint", &self.endpoint)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C, L: LoadBalancer> Deref for BalancedConnection<C, L> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<C, L: LoadBalancer> DerefMut for BalancedConnection<C, L> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<C: ThriftConnection, L: LoadBalancer> ThriftConnection for BalancedConnection<C, L> {
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.conn.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.conn.has_broken()
+    }
+}
+
+/// A [`MakeThriftConnection`] that creates each new connection with one of its endpoints,
+/// picked by the [`LoadBalancer`] `L`
+///
+/// This is the extension point for custom policies (e.g. routing to a canary, as below),
+/// the crate ships [`RoundRobin`], [`WeightedRandom`], [`Failover`], [`LeastConnections`]
+/// and [`LatencyAware`]. There is a single attempt per connection. If `L` picks no endpoint, the connect fails with [`NoEndpointPicked`].
+/// The connections are [`BalancedConnection`]s, which tell `L` when they are dropped.
+/// Clones share the same balancer
+///
+/// ```
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use thrift_pool::{Endpoint, LoadBalancer, MakeThriftConnection, MakeThriftConnectionBalanced};
+/// # struct Maker(&'static str);
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = &'static str;
+/// #     fn make_thrift_connection(&self) -> Result<&'static str, thrift::Error> {
+/// #         Ok(self.0)
+/// #     }
+/// # }
+/// // a policy sending every other connect to the canary, counting how often it is consulted
+/// #[derive(Default)]
+/// struct Canary {
+///     picks: AtomicUsize,
+/// }
+///
+/// impl LoadBalancer for Canary {
+///     fn pick<M>(&self, endpoints: &[Endpoint<M>]) -> Option<usize> {
+///         let canary = self.picks.fetch_add(1, Ordering::SeqCst) % 2 == 1;
+///         endpoints
+///             .iter()
+///             .position(|endpoint| (endpoint.label("role") == Some("canary")) == canary)
+///     }
+/// }
+///
+/// let maker = MakeThriftConnectionBalanced::new(
+///     vec![
+///         Endpoint::new(Maker("main")),
+///         Endpoint::new(Maker("canary")).with_label("role", "canary"),
+///     ],
+///     Canary::default(),
+/// );
+/// let conns = (0..4)
+///     .map(|_| maker.make_thrift_connection().map(|conn| *conn))
+///     .collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(conns, ["main", "canary", "main", "canary"]);
+/// assert_eq!(maker.balancer().picks.load(Ordering::SeqCst), 4);
+/// # Ok::<(), thrift::Error>(())
+/// ```
+pub struct MakeThriftConnectionBalanced<M, L> {
+    endpoints: Arc<[Endpoint<M>]>,
+    balancer: Arc<L>,
+}
+
+impl<M, L> MakeThriftConnectionBalanced<M, L> {
+    /// # Panics
+    ///
+    /// Panics if `endpoints` is empty
+    pub fn new(endpoints: Vec<Endpoint<M>>, balancer: L) -> Self {
+        assert!(
+            !endpoints.is_empty(),
+            "MakeThriftConnectionBalanced needs at least one endpoint"
+        );
+        Self {
+            endpoints: endpoints.into(),
+            balancer: Arc::new(balancer),
+        }
+    }
+
+    pub fn endpoints(&self) -> &[Endpoint<M>] {
+        &self.endpoints
+    }
+
+    pub fn balancer(&self) -> &L {
+        &self.balancer
+    }
+
+    /// The balancer, cloned first if it is shared with clones of the maker
+    pub(crate) fn balancer_mut(&mut self) -> &mut L
+    where
+        L: Clone,
+    {
+        Arc::make_mut(&mut self.balancer)
+    }
+}
+
+impl<M: MakeThriftConnection, L: LoadBalancer> MakeThriftConnectionBalanced<M, L> {
+    /// The endpoint picked by the balancer, `None` if it picked none
+    pub(crate) fn pick(&self) -> Option<usize> {
+        self.balancer
+            .pick(&self.endpoints)
+            .filter(|endpoint| *endpoint < self.endpoints.len())
+    }
+
+    /// Connect to the `endpoint`-th endpoint, telling the balancer whether it succeeded
+    ///
+    /// With [`pick`](Self::pick), this is the connect of the policy makers
+    /// (e.g. [`RoundRobinMaker`](crate::RoundRobinMaker)), whose connections aren't
+    /// [`BalancedConnection`]s as their policy ignores releases
+    pub(crate) fn connect_to(&self, endpoint: usize) -> Result<M::Output, M::Error> {
+        let result = self.endpoints[endpoint].maker.make_thrift_connection();
+        self.balancer.on_connect(endpoint, result.is_ok());
+        result
+    }
+}
+
+impl<M, L> Clone for MakeThriftConnectionBalanced<M, L> {
+    fn clone(&self) -> Self {
+        Self {
+            endpoints: self.endpoints.clone(),
+            balancer: self.balancer.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug, L: std::fmt::Debug> std::fmt::Debug
+    for MakeThriftConnectionBalanced<M, L>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MakeThriftConnectionBalanced")
+            .field("endpoints", &self.endpoints)
+            .field("balancer", &self.balancer)
+            .finish()
+    }
+}
+
+impl<M, L> MakeThriftConnection for MakeThriftConnectionBalanced<M, L>
+where
+    M: MakeThriftConnection,
+    M::Error: From<NoEndpointPicked>,
+    L: LoadBalancer,
+{
+    type Error = M::Error;
+
+    type Output = BalancedConnection<M::Output, L>;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let endpoint = self.pick().ok_or(NoEndpointPicked)?;
+        Ok(BalancedConnection {
+            conn: self.connect_to(endpoint)?,
+            endpoint,
+            balancer: self.balancer.clone(),
+        })
+    }
+}
+
+impl<M: TransportInfo, L> TransportInfo for MakeThriftConnectionBalanced<M, L> {
+    /// The kind of the first endpoint
+    fn transport_kind(&self) -> TransportKind {
+        self.endpoints[0].maker.transport_kind()
+    }
+}
+
+/// The state of a [`MakeThriftConnectionBalanced`] with the makers `M` and the balancer `L`,
+/// see [`Snapshot`]
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BalancedSnapshot<M, L> {
+    pub endpoints: Vec<Endpoint<M>>,
+    pub balancer: L,
+}
+
+#[cfg(feature = "serde")]
+impl<M: Snapshot, L: Snapshot> Snapshot for MakeThriftConnectionBalanced<M, L> {
+    type State = BalancedSnapshot<M::State, L::State>;
+
+    fn to_snapshot(&self) -> Self::State {
+        BalancedSnapshot {
+            endpoints: self
+                .endpoints
+                .iter()
+                .map(|endpoint| Endpoint {
+                    maker: endpoint.maker.to_snapshot(),
+                    labels: endpoint.labels.clone(),
+                })
+                .collect(),
+            balancer: self.balancer.to_snapshot(),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns `Err` if `state` has no endpoint, or if the state of an endpoint
+    /// or of the balancer is invalid
+    fn from_snapshot(state: Self::State) -> io::Result<Self> {
+        if state.endpoints.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the snapshot has no endpoint",
+            ));
+        }
+        let endpoints = state
+            .endpoints
+            .into_iter()
+            .map(|endpoint| {
+                Ok(Endpoint {
+                    maker: M::from_snapshot(endpoint.maker)?,
+                    labels: endpoint.labels,
+                })
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self::new(endpoints, L::from_snapshot(state.balancer)?))
+    }
+}
+
+/// The position of the next endpoint
+#[cfg(feature = "serde")]
+impl Snapshot for RoundRobin {
+    type State = usize;
+
+    fn to_snapshot(&self) -> usize {
+        self.next.load(Ordering::Relaxed)
+    }
+
+    fn from_snapshot(next: usize) -> io::Result<Self> {
+        Ok(Self {
+            next: AtomicUsize::new(next),
+        })
+    }
+}
+
+/// The weights
+#[cfg(feature = "serde")]
+impl Snapshot for WeightedRandom {
+    type State = Vec<u32>;
+
+    fn to_snapshot(&self) -> Vec<u32> {
+        self.weights.clone()
+    }
+
+    fn from_snapshot(weights: Vec<u32>) -> io::Result<Self> {
+        Ok(Self::new(weights))
+    }
+}
+
+/// Whether the last connect to each endpoint failed
+#[cfg(feature = "serde")]
+impl Snapshot for Failover {
+    type State = Vec<bool>;
+
+    fn to_snapshot(&self) -> Vec<bool> {
+        self.failed(0).clone()
+    }
+
+    fn from_snapshot(failed: Vec<bool>) -> io::Result<Self> {
+        Ok(Self {
+            failed: Mutex::new(failed),
+        })
+    }
+}
+
+/// Nothing: the connections don't outlive the process
+#[cfg(feature = "serde")]
+impl Snapshot for LeastConnections {
+    type State = ();
+
+    fn to_snapshot(&self) {}
+
+    fn from_snapshot((): ()) -> io::Result<Self> {
+        Ok(Self::default())
+    }
+}
+
+/// The state of a [`LatencyAware`] balancer, see [`Snapshot`]
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LatencyAwareSnapshot {
+    pub decay: f64,
+    /// The latency of each endpoint, `None` for those without a sample yet
+    pub latencies: Vec<Option<Duration>>,
+}
+
+#[cfg(feature = "serde")]
+impl Snapshot for LatencyAware {
+    type State = LatencyAwareSnapshot;
+
+    fn to_snapshot(&self) -> LatencyAwareSnapshot {
+        LatencyAwareSnapshot {
+            decay: self.decay,
+            latencies: self.latencies(),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns `Err` if the decay isn't in `[0, 1)`
+    fn from_snapshot(state: LatencyAwareSnapshot) -> io::Result<Self> {
+        if !(0.0..1.0).contains(&state.decay) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the decay must be in [0, 1)",
+            ));
+        }
+        let balancer = Self::default().with_decay(state.decay);
+        *balancer.samples() = state
+            .latencies
+            .into_iter()
+            .map(|latency| latency.map(|latency| latency.as_secs_f64()))
+            .collect();
+        Ok(balancer)
+    }
+}
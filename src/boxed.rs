@@ -0,0 +1,225 @@
+use std::any::Any;
+
+use crate::{MakeThriftConnection, ThriftConnection, TransportInfo, TransportKind};
+
+/// The error of a [`BoxedConnection`] or [`BoxedMaker`], whatever the error of the
+/// connection or maker
+///
+/// This wraps a `Box<dyn Error + Send + Sync>` because the box itself doesn't implement
+/// [`std::error::Error`], which `r2d2` requires
+#[derive(Debug)]
+pub struct BoxedError(Box<dyn std::error::Error + Send + Sync>);
+
+impl BoxedError {
+    pub fn new(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self(e.into())
+    }
+
+    /// The original error, if it is an `E`
+    pub fn downcast_ref<E: std::error::Error + 'static>(&self) -> Option<&E> {
+        self.0.downcast_ref()
+    }
+
+    pub fn into_inner(self) -> Box<dyn std::error::Error + Send + Sync> {
+        self.0
+    }
+}
+
+impl std::fmt::Display for BoxedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for BoxedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl From<BoxedError> for thrift::Error {
+    fn from(e: BoxedError) -> Self {
+        thrift::Error::User(e.0)
+    }
+}
+
+/// What [`BoxedConnection`] needs from a connection, object-safe and with its error erased
+trait ErasedConnection: Send {
+    fn is_valid(&mut self) -> Result<(), BoxedError>;
+
+    fn has_broken(&mut self) -> bool;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<C> ErasedConnection for C
+where
+    C: ThriftConnection + Send + 'static,
+    C::Error: std::error::Error + Send + Sync + 'static,
+{
+    fn is_valid(&mut self) -> Result<(), BoxedError> {
+        ThriftConnection::is_valid(self).map_err(BoxedError::new)
+    }
+
+    fn has_broken(&mut self) -> bool {
+        ThriftConnection::has_broken(self)
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// A [`ThriftConnection`] of any type, with its error erased to a [`BoxedError`]
+///
+/// [`ThriftConnection`] is object-safe (`dyn ThriftConnection<Error = E>` is allowed),
+/// but connections with different error types still can't be stored together:
+/// boxing them here allows it. [`BoxedConnection::downcast_mut`] gives the connection back
+///
+/// ```
+/// # use thrift_pool::{BoxedConnection, ThriftConnection};
+/// struct Healthy;
+/// impl ThriftConnection for Healthy {
+///     type Error = thrift::Error;
+///     fn is_valid(&mut self) -> Result<(), thrift::Error> {
+///         Ok(())
+///     }
+/// }
+///
+/// struct Broken;
+/// impl ThriftConnection for Broken {
+///     type Error = std::io::Error;
+///     fn is_valid(&mut self) -> Result<(), std::io::Error> {
+///         Err(std::io::Error::other("broken"))
+///     }
+/// }
+///
+/// // `ThriftConnection` itself stays object-safe
+/// let _: Box<dyn ThriftConnection<Error = thrift::Error>> = Box::new(Healthy);
+///
+/// let mut conns = vec![BoxedConnection::new(Healthy), BoxedConnection::new(Broken)];
+/// assert!(conns[0].is_valid().is_ok());
+/// let err = conns[1].is_valid().unwrap_err();
+/// assert_eq!(err.to_string(), "broken");
+/// assert!(err.downcast_ref::<std::io::Error>().is_some());
+///
+/// assert!(conns[0].downcast_mut::<Healthy>().is_some());
+/// assert!(conns[1].downcast_mut::<Healthy>().is_none());
+/// ```
+pub struct BoxedConnection(Box<dyn ErasedConnection>);
+
+impl BoxedConnection {
+    pub fn new<C>(conn: C) -> Self
+    where
+        C: ThriftConnection + Send + 'static,
+        C::Error: std::error::Error + Send + Sync + 'static,
+    {
+        Self(Box::new(conn))
+    }
+
+    /// The connection, if it is a `C`
+    pub fn downcast_mut<C: 'static>(&mut self) -> Option<&mut C> {
+        self.0.as_any_mut().downcast_mut()
+    }
+}
+
+impl std::fmt::Debug for BoxedConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxedConnection").finish_non_exhaustive()
+    }
+}
+
+impl ThriftConnection for BoxedConnection {
+    type Error = BoxedError;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.0.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.0.has_broken()
+    }
+}
+
+/// A [`MakeThriftConnection`] that boxes the connections of `M` into [`BoxedConnection`]s,
+/// erasing its error to a [`BoxedError`]
+///
+/// Pools of different kinds of connections then have the same connection type
+///
+/// ```
+/// # use thrift_pool::{BoxedConnection, BoxedMaker, MakeThriftConnection, ThriftConnection};
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// let maker = BoxedMaker::new(Maker);
+/// let mut conn: BoxedConnection = maker.make_thrift_connection()?;
+/// assert!(conn.is_valid().is_ok());
+/// assert!(conn.downcast_mut::<Conn>().is_some());
+/// # Ok::<(), thrift_pool::BoxedError>(())
+/// ```
+pub struct BoxedMaker<M> {
+    maker: M,
+}
+
+impl<M> BoxedMaker<M> {
+    pub fn new(maker: M) -> Self {
+        Self { maker }
+    }
+
+    pub fn maker(&self) -> &M {
+        &self.maker
+    }
+}
+
+impl<M: Clone> Clone for BoxedMaker<M> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for BoxedMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxedMaker")
+            .field("maker", &self.maker)
+            .finish()
+    }
+}
+
+impl<M> MakeThriftConnection for BoxedMaker<M>
+where
+    M: MakeThriftConnection,
+    M::Error: std::error::Error + Send + Sync + 'static,
+    M::Output: ThriftConnection + Send + 'static,
+    <M::Output as ThriftConnection>::Error: std::error::Error + Send + Sync + 'static,
+{
+    type Error = BoxedError;
+
+    type Output = BoxedConnection;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        self.maker
+            .make_thrift_connection()
+            .map(BoxedConnection::new)
+            .map_err(BoxedError::new)
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for BoxedMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{MakeThriftConnection, ThriftConnection, TransportInfo, TransportKind};
+
+/// The error returned by [`BudgetedMaker`] when its [`ConnectionBudget`] is exhausted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetExhausted {
+    pub max: usize,
+}
+
+impl std::fmt::Display for BudgetExhausted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the budget of {} connections is exhausted", self.max)
+    }
+}
+
+impl std::error::Error for BudgetExhausted {}
+
+impl From<BudgetExhausted> for thrift::Error {
+    fn from(e: BudgetExhausted) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// A cap on the connections open at once, shared by every [`BudgetedMaker`] given
+/// a clone of it
+#[derive(Debug, Clone)]
+pub struct ConnectionBudget {
+    max: usize,
+    outstanding: Arc<AtomicUsize>,
+}
+
+impl ConnectionBudget {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            outstanding: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// How many connections made under this budget are still open
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.load(Ordering::SeqCst)
+    }
+
+    fn take(&self) -> Result<BudgetSlot, BudgetExhausted> {
+        self.outstanding
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < self.max).then_some(n + 1)
+            })
+            .map_err(|_| BudgetExhausted { max: self.max })?;
+        Ok(BudgetSlot(self.outstanding.clone()))
+    }
+}
+
+/// A connection counted in a [`ConnectionBudget`], given back when dropped
+#[derive(Debug)]
+struct BudgetSlot(Arc<AtomicUsize>);
+
+impl Drop for BudgetSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A [`ThriftConnection`] counted in a [`ConnectionBudget`] until it is dropped.
+/// Created by [`BudgetedMaker`]
+///
+/// It derefs to `C`
+#[derive(Debug)]
+pub struct BudgetedConnection<C> {
+    conn: C,
+    _slot: BudgetSlot,
+}
+
+impl<C> BudgetedConnection<C> {
+    /// Take `C` out, giving its slot back to the budget
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+impl<C> Deref for BudgetedConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<C> DerefMut for BudgetedConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<C: ThriftConnection> ThriftConnection for BudgetedConnection<C> {
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.conn.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.conn.has_broken()
+    }
+}
+
+/// A [`MakeThriftConnection`] that fails with [`BudgetExhausted`] instead of letting
+/// the inner maker `M` open a connection past its [`ConnectionBudget`]
+///
+/// Sharing a budget between the makers of several pools caps the connections they open
+/// together, e.g. to respect the connection limit of a server they all use.
+/// Unlike [`ThriftConnectionManager::with_max_concurrent_connects`](crate::ThriftConnectionManager::with_max_concurrent_connects),
+/// which limits connects in progress, this counts connections from their creation until
+/// they are dropped
+///
+/// ```
+/// # use thrift_pool::{BudgetedMaker, ConnectionBudget, MakeThriftConnection};
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = ();
+/// #     fn make_thrift_connection(&self) -> Result<(), thrift::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// let budget = ConnectionBudget::new(3);
+/// let users = BudgetedMaker::new(Maker, budget.clone());
+/// let orders = BudgetedMaker::new(Maker, budget.clone());
+///
+/// let mut conns = vec![
+///     users.make_thrift_connection()?,
+///     orders.make_thrift_connection()?,
+///     users.make_thrift_connection()?,
+/// ];
+/// assert_eq!(budget.outstanding(), 3);
+/// assert!(orders.make_thrift_connection().is_err());
+/// assert!(users.make_thrift_connection().is_err());
+///
+/// // dropping a connection frees a slot, for any of the makers
+/// conns.pop();
+/// assert_eq!(budget.outstanding(), 2);
+/// conns.push(orders.make_thrift_connection()?);
+/// assert!(users.make_thrift_connection().is_err());
+/// # Ok::<(), thrift::Error>(())
+/// ```
+pub struct BudgetedMaker<M> {
+    maker: M,
+    budget: ConnectionBudget,
+}
+
+impl<M> BudgetedMaker<M> {
+    pub fn new(maker: M, budget: ConnectionBudget) -> Self {
+        Self { maker, budget }
+    }
+
+    pub fn budget(&self) -> &ConnectionBudget {
+        &self.budget
+    }
+
+    pub fn maker(&self) -> &M {
+        &self.maker
+    }
+}
+
+impl<M: Clone> Clone for BudgetedMaker<M> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            budget: self.budget.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for BudgetedMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BudgetedMaker")
+            .field("maker", &self.maker)
+            .field("budget", &self.budget)
+            .finish()
+    }
+}
+
+impl<M> MakeThriftConnection for BudgetedMaker<M>
+where
+    M: MakeThriftConnection,
+    M::Error: From<BudgetExhausted>,
+{
+    type Error = M::Error;
+
+    type Output = BudgetedConnection<M::Output>;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        // taken before connecting, so that concurrent connects can't overshoot,
+        // and given back if connecting fails
+        let slot = self.budget.take()?;
+        let conn = self.maker.make_thrift_connection()?;
+        Ok(BudgetedConnection { conn, _slot: slot })
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for BudgetedMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
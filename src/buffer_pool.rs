@@ -0,0 +1,227 @@
+use std::{
+    io::{self, Read, Write},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use crate::{FromRead, FromWrite};
+
+/// The size of the buffers of [`PooledBufferedReadTransport`] and
+/// [`PooledBufferedWriteTransport`], the default of thrift's buffered transports
+pub const POOLED_BUFFER_SIZE: usize = 4096;
+
+static BUFFERS: Mutex<Vec<Box<[u8]>>> = Mutex::new(Vec::new());
+static CAPACITY: AtomicUsize = AtomicUsize::new(64);
+static ALLOCATED: AtomicU64 = AtomicU64::new(0);
+static REUSED: AtomicU64 = AtomicU64::new(0);
+
+/// Set how many unused buffers the process-wide buffer pool keeps (defaults to 64)
+///
+/// Each buffer is [`POOLED_BUFFER_SIZE`] bytes: this caps the memory held by idle buffers.
+/// Buffers returned while the pool is full are freed
+pub fn set_buffer_pool_capacity(capacity: usize) {
+    CAPACITY.store(capacity, Ordering::Relaxed);
+    let mut buffers = buffers();
+    buffers.truncate(capacity);
+    buffers.shrink_to(capacity);
+}
+
+/// Counters of the process-wide buffer pool, see [`buffer_pool_stats`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferPoolStats {
+    /// Buffers that had to be allocated because the pool was empty
+    pub allocated: u64,
+    /// Buffers taken from the pool instead of being allocated
+    pub reused: u64,
+    /// Buffers currently held by the pool
+    pub idle: usize,
+}
+
+/// The counters of the process-wide buffer pool, since the start of the process
+pub fn buffer_pool_stats() -> BufferPoolStats {
+    BufferPoolStats {
+        allocated: ALLOCATED.load(Ordering::Relaxed),
+        reused: REUSED.load(Ordering::Relaxed),
+        idle: buffers().len(),
+    }
+}
+
+fn buffers() -> std::sync::MutexGuard<'static, Vec<Box<[u8]>>> {
+    BUFFERS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn take_buffer() -> Box<[u8]> {
+    if let Some(buffer) = buffers().pop() {
+        REUSED.fetch_add(1, Ordering::Relaxed);
+        return buffer;
+    }
+    ALLOCATED.fetch_add(1, Ordering::Relaxed);
+    vec![0; POOLED_BUFFER_SIZE].into_boxed_slice()
+}
+
+fn return_buffer(buffer: Box<[u8]>) {
+    let mut buffers = buffers();
+    if buffers.len() < CAPACITY.load(Ordering::Relaxed) {
+        buffers.push(buffer);
+    }
+}
+
+/// Like [`TBufferedReadTransport`](thrift::transport::TBufferedReadTransport), but its buffer
+/// is taken from a process-wide pool, and returned to it when the transport is dropped
+///
+/// With many short-lived connections this saves allocating (and freeing) two buffers per
+/// connection, see [`buffer_pool_stats`] and [`set_buffer_pool_capacity`]
+///
+/// ```
+/// # use std::io::{Read, Write};
+/// # use thrift_pool::{
+/// #     buffer_pool_stats, FromRead, FromWrite, PooledBufferedReadTransport,
+/// #     PooledBufferedWriteTransport,
+/// # };
+/// let mut write = PooledBufferedWriteTransport::from_write(Vec::new());
+/// write.write_all(b"hello")?;
+/// write.flush()?;
+/// let mut read = PooledBufferedReadTransport::from_read(&b"hello"[..]);
+/// let mut hello = String::new();
+/// read.read_to_string(&mut hello)?;
+/// assert_eq!(hello, "hello");
+///
+/// // the buffers are reused by the next connections
+/// drop((read, write));
+/// let before = buffer_pool_stats();
+/// for _ in 0..10 {
+///     let read = PooledBufferedReadTransport::from_read(std::io::empty());
+///     let write = PooledBufferedWriteTransport::from_write(std::io::sink());
+///     drop((read, write));
+/// }
+/// let after = buffer_pool_stats();
+/// assert_eq!(after.allocated, before.allocated);
+/// assert_eq!(after.reused, before.reused + 20);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct PooledBufferedReadTransport<R> {
+    read: R,
+    buffer: Option<Box<[u8]>>,
+    pos: usize,
+    len: usize,
+}
+
+impl<R> PooledBufferedReadTransport<R> {
+    pub fn new(read: R) -> Self {
+        Self {
+            read,
+            buffer: Some(take_buffer()),
+            pos: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<R> Drop for PooledBufferedReadTransport<R> {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            return_buffer(buffer);
+        }
+    }
+}
+
+impl<R: Read> Read for PooledBufferedReadTransport<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let buffer = self
+            .buffer
+            .as_mut()
+            .expect("the buffer is only taken on drop");
+        if self.pos == self.len {
+            // nothing buffered: large reads skip the buffer
+            if buf.len() >= buffer.len() {
+                return self.read.read(buf);
+            }
+            self.len = self.read.read(buffer)?;
+            self.pos = 0;
+        }
+        let n = buf.len().min(self.len - self.pos);
+        buf[..n].copy_from_slice(&buffer[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl<R: Read> FromRead for PooledBufferedReadTransport<R> {
+    type Read = R;
+    fn from_read(read: R) -> Self {
+        Self::new(read)
+    }
+}
+
+/// Like [`TBufferedWriteTransport`](thrift::transport::TBufferedWriteTransport), but its buffer
+/// is taken from a process-wide pool, see [`PooledBufferedReadTransport`]
+///
+/// As with thrift's transport, buffered bytes are only sent on [`Write::flush`]
+/// (or when the buffer is full), not on drop
+#[derive(Debug)]
+pub struct PooledBufferedWriteTransport<W> {
+    write: W,
+    buffer: Option<Box<[u8]>>,
+    len: usize,
+}
+
+impl<W> PooledBufferedWriteTransport<W> {
+    pub fn new(write: W) -> Self {
+        Self {
+            write,
+            buffer: Some(take_buffer()),
+            len: 0,
+        }
+    }
+}
+
+impl<W> Drop for PooledBufferedWriteTransport<W> {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            return_buffer(buffer);
+        }
+    }
+}
+
+impl<W: Write> PooledBufferedWriteTransport<W> {
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        let buffer = self
+            .buffer
+            .as_ref()
+            .expect("the buffer is only taken on drop");
+        self.write.write_all(&buffer[..self.len])?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for PooledBufferedWriteTransport<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.len == POOLED_BUFFER_SIZE {
+            self.flush_buffer()?;
+        }
+        let buffer = self
+            .buffer
+            .as_mut()
+            .expect("the buffer is only taken on drop");
+        let n = buf.len().min(buffer.len() - self.len);
+        buffer[self.len..self.len + n].copy_from_slice(&buf[..n]);
+        self.len += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buffer()?;
+        self.write.flush()
+    }
+}
+
+impl<W: Write> FromWrite for PooledBufferedWriteTransport<W> {
+    type Write = W;
+    fn from_write(write: W) -> Self {
+        Self::new(write)
+    }
+}
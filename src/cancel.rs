@@ -0,0 +1,160 @@
+use std::{
+    io,
+    net::{TcpStream, ToSocketAddrs},
+};
+
+use thrift::transport::{ReadHalf, TTcpChannel, WriteHalf};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    FromProtocolWithSocket, FromRead, FromReadTransport, FromWrite, FromWriteTransport,
+    MakeThriftConnectionFromAddrs,
+};
+
+fn cancelled() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Interrupted,
+        "connection establishment was cancelled",
+    )
+}
+
+impl<T, S: ToSocketAddrs> MakeThriftConnectionFromAddrs<T, S> {
+    /// Like [`MakeThriftConnectionFromAddrs::open_stream`], but connecting asynchronously,
+    /// and giving up as soon as `token` is cancelled
+    ///
+    /// A socket that is still connecting when `token` is cancelled is closed.
+    /// Address resolution is synchronous, and isn't cancelled.
+    /// This must be called from a tokio runtime
+    ///
+    /// # Errors
+    ///
+    /// Returns [`io::ErrorKind::Interrupted`] if `token` is cancelled before the connection
+    /// is established, or `Err` if it can't be established or the socket options can't be applied
+    pub async fn open_stream_cancellable(
+        &self,
+        token: &CancellationToken,
+    ) -> thrift::Result<TcpStream> {
+        let mut last_err = None;
+        for addr in self.addrs.to_socket_addrs()? {
+            let connect = tokio::net::TcpStream::connect(addr);
+            let res = match self.connect_timeout {
+                None => token.run_until_cancelled(connect).await,
+                Some(timeout) => token
+                    .run_until_cancelled(tokio::time::timeout(timeout, connect))
+                    .await
+                    .map(|res| {
+                        res.unwrap_or_else(|_| Err(io::Error::from(io::ErrorKind::TimedOut)))
+                    }),
+            };
+            match res {
+                // dropping the connect future closed its socket
+                None => return Err(cancelled().into()),
+                Some(Ok(stream)) => {
+                    let stream = stream.into_std()?;
+                    self.configure_stream(&stream)?;
+                    return Ok(stream);
+                }
+                Some(Err(e)) => last_err = Some(e),
+            }
+        }
+        Err(last_err
+            .unwrap_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "could not resolve to any addresses",
+                )
+            })
+            .into())
+    }
+}
+
+impl<
+        S: ToSocketAddrs + Clone,
+        RL: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RT: FromRead<Read = RL>,
+        IP: FromReadTransport<ReadTransport = RT>,
+        WL: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WT: FromWrite<Write = WL>,
+        OP: FromWriteTransport<WriteTransport = WT>,
+        T: FromProtocolWithSocket<InputProtocol = IP, OutputProtocol = OP>,
+    > MakeThriftConnectionFromAddrs<T, S>
+{
+    /// Like [`MakeThriftConnection::make_thrift_connection`](crate::MakeThriftConnection::make_thrift_connection),
+    /// but connecting asynchronously, and giving up as soon as `token` is cancelled,
+    /// see [`MakeThriftConnectionFromAddrs::open_stream_cancellable`]
+    ///
+    /// ```
+    /// # use std::net::{SocketAddr, TcpStream};
+    /// # use std::time::Duration;
+    /// # use thrift::protocol::{
+    /// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+    /// # };
+    /// # use thrift::transport::{
+    /// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+    /// # };
+    /// # use thrift_pool::{FromProtocol, MakeThriftConnectionFromAddrs};
+    /// # use tokio_util::sync::CancellationToken;
+    /// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+    /// #     i_prot: Ip,
+    /// #     o_prot: Op,
+    /// # }
+    /// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+    /// #     type InputProtocol = Ip;
+    /// #     type OutputProtocol = Op;
+    /// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+    /// #         MyThriftClient { i_prot, o_prot }
+    /// #     }
+    /// # }
+    /// # fn open_fds() -> usize {
+    /// #     std::fs::read_dir("/proc/self/fd").map_or(0, |fds| fds.count())
+    /// # }
+    /// type Client = MyThriftClient<
+    ///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+    ///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+    /// >;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// // a server whose accept queue is full, so new connects hang
+    /// let socket = tokio::net::TcpSocket::new_v4()?;
+    /// socket.bind("127.0.0.1:0".parse()?)?;
+    /// let listener = socket.listen(0)?;
+    /// let addr = listener.local_addr()?;
+    /// let _queued = (0..4)
+    ///     .filter_map(|_| TcpStream::connect_timeout(&addr, Duration::from_millis(50)).ok())
+    ///     .collect::<Vec<_>>();
+    ///
+    /// let maker = MakeThriftConnectionFromAddrs::<Client, _>::new(addr);
+    /// let token = CancellationToken::new();
+    /// let before = open_fds();
+    /// let cancel = token.clone();
+    /// tokio::spawn(async move {
+    ///     tokio::time::sleep(Duration::from_millis(50)).await;
+    ///     cancel.cancel();
+    /// });
+    /// let err = match maker.make_thrift_connection_cancellable(&token).await {
+    ///     Err(thrift::Error::Transport(e)) => e,
+    ///     _ => panic!("expected the connect to be cancelled"),
+    /// };
+    /// assert_eq!(err.message, "connection establishment was cancelled");
+    /// // the half-open socket was closed
+    /// assert_eq!(open_fds(), before);
+    ///
+    /// // an already cancelled token doesn't even connect
+    /// assert!(maker.make_thrift_connection_cancellable(&token).await.is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See [`MakeThriftConnectionFromAddrs::open_stream_cancellable`] and
+    /// [`MakeThriftConnectionFromAddrs::connection_from_stream`]
+    pub async fn make_thrift_connection_cancellable(
+        &self,
+        token: &CancellationToken,
+    ) -> thrift::Result<T> {
+        let stream = self.open_stream_cancellable(token).await?;
+        self.connection_from_stream(stream)
+    }
+}
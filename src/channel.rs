@@ -0,0 +1,141 @@
+use std::{
+    marker::PhantomData,
+    sync::{Mutex, MutexGuard},
+};
+
+use thrift::transport::{ReadHalf, TIoChannel, TTcpChannel, WriteHalf};
+
+use crate::{
+    FromProtocol, FromRead, FromReadTransport, FromWrite, FromWriteTransport, MakeThriftConnection,
+    TransportInfo, TransportKind,
+};
+
+/// The error returned by [`MakeThriftConnectionFromChannel`] once its channel was used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelConsumed;
+
+impl std::fmt::Display for ChannelConsumed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the channel was already used to make a connection")
+    }
+}
+
+impl std::error::Error for ChannelConsumed {}
+
+impl From<ChannelConsumed> for thrift::Error {
+    fn from(e: ChannelConsumed) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// A [`MakeThriftConnection`] building a single client of type `T` over a [`TTcpChannel`]
+/// opened by the caller, with the same transports and protocols as
+/// [`MakeThriftConnectionFromAddrs`](crate::MakeThriftConnectionFromAddrs)
+///
+/// This is for one-shot clients and tests. Since it only has one channel, it only makes
+/// one connection: the following calls fail with [`ChannelConsumed`], so it isn't suitable
+/// for pools (which reconnect whenever a connection breaks)
+///
+/// ```
+/// # use std::net::TcpListener;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{
+/// #     ChannelConsumed, FromProtocol, MakeThriftConnection, MakeThriftConnectionFromChannel,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let mut channel = TTcpChannel::new();
+/// channel.open(listener.local_addr()?)?;
+///
+/// let maker = MakeThriftConnectionFromChannel::<Client>::from_existing(channel);
+/// let mut client = maker.make_thrift_connection()?;
+/// let (mut server, _) = listener.accept()?;
+///
+/// client.o_prot.write_i32(42)?;
+/// client.o_prot.flush()?;
+/// let mut server_prot = TBinaryInputProtocol::new(&mut server, true);
+/// assert_eq!(server_prot.read_i32()?, 42);
+///
+/// let err = maker.make_thrift_connection().err().unwrap();
+/// assert!(matches!(err, thrift::Error::User(e) if e.is::<ChannelConsumed>()));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MakeThriftConnectionFromChannel<T> {
+    channel: Mutex<Option<TTcpChannel>>,
+    conn: PhantomData<T>,
+}
+
+impl<T> MakeThriftConnectionFromChannel<T> {
+    pub fn from_existing(channel: TTcpChannel) -> Self {
+        Self {
+            channel: Mutex::new(Some(channel)),
+            conn: PhantomData,
+        }
+    }
+
+    fn channel(&self) -> MutexGuard<'_, Option<TTcpChannel>> {
+        self.channel.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether the channel is still there to make the connection
+    pub fn is_consumed(&self) -> bool {
+        self.channel().is_none()
+    }
+}
+
+impl<
+        RL: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RT: FromRead<Read = RL>,
+        IP: FromReadTransport<ReadTransport = RT>,
+        WL: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WT: FromWrite<Write = WL>,
+        OP: FromWriteTransport<WriteTransport = WT>,
+        T: FromProtocol<InputProtocol = IP, OutputProtocol = OP>,
+    > MakeThriftConnection for MakeThriftConnectionFromChannel<T>
+{
+    type Error = thrift::Error;
+
+    type Output = T;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let channel = self.channel().take().ok_or(ChannelConsumed)?;
+        let (read, write) = channel.split()?;
+
+        let read_transport = RT::from_read(RL::from_read(read));
+        let input_protocol = IP::from_read_transport(read_transport);
+
+        let write_transport = WT::from_write(WL::from_write(write));
+        let output_protocol = OP::from_write_transport(write_transport);
+
+        Ok(T::from_protocol(input_protocol, output_protocol))
+    }
+}
+
+impl<T> TransportInfo for MakeThriftConnectionFromChannel<T> {
+    fn transport_kind(&self) -> TransportKind {
+        TransportKind::Tcp
+    }
+}
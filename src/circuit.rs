@@ -0,0 +1,284 @@
+use std::{
+    mem,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::{MakeThriftConnection, TransportInfo, TransportKind};
+
+/// The error returned by [`CircuitBreakerMaker`] when its circuit is open
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitOpen;
+
+impl std::fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the circuit is open: the backend failed too many connects")
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+impl From<CircuitOpen> for thrift::Error {
+    fn from(e: CircuitOpen) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// The state of the circuit of a [`CircuitBreakerMaker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CircuitState {
+    /// Connects go through
+    Closed,
+    /// Connects fail with [`CircuitOpen`] until the cooldown has passed
+    Open,
+    /// The cooldown has passed, and a single connect probes the backend:
+    /// the circuit closes if it succeeds, and opens again if it fails
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum Breaker {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// A probe is running
+    HalfOpen,
+}
+
+/// A [`MakeThriftConnection`] that stops calling the inner maker `M` after
+/// `failure_threshold` consecutive failed connects, failing with [`CircuitOpen`] instead
+///
+/// This spares a struggling backend the connects of a whole pool (and the callers the time
+/// they take to fail). Once `cooldown` has passed, half-open, the next connect probes the
+/// backend while the others keep failing: if it succeeds the circuit closes again, otherwise
+/// it opens for another `cooldown`. A connect that panics counts as failed.
+/// Clones share the same circuit
+///
+/// ```
+/// # use std::sync::atomic::{AtomicBool, Ordering};
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// # use thrift_pool::{CircuitBreakerMaker, CircuitOpen, CircuitState, MakeThriftConnection};
+/// // a backend that is down while `down` is set
+/// struct Maker(Arc<AtomicBool>);
+///
+/// impl MakeThriftConnection for Maker {
+///     type Error = thrift::Error;
+///     type Output = ();
+///     fn make_thrift_connection(&self) -> Result<(), thrift::Error> {
+///         if self.0.load(Ordering::SeqCst) {
+///             Err(thrift::Error::from(std::io::Error::from(std::io::ErrorKind::ConnectionRefused)))
+///         } else {
+///             Ok(())
+///         }
+///     }
+/// }
+///
+/// let is_circuit_open = |e: &thrift::Error| {
+///     matches!(e, thrift::Error::User(e) if e.downcast_ref::<CircuitOpen>().is_some())
+/// };
+///
+/// let down = Arc::new(AtomicBool::new(true));
+/// let maker = CircuitBreakerMaker::new(Maker(down.clone()), 2, Duration::from_millis(100));
+///
+/// // closed: failures reach the backend, until the threshold opens the circuit
+/// assert!(!is_circuit_open(&maker.make_thrift_connection().unwrap_err()));
+/// assert_eq!(maker.state(), CircuitState::Closed);
+/// assert!(!is_circuit_open(&maker.make_thrift_connection().unwrap_err()));
+/// assert_eq!(maker.state(), CircuitState::Open);
+///
+/// // open: the backend isn't called
+/// down.store(false, Ordering::SeqCst);
+/// assert!(is_circuit_open(&maker.make_thrift_connection().unwrap_err()));
+///
+/// // half-open after the cooldown: a failed probe opens the circuit again
+/// std::thread::sleep(Duration::from_millis(150));
+/// assert_eq!(maker.state(), CircuitState::HalfOpen);
+/// down.store(true, Ordering::SeqCst);
+/// assert!(!is_circuit_open(&maker.make_thrift_connection().unwrap_err()));
+/// assert_eq!(maker.state(), CircuitState::Open);
+///
+/// // and a successful one closes it
+/// std::thread::sleep(Duration::from_millis(150));
+/// down.store(false, Ordering::SeqCst);
+/// assert!(maker.make_thrift_connection().is_ok());
+/// assert_eq!(maker.state(), CircuitState::Closed);
+/// ```
+///
+/// ```
+/// # use std::panic::{catch_unwind, AssertUnwindSafe};
+/// # use std::time::Duration;
+/// # use thrift_pool::{CircuitBreakerMaker, CircuitState, MakeThriftConnection};
+/// struct Panicking;
+///
+/// impl MakeThriftConnection for Panicking {
+///     type Error = thrift::Error;
+///     type Output = ();
+///     fn make_thrift_connection(&self) -> Result<(), thrift::Error> {
+///         panic!("the backend sent garbage")
+///     }
+/// }
+///
+/// let maker = CircuitBreakerMaker::new(Panicking, 1, Duration::from_millis(100));
+/// let connect = || catch_unwind(AssertUnwindSafe(|| maker.make_thrift_connection()));
+/// assert!(connect().is_err());
+/// assert_eq!(maker.state(), CircuitState::Open);
+///
+/// // a panicking probe opens the circuit again, rather than leaving it half-open
+/// std::thread::sleep(Duration::from_millis(150));
+/// assert!(connect().is_err());
+/// assert_eq!(maker.state(), CircuitState::Open);
+/// ```
+pub struct CircuitBreakerMaker<M> {
+    maker: M,
+    failure_threshold: u32,
+    cooldown: Duration,
+    breaker: Arc<Mutex<Breaker>>,
+}
+
+impl<M> CircuitBreakerMaker<M> {
+    /// Open the circuit for `cooldown` after `failure_threshold` consecutive failed connects
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure_threshold` is 0
+    pub fn new(maker: M, failure_threshold: u32, cooldown: Duration) -> Self {
+        assert!(
+            failure_threshold > 0,
+            "failure_threshold must be at least 1"
+        );
+        Self {
+            maker,
+            failure_threshold,
+            cooldown,
+            breaker: Arc::new(Mutex::new(Breaker::Closed { failures: 0 })),
+        }
+    }
+
+    pub fn failure_threshold(&self) -> u32 {
+        self.failure_threshold
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+
+    /// The current state of the circuit
+    pub fn state(&self) -> CircuitState {
+        match *self.breaker() {
+            Breaker::Closed { .. } => CircuitState::Closed,
+            Breaker::Open { until } if Instant::now() < until => CircuitState::Open,
+            Breaker::Open { .. } | Breaker::HalfOpen => CircuitState::HalfOpen,
+        }
+    }
+
+    pub fn maker(&self) -> &M {
+        &self.maker
+    }
+
+    fn breaker(&self) -> MutexGuard<'_, Breaker> {
+        self.breaker.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether a connect may call the inner maker, and if so whether it is the probe
+    fn admit(&self) -> Result<bool, CircuitOpen> {
+        let mut breaker = self.breaker();
+        match *breaker {
+            Breaker::Closed { .. } => Ok(false),
+            Breaker::Open { until } if Instant::now() >= until => {
+                *breaker = Breaker::HalfOpen;
+                Ok(true)
+            }
+            Breaker::Open { .. } | Breaker::HalfOpen => Err(CircuitOpen),
+        }
+    }
+
+    fn record(&self, success: bool, probe: bool) {
+        let mut breaker = self.breaker();
+        let open = Breaker::Open {
+            until: Instant::now() + self.cooldown,
+        };
+        *breaker = match *breaker {
+            _ if success => Breaker::Closed { failures: 0 },
+            _ if probe => open,
+            Breaker::Closed { failures } if failures + 1 >= self.failure_threshold => open,
+            Breaker::Closed { failures } => Breaker::Closed {
+                failures: failures + 1,
+            },
+            // a connect started before the circuit opened doesn't extend the cooldown
+            Breaker::Open { until } => Breaker::Open { until },
+            Breaker::HalfOpen => Breaker::HalfOpen,
+        };
+    }
+}
+
+/// A connect admitted by the circuit, recorded as failed if it is dropped unrecorded
+/// (when the inner maker panics)
+struct Attempt<'a, M> {
+    maker: &'a CircuitBreakerMaker<M>,
+    probe: bool,
+}
+
+impl<M> Attempt<'_, M> {
+    fn record(self, success: bool) {
+        self.maker.record(success, self.probe);
+        mem::forget(self);
+    }
+}
+
+impl<M> Drop for Attempt<'_, M> {
+    fn drop(&mut self) {
+        self.maker.record(false, self.probe);
+    }
+}
+
+impl<M: Clone> Clone for CircuitBreakerMaker<M> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            failure_threshold: self.failure_threshold,
+            cooldown: self.cooldown,
+            breaker: self.breaker.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for CircuitBreakerMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CircuitBreakerMaker")
+            .field("maker", &self.maker)
+            .field("failure_threshold", &self.failure_threshold)
+            .field("cooldown", &self.cooldown)
+            .field("state", &self.state())
+            .finish()
+    }
+}
+
+impl<M> MakeThriftConnection for CircuitBreakerMaker<M>
+where
+    M: MakeThriftConnection,
+    M::Error: From<CircuitOpen>,
+{
+    type Error = M::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let attempt = Attempt {
+            maker: self,
+            probe: self.admit()?,
+        };
+        let result = self.maker.make_thrift_connection();
+        attempt.record(result.is_ok());
+        result
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for CircuitBreakerMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    io,
+    ops::{Deref, DerefMut},
+};
+
+use thrift::{TransportError, TransportErrorKind};
+
+use crate::{FromProtocol, ProtocolAccess, ThriftConnection};
+
+/// How the peer ended a connection, see [`CloseAwareConnection::broken_reason`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BrokenReason {
+    /// The peer reset the connection (e.g. its process crashed)
+    Reset,
+    /// The peer closed the connection gracefully (e.g. it shut down, or closed an idle connection)
+    Closed,
+}
+
+impl BrokenReason {
+    /// Classify `e`, returning `None` if it doesn't say the peer ended the connection
+    ///
+    /// Thrift turns [`io::Error`]s into [`TransportError`]s keeping only their message,
+    /// so the kind is recovered from the OS error code at its end
+    pub fn classify(e: &thrift::Error) -> Option<Self> {
+        let thrift::Error::Transport(TransportError { kind, message }) = e else {
+            return None;
+        };
+        if *kind == TransportErrorKind::EndOfFile {
+            return Some(Self::Closed);
+        }
+        match os_error_kind(message)? {
+            io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted => Some(Self::Reset),
+            io::ErrorKind::UnexpectedEof => Some(Self::Closed),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for BrokenReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Reset => "the connection was reset by the peer",
+            Self::Closed => "the connection was closed by the peer",
+        })
+    }
+}
+
+/// The kind of the OS error displayed at the end of `message` (`... (os error 104)`)
+pub(crate) fn os_error_kind(message: &str) -> Option<io::ErrorKind> {
+    let (_, code) = message.rsplit_once("(os error ")?;
+    let code = code.strip_suffix(')')?.parse().ok()?;
+    Some(io::Error::from_raw_os_error(code).kind())
+}
+
+/// A [`ThriftConnection`] that remembers whether a call made through
+/// [`CloseAwareConnection::call`] found the connection reset or closed by the peer,
+/// see [`CloseAwareConnection::broken_reason`]
+///
+/// Such a connection reports [`ThriftConnection::has_broken`], so the pool drops it.
+/// Telling the two apart allows treating them differently, e.g. a reset as a crashed backend
+/// to retry elsewhere, and a close as an expected idle timeout of the server
+///
+/// Since it implements [`FromProtocol`], the wrapper can be used directly
+/// as the connection type of [`MakeThriftConnectionFromAddrs`](crate::MakeThriftConnectionFromAddrs).
+/// It derefs to `C`
+///
+/// ```
+/// # use std::net::TcpListener;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{
+/// #     BrokenReason, CloseAwareConnection, FromProtocol, MakeThriftConnection,
+/// #     MakeThriftConnectionFromAddrs, ThriftConnection,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ThriftConnection for MyThriftClient<Ip, Op> {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// type Client = CloseAwareConnection<
+///     MyThriftClient<
+///         TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///         TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+///     >,
+/// >;
+///
+/// fn read_reply(conn: &mut Client) -> thrift::Result<i32> {
+///     conn.call(|client| client.i_prot.read_i32())
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let maker = MakeThriftConnectionFromAddrs::<Client, _>::new(listener.local_addr()?);
+///
+/// // closing the socket gracefully
+/// let mut conn = maker.make_thrift_connection()?;
+/// let (server, _) = listener.accept()?;
+/// drop(server);
+/// assert!(read_reply(&mut conn).is_err());
+/// assert_eq!(conn.broken_reason(), Some(BrokenReason::Closed));
+/// assert!(conn.has_broken());
+///
+/// // closing the socket with a request unread resets it
+/// let mut conn = maker.make_thrift_connection()?;
+/// let (server, _) = listener.accept()?;
+/// conn.o_prot.write_i32(1)?;
+/// conn.o_prot.flush()?;
+/// server.peek(&mut [0])?;
+/// drop(server);
+/// assert!(read_reply(&mut conn).is_err());
+/// assert_eq!(conn.broken_reason(), Some(BrokenReason::Reset));
+/// assert!(conn.has_broken());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CloseAwareConnection<C> {
+    conn: C,
+    broken_reason: Option<BrokenReason>,
+}
+
+impl<C> CloseAwareConnection<C> {
+    pub fn new(conn: C) -> Self {
+        Self {
+            conn,
+            broken_reason: None,
+        }
+    }
+
+    /// Run the call `f`, remembering if it fails because the peer reset or closed
+    /// the connection (see [`BrokenReason::classify`])
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `f`
+    pub fn call<F, R>(&mut self, f: F) -> thrift::Result<R>
+    where
+        F: FnOnce(&mut C) -> thrift::Result<R>,
+    {
+        let res = f(&mut self.conn);
+        if let Err(e) = &res {
+            self.broken_reason = self.broken_reason.or(BrokenReason::classify(e));
+        }
+        res
+    }
+
+    /// How the peer ended the connection, if a call found out
+    pub fn broken_reason(&self) -> Option<BrokenReason> {
+        self.broken_reason
+    }
+
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+impl<C> Deref for CloseAwareConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<C> DerefMut for CloseAwareConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<C: FromProtocol> FromProtocol for CloseAwareConnection<C> {
+    type InputProtocol = C::InputProtocol;
+
+    type OutputProtocol = C::OutputProtocol;
+
+    fn from_protocol(
+        input_protocol: Self::InputProtocol,
+        output_protocol: Self::OutputProtocol,
+    ) -> Self {
+        Self::new(C::from_protocol(input_protocol, output_protocol))
+    }
+}
+
+impl<C: ProtocolAccess> ProtocolAccess for CloseAwareConnection<C> {
+    fn input_protocol_mut(&mut self) -> &mut Self::InputProtocol {
+        self.conn.input_protocol_mut()
+    }
+
+    fn output_protocol_mut(&mut self) -> &mut Self::OutputProtocol {
+        self.conn.output_protocol_mut()
+    }
+}
+
+impl<C: ThriftConnection> ThriftConnection for CloseAwareConnection<C> {
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.conn.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.broken_reason.is_some() || self.conn.has_broken()
+    }
+}
@@ -0,0 +1,314 @@
+use std::{
+    cell::{Cell, RefCell},
+    io::{self, Write},
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use thrift::protocol::TOutputProtocol;
+
+use crate::{FromProtocol, FromWrite, ProtocolAccess, ThriftConnection};
+
+/// The id of the next [`CoalescingWrite`]
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The ids of the [`CoalescingWrite`]s of the connections in
+    /// [`CoalescingConnection::coalesce`] on this thread
+    static COALESCING: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+    /// Set while `coalesce` flushes its connection, for its layer to tell its id
+    static CLAIMING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Defers the flushes of the layer of a connection while alive, even if the calls panic
+struct Scope {
+    /// The ids coalescing before this scope
+    outer: usize,
+}
+
+impl Scope {
+    /// Find the layer of `conn` by flushing it, and defer its flushes
+    fn enter<C: ProtocolAccess>(conn: &mut C) -> thrift::Result<Self> {
+        let scope = Self {
+            outer: COALESCING.with(|ids| ids.borrow().len()),
+        };
+        CLAIMING.with(|claiming| claiming.set(true));
+        let flushed = conn.output_protocol_mut().flush();
+        // in case the client type has no layer
+        CLAIMING.with(|claiming| claiming.set(false));
+        flushed?;
+        Ok(scope)
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        COALESCING.with(|ids| ids.borrow_mut().truncate(self.outer));
+    }
+}
+
+/// A [`Write`] layer coalescing the writes of several calls into one,
+/// within [`CoalescingConnection::coalesce`]
+///
+/// Thrift flushes the write transport at the end of every call, which writes each request to
+/// the socket on its own: many tiny calls (e.g. oneway ones) make as many small writes.
+/// This layer buffers the bytes written to it, and while `coalesce` runs for its connection it
+/// defers the flushes, until `coalesce` returns or a flush comes `MAX_DELAY_MS` after the first
+/// deferred one. Only then are the buffered bytes written, at once. Otherwise, every flush
+/// writes them right away. Like the other layers it is created by the maker without
+/// any state, so the delay is a parameter of the type
+///
+/// It goes between the [`WriteHalf`](thrift::transport::WriteHalf) and the write transport
+/// in the client type, and the client must be wrapped in a [`CoalescingConnection`]
+/// (see there for an example)
+#[derive(Debug)]
+pub struct CoalescingWrite<W, const MAX_DELAY_MS: u64 = 1> {
+    /// Tells the layer of the connection in `coalesce` apart from the others
+    id: u64,
+    write: W,
+    buf: Vec<u8>,
+    /// When the first flush was deferred, if any is pending
+    deferred_since: Option<Instant>,
+}
+
+impl<W, const MAX_DELAY_MS: u64> CoalescingWrite<W, MAX_DELAY_MS> {
+    pub fn new(write: W) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            write,
+            buf: Vec::new(),
+            deferred_since: None,
+        }
+    }
+
+    /// Unwrap the inner [`Write`], dropping any bytes not flushed
+    pub fn into_inner(self) -> W {
+        self.write
+    }
+}
+
+impl<W: Write, const MAX_DELAY_MS: u64> Write for CoalescingWrite<W, MAX_DELAY_MS> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if CLAIMING.with(|claiming| claiming.replace(false)) {
+            COALESCING.with(|ids| ids.borrow_mut().push(self.id));
+        } else if COALESCING.with(|ids| ids.borrow().contains(&self.id)) {
+            let deferred_since = *self.deferred_since.get_or_insert_with(Instant::now);
+            if deferred_since.elapsed() < Duration::from_millis(MAX_DELAY_MS) {
+                return Ok(());
+            }
+        }
+        self.deferred_since = None;
+        if !self.buf.is_empty() {
+            let written = self.write.write_all(&self.buf);
+            // the bytes of a failed write can't be told apart from the next ones
+            self.buf.clear();
+            written?;
+        }
+        self.write.flush()
+    }
+}
+
+impl<W: Write, const MAX_DELAY_MS: u64> FromWrite for CoalescingWrite<W, MAX_DELAY_MS> {
+    type Write = W;
+    fn from_write(write: W) -> Self {
+        Self::new(write)
+    }
+}
+
+/// A [`ThriftConnection`] coalescing the writes of the calls made through
+/// [`CoalescingConnection::coalesce`]: they reach the socket together, in fewer writes
+///
+/// The crate can't tell where the calls of a batch end, so `coalesce` is the boundary:
+/// the writes are deferred by the [`CoalescingWrite`] layer of the client type while it runs,
+/// and flushed when it returns. Only the writes of this connection are deferred: those of the
+/// other connections used from within `coalesce` go out as usual. A call waiting for its reply
+/// must not be made within `coalesce`, unless the delay of the layer lets its request go
+/// meanwhile: otherwise the request would only be sent after the reply is received
+///
+/// Since it implements [`FromProtocol`], the wrapper can be used directly
+/// as the connection type of [`MakeThriftConnectionFromAddrs`](crate::MakeThriftConnectionFromAddrs).
+/// It derefs to `C`
+///
+/// ```
+/// # use std::io::{self, Write};
+/// # use std::sync::{Arc, Mutex};
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::TBufferedWriteTransport;
+/// # use thrift_pool::{CoalescingConnection, CoalescingWrite, FromProtocol, ProtocolAccess, ThriftConnection};
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ProtocolAccess for MyThriftClient<Ip, Op> {
+/// #     fn input_protocol_mut(&mut self) -> &mut Ip {
+/// #         &mut self.i_prot
+/// #     }
+/// #     fn output_protocol_mut(&mut self) -> &mut Op {
+/// #         &mut self.o_prot
+/// #     }
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ThriftConnection for MyThriftClient<Ip, Op> {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// // a socket recording each write made to it
+/// #[derive(Clone, Default)]
+/// struct Socket(Arc<Mutex<Vec<usize>>>);
+///
+/// impl Write for Socket {
+///     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+///         self.0.lock().unwrap().push(buf.len());
+///         Ok(buf.len())
+///     }
+///     fn flush(&mut self) -> io::Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// // a long delay, so that only the boundary flushes
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<io::Empty>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<CoalescingWrite<Socket, 60_000>>>,
+/// >;
+///
+/// let connect = |socket: &Socket| {
+///     CoalescingConnection::<Client>::from_protocol(
+///         TBinaryInputProtocol::new(io::empty(), true),
+///         TBinaryOutputProtocol::new(
+///             TBufferedWriteTransport::new(CoalescingWrite::new(socket.clone())),
+///             true,
+///         ),
+///     )
+/// };
+/// let socket = Socket::default();
+/// let mut conn = connect(&socket);
+/// let call = |client: &mut Client| {
+///     client.o_prot.write_i32(42)?;
+///     client.o_prot.flush()
+/// };
+///
+/// // a write per call
+/// call(&mut conn)?;
+/// call(&mut conn)?;
+/// assert_eq!(*socket.0.lock().unwrap(), [4, 4]);
+///
+/// // a single write for all of them
+/// socket.0.lock().unwrap().clear();
+/// conn.coalesce(|client| (0..10).try_for_each(|_| call(client)))?;
+/// assert_eq!(*socket.0.lock().unwrap(), [40]);
+///
+/// // the writes of another connection aren't deferred
+/// socket.0.lock().unwrap().clear();
+/// let other_socket = Socket::default();
+/// let mut other = connect(&other_socket);
+/// conn.coalesce(|client| {
+///     call(client)?;
+///     call(&mut other)?;
+///     assert_eq!(*other_socket.0.lock().unwrap(), [4]);
+///     call(client)
+/// })?;
+/// assert_eq!(*socket.0.lock().unwrap(), [8]);
+/// # Ok::<(), thrift::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct CoalescingConnection<C>(C);
+
+impl<C> CoalescingConnection<C> {
+    pub fn new(conn: C) -> Self {
+        Self(conn)
+    }
+
+    pub fn into_inner(self) -> C {
+        self.0
+    }
+}
+
+impl<C: ProtocolAccess> CoalescingConnection<C> {
+    /// Make the calls of `f`, then flush their coalesced writes at once
+    ///
+    /// The connection is flushed first, which finds its [`CoalescingWrite`] layer.
+    /// The writes are flushed even if `f` fails
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first flush, or else that of `f`, or else that of the last flush
+    pub fn coalesce<F, R>(&mut self, f: F) -> thrift::Result<R>
+    where
+        F: FnOnce(&mut C) -> thrift::Result<R>,
+    {
+        let res = {
+            let _scope = Scope::enter(&mut self.0)?;
+            f(&mut self.0)
+        };
+        let flushed = self.0.output_protocol_mut().flush();
+        let res = res?;
+        flushed?;
+        Ok(res)
+    }
+}
+
+impl<C> Deref for CoalescingConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<C> DerefMut for CoalescingConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<C: FromProtocol> FromProtocol for CoalescingConnection<C> {
+    type InputProtocol = C::InputProtocol;
+
+    type OutputProtocol = C::OutputProtocol;
+
+    fn from_protocol(
+        input_protocol: Self::InputProtocol,
+        output_protocol: Self::OutputProtocol,
+    ) -> Self {
+        Self(C::from_protocol(input_protocol, output_protocol))
+    }
+}
+
+impl<C: ProtocolAccess> ProtocolAccess for CoalescingConnection<C> {
+    fn input_protocol_mut(&mut self) -> &mut Self::InputProtocol {
+        self.0.input_protocol_mut()
+    }
+
+    fn output_protocol_mut(&mut self) -> &mut Self::OutputProtocol {
+        self.0.output_protocol_mut()
+    }
+}
+
+impl<C: ThriftConnection> ThriftConnection for CoalescingConnection<C> {
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.0.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.0.has_broken()
+    }
+}
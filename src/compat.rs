@@ -0,0 +1,90 @@
+use crate::{MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+
+/// A [`r2d2::Pool`] of connections created by `T`
+#[cfg(feature = "impl-r2d2")]
+pub type R2d2Pool<T> = r2d2::Pool<ThriftConnectionManager<T>>;
+
+/// A connection checked out from a [`R2d2Pool`]
+#[cfg(feature = "impl-r2d2")]
+pub type R2d2PooledConnection<T> = r2d2::PooledConnection<ThriftConnectionManager<T>>;
+
+/// A [`bb8::Pool`] of connections created by `T`
+#[cfg(feature = "impl-bb8")]
+pub type Bb8Pool<T> = bb8::Pool<ThriftConnectionManager<T>>;
+
+/// A connection checked out from a [`Bb8Pool`]
+#[cfg(feature = "impl-bb8")]
+pub type Bb8PooledConnection<'a, T> = bb8::PooledConnection<'a, ThriftConnectionManager<T>>;
+
+/// Assert at compile time that `manager` implements [`r2d2::ManageConnection`]
+///
+/// Both `r2d2` and `bb8` are implemented on [`ThriftConnectionManager`], but with different
+/// bounds on the error type: `std::error::Error` for `r2d2` and `Send + Debug` for `bb8`.
+/// A manager can satisfy one and not the other, which usually shows up as a confusing error
+/// at the `build` call. Each requirement is spelled out here, so the compiler points at the
+/// one that isn't met, where the manager is created
+///
+/// ```
+/// # use thrift_pool::{ensure_r2d2_compatible, MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// let manager = ensure_r2d2_compatible(ThriftConnectionManager::new(Maker));
+/// ```
+#[cfg(feature = "impl-r2d2")]
+pub fn ensure_r2d2_compatible<T>(manager: ThriftConnectionManager<T>) -> ThriftConnectionManager<T>
+where
+    T: MakeThriftConnection + Send + Sync + 'static,
+    T::Error: std::error::Error + 'static,
+    T::Output: ThriftConnection<Error = T::Error> + Send + 'static,
+{
+    manager
+}
+
+/// Assert at compile time that `manager` implements [`bb8::ManageConnection`]
+///
+/// See [`ensure_r2d2_compatible`] for why this is useful
+///
+/// ```
+/// # use thrift_pool::{ensure_bb8_compatible, MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+/// #[derive(Debug)]
+/// struct MyError;
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = MyError;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = MyError;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, MyError> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// // bb8 only needs the error to be `Debug`
+/// let manager = ensure_bb8_compatible(ThriftConnectionManager::new(Maker));
+/// ```
+#[cfg(feature = "impl-bb8")]
+pub fn ensure_bb8_compatible<T>(manager: ThriftConnectionManager<T>) -> ThriftConnectionManager<T>
+where
+    T: MakeThriftConnection + Send + Sync + 'static,
+    T::Error: Send + std::fmt::Debug + 'static,
+    T::Output: ThriftConnection<Error = T::Error> + Send + 'static,
+{
+    manager
+}
@@ -0,0 +1,357 @@
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs},
+    time::Duration,
+};
+
+use thrift::{
+    protocol::{
+        TBinaryInputProtocol, TBinaryOutputProtocol, TCompactInputProtocol, TCompactOutputProtocol,
+        TInputProtocol, TOutputProtocol,
+    },
+    transport::{
+        TBufferedReadTransport, TBufferedWriteTransport, TFramedReadTransport,
+        TFramedWriteTransport, TIoChannel, TTcpChannel,
+    },
+};
+
+use crate::{
+    ConfigError, FromProtocolWithSocket, MakeThriftConnection, MakeThriftConnectionFromAddrs,
+    ThriftConnectionManager, TransportInfo, TransportKind, ValidateAddrs,
+};
+
+/// The transport selected by [`ThriftPoolConfig::framing`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Framing {
+    /// [`TFramedReadTransport`]/[`TFramedWriteTransport`]
+    #[default]
+    Framed,
+    /// [`TBufferedReadTransport`]/[`TBufferedWriteTransport`]
+    Buffered,
+}
+
+/// The protocol selected by [`ThriftPoolConfig::protocol`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    /// [`TBinaryInputProtocol`]/[`TBinaryOutputProtocol`]
+    Binary,
+    /// [`TCompactInputProtocol`]/[`TCompactOutputProtocol`]
+    #[default]
+    Compact,
+}
+
+/// Connection settings that can be deserialized from a configuration file
+///
+/// Since the transport and protocol are only known at runtime, the client created by
+/// [`ThriftPoolConfig::build_manager`] is built from boxed protocols
+/// (see [`BoxedInputProtocol`] and [`BoxedOutputProtocol`])
+///
+/// ```
+/// # use std::net::TcpListener;
+/// # use std::time::Duration;
+/// # use thrift::protocol::{TInputProtocol, TOutputProtocol};
+/// # use thrift_pool::{
+/// #     BoxedInputProtocol, BoxedOutputProtocol, ConfigError, FromProtocol, MakeThriftConnection,
+/// #     ThriftPoolConfig,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// type Client = MyThriftClient<BoxedInputProtocol, BoxedOutputProtocol>;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let config: ThriftPoolConfig = toml::from_str(&format!(
+///     r#"
+///     addrs = ["{}"]
+///     connect_timeout_ms = 500
+///     read_timeout_ms = 2000
+///     write_timeout_ms = 2000
+///     framing = "buffered"
+///     protocol = "binary"
+///     "#,
+///     listener.local_addr()?
+/// ))?;
+///
+/// let manager = config.build_manager::<Client>()?;
+/// assert_eq!(manager.inner().read_timeout(), Some(Duration::from_secs(2)));
+/// manager.inner().make_thrift_connection()?;
+///
+/// let config: ThriftPoolConfig = toml::from_str("addrs = []")?;
+/// assert_eq!(config.build_manager::<Client>().unwrap_err(), ConfigError::NoAddrs);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ThriftPoolConfig {
+    /// The addresses to connect to, tried in order
+    pub addrs: Vec<String>,
+    /// See [`MakeThriftConnectionFromAddrs::with_connect_timeout`]
+    #[serde(default)]
+    pub connect_timeout_ms: Option<u64>,
+    /// See [`MakeThriftConnectionFromAddrs::with_read_timeout`]
+    #[serde(default)]
+    pub read_timeout_ms: Option<u64>,
+    /// See [`MakeThriftConnectionFromAddrs::with_write_timeout`]
+    #[serde(default)]
+    pub write_timeout_ms: Option<u64>,
+    /// Must be `false` for now, since the only underlying maker is [`MakeThriftConnectionFromAddrs`]
+    #[serde(default)]
+    pub tls: bool,
+    #[serde(default)]
+    pub framing: Framing,
+    #[serde(default)]
+    pub protocol: Protocol,
+}
+
+impl ThriftPoolConfig {
+    /// Check the configuration for invalid values, without connecting nor resolving `addrs`
+    ///
+    /// An address that doesn't resolve or a backend that is down is only found out
+    /// when connecting, so a valid configuration can still fail to connect
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` describing the first invalid setting
+    ///
+    /// ```
+    /// # use thrift::protocol::{TInputProtocol, TOutputProtocol};
+    /// # use thrift_pool::{
+    /// #     BoxedInputProtocol, BoxedOutputProtocol, ConfigError, FromProtocol, MakeThriftConnection,
+    /// #     ThriftPoolConfig,
+    /// # };
+    /// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+    /// #     i_prot: Ip,
+    /// #     o_prot: Op,
+    /// # }
+    /// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+    /// #     type InputProtocol = Ip;
+    /// #     type OutputProtocol = Op;
+    /// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+    /// #         MyThriftClient { i_prot, o_prot }
+    /// #     }
+    /// # }
+    /// # type Client = MyThriftClient<BoxedInputProtocol, BoxedOutputProtocol>;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config: ThriftPoolConfig = toml::from_str(r#"addrs = ["localhost"]"#)?;
+    /// assert_eq!(
+    ///     config.validate().unwrap_err(),
+    ///     ConfigError::InvalidAddr("localhost".to_owned())
+    /// );
+    ///
+    /// // nothing listens on port 1, but the configuration itself is valid
+    /// let config: ThriftPoolConfig = toml::from_str(r#"addrs = ["127.0.0.1:1"]"#)?;
+    /// assert_eq!(config.validate(), Ok(()));
+    /// let manager = config.build_manager::<Client>()?;
+    /// assert!(manager.inner().make_thrift_connection().is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.addrs.validate_addrs()?;
+        if self.connect_timeout_ms == Some(0) {
+            return Err(ConfigError::ZeroConnectTimeout);
+        }
+        if self.read_timeout_ms == Some(0) {
+            return Err(ConfigError::ZeroReadTimeout);
+        }
+        if self.write_timeout_ms == Some(0) {
+            return Err(ConfigError::ZeroWriteTimeout);
+        }
+        if self.tls {
+            return Err(ConfigError::TlsUnsupported);
+        }
+        Ok(())
+    }
+
+    /// Create a [`ThriftConnectionManager`] for the client `T`
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the configuration is invalid (see [`ThriftPoolConfig::validate`])
+    pub fn build_manager<T>(
+        &self,
+    ) -> Result<ThriftConnectionManager<MakeThriftConnectionFromConfig<T>>, ConfigError> {
+        self.validate()?;
+        let mut addrs = MakeThriftConnectionFromAddrs::new(AddrList(self.addrs.clone()));
+        if let Some(millis) = self.connect_timeout_ms {
+            addrs = addrs.with_connect_timeout(Duration::from_millis(millis));
+        }
+        if let Some(millis) = self.read_timeout_ms {
+            addrs = addrs.with_read_timeout(Duration::from_millis(millis));
+        }
+        if let Some(millis) = self.write_timeout_ms {
+            addrs = addrs.with_write_timeout(Duration::from_millis(millis));
+        }
+        Ok(ThriftConnectionManager::new(
+            MakeThriftConnectionFromConfig {
+                addrs,
+                framing: self.framing,
+                protocol: self.protocol,
+            },
+        ))
+    }
+}
+
+/// The input protocol of clients created from a [`ThriftPoolConfig`]
+pub type BoxedInputProtocol = Box<dyn TInputProtocol + Send>;
+/// The output protocol of clients created from a [`ThriftPoolConfig`]
+pub type BoxedOutputProtocol = Box<dyn TOutputProtocol + Send>;
+
+/// A list of `host:port` strings, resolved in order
+#[derive(Debug, Clone)]
+struct AddrList(Vec<String>);
+
+impl ToSocketAddrs for AddrList {
+    type Iter = std::vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        let mut resolved = Vec::new();
+        let mut last_err = None;
+        for addr in &self.0 {
+            match addr.to_socket_addrs() {
+                Ok(addrs) => resolved.extend(addrs),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        match last_err {
+            Some(e) if resolved.is_empty() => Err(e),
+            _ => Ok(resolved.into_iter()),
+        }
+    }
+}
+
+impl ValidateAddrs for AddrList {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        self.0.validate_addrs()
+    }
+}
+
+/// The [`MakeThriftConnection`] created by [`ThriftPoolConfig::build_manager`]
+pub struct MakeThriftConnectionFromConfig<T> {
+    addrs: MakeThriftConnectionFromAddrs<T, AddrList>,
+    framing: Framing,
+    protocol: Protocol,
+}
+
+impl<T> std::fmt::Debug for MakeThriftConnectionFromConfig<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MakeThriftConnectionFromConfig")
+            .field("addrs", &self.addrs)
+            .field("framing", &self.framing)
+            .field("protocol", &self.protocol)
+            .finish()
+    }
+}
+
+impl<T> Clone for MakeThriftConnectionFromConfig<T> {
+    fn clone(&self) -> Self {
+        Self {
+            addrs: self.addrs.clone(),
+            framing: self.framing,
+            protocol: self.protocol,
+        }
+    }
+}
+
+impl<T> MakeThriftConnectionFromConfig<T> {
+    /// The configured connect timeout, if any
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.addrs.connect_timeout()
+    }
+
+    /// The configured read timeout, if any
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.addrs.read_timeout()
+    }
+
+    /// The configured write timeout, if any
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.addrs.write_timeout()
+    }
+
+    /// Check the addresses and timeouts for invalid values, without connecting nor resolving
+    /// the addresses, see [`ThriftPoolConfig::validate`]
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` describing the first invalid setting
+    pub fn validate_config(&self) -> Result<(), ConfigError> {
+        self.addrs.validate_config()
+    }
+}
+
+impl<T> MakeThriftConnection for MakeThriftConnectionFromConfig<T>
+where
+    T: FromProtocolWithSocket<
+        InputProtocol = BoxedInputProtocol,
+        OutputProtocol = BoxedOutputProtocol,
+    >,
+{
+    type Error = thrift::Error;
+
+    type Output = T;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let stream = self.addrs.open_stream()?;
+        let socket = stream.try_clone()?;
+        let channel = TTcpChannel::with_stream(stream);
+        let (read, write) = channel.split()?;
+
+        let (input_protocol, output_protocol): (BoxedInputProtocol, BoxedOutputProtocol) =
+            match (self.framing, self.protocol) {
+                (Framing::Framed, Protocol::Binary) => (
+                    Box::new(TBinaryInputProtocol::new(
+                        TFramedReadTransport::new(read),
+                        true,
+                    )),
+                    Box::new(TBinaryOutputProtocol::new(
+                        TFramedWriteTransport::new(write),
+                        true,
+                    )),
+                ),
+                (Framing::Framed, Protocol::Compact) => (
+                    Box::new(TCompactInputProtocol::new(TFramedReadTransport::new(read))),
+                    Box::new(TCompactOutputProtocol::new(TFramedWriteTransport::new(
+                        write,
+                    ))),
+                ),
+                (Framing::Buffered, Protocol::Binary) => (
+                    Box::new(TBinaryInputProtocol::new(
+                        TBufferedReadTransport::new(read),
+                        true,
+                    )),
+                    Box::new(TBinaryOutputProtocol::new(
+                        TBufferedWriteTransport::new(write),
+                        true,
+                    )),
+                ),
+                (Framing::Buffered, Protocol::Compact) => (
+                    Box::new(TCompactInputProtocol::new(TBufferedReadTransport::new(
+                        read,
+                    ))),
+                    Box::new(TCompactOutputProtocol::new(TBufferedWriteTransport::new(
+                        write,
+                    ))),
+                ),
+            };
+
+        T::from_protocol_with_socket(input_protocol, output_protocol, &socket)
+    }
+}
+
+impl<T> TransportInfo for MakeThriftConnectionFromConfig<T> {
+    fn transport_kind(&self) -> TransportKind {
+        self.addrs.transport_kind()
+    }
+}
@@ -0,0 +1,150 @@
+use std::sync::{Mutex, MutexGuard};
+
+#[cfg(feature = "impl-r2d2")]
+use std::sync::Condvar;
+
+/// How urgently a [`ThriftConnectionManager`](crate::ThriftConnectionManager) needs its
+/// connections, see
+/// [`ThriftConnectionManager::with_connect_priority`](crate::ThriftConnectionManager::with_connect_priority)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    const ALL: [Priority; 3] = [Priority::Low, Priority::Normal, Priority::High];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    in_flight: usize,
+    /// How many connects of each [`Priority`] are waiting
+    waiting: [usize; Priority::ALL.len()],
+}
+
+impl State {
+    /// Whether a connect of `priority` can start: there is a free slot,
+    /// and no connect of a higher priority is waiting for it
+    fn can_start(&self, max: usize, priority: Priority) -> bool {
+        self.in_flight < max && self.waiting[priority.index() + 1..].iter().all(|&n| n == 0)
+    }
+}
+
+/// Caps how many connections a [`ThriftConnectionManager`](crate::ThriftConnectionManager)
+/// creates at once, see
+/// [`ThriftConnectionManager::with_max_concurrent_connects`](crate::ThriftConnectionManager::with_max_concurrent_connects)
+///
+/// A free slot goes to a waiting connect of the highest [`Priority`].
+/// `r2d2` connects from its own threads, which wait on a condition variable;
+/// `bb8` connects from tasks, which wait on a [`tokio::sync::Notify`]
+#[derive(Debug)]
+pub(crate) struct ConnectLimit {
+    max: usize,
+    state: Mutex<State>,
+    #[cfg(feature = "impl-r2d2")]
+    released: Condvar,
+    #[cfg(feature = "impl-bb8")]
+    released_async: tokio::sync::Notify,
+}
+
+/// Releases a slot of a [`ConnectLimit`] when dropped
+pub(crate) struct ConnectPermit<'a>(&'a ConnectLimit);
+
+impl Drop for ConnectPermit<'_> {
+    fn drop(&mut self) {
+        self.0.state().in_flight -= 1;
+        self.0.notify();
+    }
+}
+
+/// Counts a connect as waiting while it lives, so that one that gives up
+/// (e.g. a dropped `bb8` connect future) stops holding back lower priorities
+struct Waiting<'a> {
+    limit: &'a ConnectLimit,
+    priority: Priority,
+}
+
+impl<'a> Waiting<'a> {
+    fn new(limit: &'a ConnectLimit, state: &mut State, priority: Priority) -> Self {
+        state.waiting[priority.index()] += 1;
+        Self { limit, priority }
+    }
+
+    /// Stop waiting, and take a slot
+    fn start(self, state: &mut State) -> ConnectPermit<'a> {
+        state.waiting[self.priority.index()] -= 1;
+        state.in_flight += 1;
+        let limit = self.limit;
+        std::mem::forget(self);
+        ConnectPermit(limit)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.limit.state().waiting[self.priority.index()] -= 1;
+        // lower priorities may now start
+        self.limit.notify();
+    }
+}
+
+impl ConnectLimit {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            max,
+            state: Mutex::new(State::default()),
+            #[cfg(feature = "impl-r2d2")]
+            released: Condvar::new(),
+            #[cfg(feature = "impl-bb8")]
+            released_async: tokio::sync::Notify::new(),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn notify(&self) {
+        #[cfg(feature = "impl-r2d2")]
+        self.released.notify_all();
+        #[cfg(feature = "impl-bb8")]
+        self.released_async.notify_waiters();
+    }
+
+    /// Block until a connect of `priority` can start
+    #[cfg(feature = "impl-r2d2")]
+    pub(crate) fn acquire(&self, priority: Priority) -> ConnectPermit<'_> {
+        let mut state = self.state();
+        let waiting = Waiting::new(self, &mut state, priority);
+        let mut state = self
+            .released
+            .wait_while(state, |state| !state.can_start(self.max, priority))
+            .unwrap_or_else(|e| e.into_inner());
+        waiting.start(&mut state)
+    }
+
+    /// Wait until a connect of `priority` can start
+    #[cfg(feature = "impl-bb8")]
+    pub(crate) async fn acquire_async(&self, priority: Priority) -> ConnectPermit<'_> {
+        let waiting = Waiting::new(self, &mut self.state(), priority);
+        loop {
+            // registered before checking, so that a release in between isn't missed
+            let mut released = std::pin::pin!(self.released_async.notified());
+            released.as_mut().enable();
+            {
+                let mut state = self.state();
+                if state.can_start(self.max, priority) {
+                    return waiting.start(&mut state);
+                }
+            }
+            released.await;
+        }
+    }
+}
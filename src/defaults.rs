@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use crate::ThriftConnectionManager;
+
+/// The connection timeout used by [`ThriftConnectionManager::build_default_r2d2`] and/or
+/// [`ThriftConnectionManager::build_default_bb8`]
+pub const DEFAULT_CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The max pool size used by [`ThriftConnectionManager::build_default_r2d2`] and/or
+/// [`ThriftConnectionManager::build_default_bb8`]: the number of available CPUs
+/// (see [`std::thread::available_parallelism`]), or 4 if it can't be determined
+pub fn default_pool_size() -> u32 {
+    std::thread::available_parallelism()
+        .ok()
+        .and_then(|n| u32::try_from(n.get()).ok())
+        .unwrap_or(4)
+}
+
+impl<T> ThriftConnectionManager<T> {
+    /// Build a [`r2d2::Pool`] with opinionated defaults:
+    ///
+    /// * max size of [`default_pool_size`]
+    /// * connections are validated on checkout
+    /// * connection timeout of [`DEFAULT_CONNECTION_TIMEOUT`]
+    ///
+    /// Use [`r2d2::Pool::builder`] directly for anything else
+    ///
+    /// ```
+    /// # use thrift_pool::{default_pool_size, MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+    /// # struct Conn;
+    /// # impl ThriftConnection for Conn {
+    /// #     type Error = thrift::Error;
+    /// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+    /// #         Ok(())
+    /// #     }
+    /// # }
+    /// # struct Maker;
+    /// # impl MakeThriftConnection for Maker {
+    /// #     type Error = thrift::Error;
+    /// #     type Output = Conn;
+    /// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+    /// #         Ok(Conn)
+    /// #     }
+    /// # }
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let pool = ThriftConnectionManager::new(Maker).build_default_r2d2()?;
+    /// assert_eq!(pool.max_size(), default_pool_size());
+    /// let conn = pool.get()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See [`r2d2::Builder::build`]
+    #[cfg(feature = "impl-r2d2")]
+    pub fn build_default_r2d2(self) -> Result<r2d2::Pool<Self>, r2d2::Error>
+    where
+        Self: r2d2::ManageConnection,
+    {
+        r2d2::Pool::builder()
+            .max_size(default_pool_size())
+            .test_on_check_out(true)
+            .connection_timeout(DEFAULT_CONNECTION_TIMEOUT)
+            .build(self)
+    }
+
+    /// Build a [`bb8::Pool`] with opinionated defaults:
+    ///
+    /// * max size of [`default_pool_size`]
+    /// * connections are validated on checkout
+    /// * connection timeout of [`DEFAULT_CONNECTION_TIMEOUT`]
+    ///
+    /// Use [`bb8::Pool::builder`] directly for anything else
+    ///
+    /// ```
+    /// # use thrift_pool::{default_pool_size, MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+    /// # #[derive(Debug)]
+    /// # struct Conn;
+    /// # impl ThriftConnection for Conn {
+    /// #     type Error = thrift::Error;
+    /// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+    /// #         Ok(())
+    /// #     }
+    /// # }
+    /// # struct Maker;
+    /// # impl MakeThriftConnection for Maker {
+    /// #     type Error = thrift::Error;
+    /// #     type Output = Conn;
+    /// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+    /// #         Ok(Conn)
+    /// #     }
+    /// # }
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let pool = ThriftConnectionManager::new(Maker).build_default_bb8().await?;
+    ///
+    /// let mut conns = Vec::new();
+    /// for _ in 0..default_pool_size() {
+    ///     conns.push(pool.get().await?);
+    /// }
+    /// assert_eq!(pool.state().connections, default_pool_size());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See [`bb8::Builder::build`]
+    #[cfg(feature = "impl-bb8")]
+    pub async fn build_default_bb8(
+        self,
+    ) -> Result<bb8::Pool<Self>, <Self as bb8::ManageConnection>::Error>
+    where
+        Self: bb8::ManageConnection,
+    {
+        bb8::Pool::builder()
+            .max_size(default_pool_size())
+            .test_on_check_out(true)
+            .connection_timeout(DEFAULT_CONNECTION_TIMEOUT)
+            .build(self)
+            .await
+    }
+}
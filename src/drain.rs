@@ -0,0 +1,132 @@
+use crate::{validate::evict, MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+
+/// Close the idle connections of `pool`, returning how many were closed
+///
+/// Idle connections are checked out with [`r2d2::Pool::try_get`] and evicted (the manager
+/// reports them as broken, so the pool drops them instead of taking them back) until the pool
+/// has no idle connection left. Connections in use are left alone: they are closed when
+/// returned only if this is called again then.
+///
+/// This races with the rest of the program: a connection checked out by another thread
+/// meanwhile isn't closed, and the pool keeps opening connections to stay at its `min_idle`
+/// (which defaults to its `max_size`). At most as many connections as the pool had when this
+/// was called are closed, so this returns even if the pool replaces them as fast
+///
+/// ```
+/// # use thrift_pool::{drain_idle_r2d2, MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = r2d2::Pool::builder()
+///     .max_size(4)
+///     .min_idle(Some(0))
+///     .build(ThriftConnectionManager::new(Maker))?;
+/// // fill the pool, keeping one connection in use
+/// let mut conns = (0..4).map(|_| pool.get()).collect::<Result<Vec<_>, _>>()?;
+/// let in_use = conns.pop();
+/// drop(conns);
+///
+/// assert_eq!(drain_idle_r2d2(&pool), 3);
+/// assert_eq!(pool.state().connections, 1);
+/// drop(in_use);
+/// assert_eq!(drain_idle_r2d2(&pool), 1);
+/// assert_eq!(pool.state().connections, 0);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "impl-r2d2")]
+pub fn drain_idle_r2d2<T>(pool: &r2d2::Pool<ThriftConnectionManager<T>>) -> usize
+where
+    T: MakeThriftConnection + Send + Sync + 'static,
+    T::Error: std::error::Error + 'static,
+    T::Output: ThriftConnection<Error = T::Error> + Send + 'static,
+{
+    let mut closed = 0;
+    for _ in 0..pool.state().connections {
+        if pool.state().idle_connections == 0 {
+            break;
+        }
+        let Some(conn) = pool.try_get() else {
+            break;
+        };
+        evict(conn);
+        closed += 1;
+    }
+    closed
+}
+
+/// Close the idle connections of `pool`, returning how many were closed
+///
+/// This works like [`drain_idle_r2d2`], except that connections are checked out with
+/// [`bb8::Pool::get`]: if other tasks take the idle connections first, this may wait for
+/// (or create, and close) another one, up to the pool's connection timeout
+///
+/// ```
+/// # use thrift_pool::{drain_idle_bb8, MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+/// # #[derive(Debug)]
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = bb8::Pool::builder()
+///     .max_size(4)
+///     .build(ThriftConnectionManager::new(Maker))
+///     .await?;
+/// // fill the pool
+/// let mut conns = Vec::new();
+/// for _ in 0..4 {
+///     conns.push(pool.get().await?);
+/// }
+/// drop(conns);
+///
+/// assert_eq!(drain_idle_bb8(&pool).await, 4);
+/// assert_eq!(pool.state().connections, 0);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "impl-bb8")]
+pub async fn drain_idle_bb8<T>(pool: &bb8::Pool<ThriftConnectionManager<T>>) -> usize
+where
+    T: MakeThriftConnection + Send + Sync + 'static,
+    T::Error: Send + std::fmt::Debug + 'static,
+    T::Output: ThriftConnection<Error = T::Error> + Send + 'static,
+{
+    let mut closed = 0;
+    for _ in 0..pool.state().connections {
+        if pool.state().idle_connections == 0 {
+            break;
+        }
+        let Ok(conn) = pool.get().await else {
+            break;
+        };
+        evict(conn);
+        closed += 1;
+    }
+    closed
+}
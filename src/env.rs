@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use thrift::transport::{ReadHalf, TTcpChannel, WriteHalf};
+
+use crate::{
+    ConfigError, FromProtocolWithSocket, FromRead, FromReadTransport, FromWrite,
+    FromWriteTransport, MakeThriftConnection, MakeThriftConnectionFromAddrs,
+    ThriftConnectionManager, TransportInfo, TransportKind,
+};
+
+/// The address to connect to (required), e.g. `localhost:9090`
+pub const THRIFT_ADDR: &str = "THRIFT_ADDR";
+/// The connect timeout in milliseconds (optional)
+pub const THRIFT_CONNECT_TIMEOUT_MS: &str = "THRIFT_CONNECT_TIMEOUT_MS";
+/// Whether to use TLS (optional), one of `1`/`true`/`yes`/`on` or `0`/`false`/`no`/`off`
+pub const THRIFT_TLS: &str = "THRIFT_TLS";
+
+/// The error returned when [`MakeThriftConnectionFromEnv`] can't be built from the environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvConfigError {
+    /// A required variable is not set
+    Missing(&'static str),
+    /// A variable is set to a value that can't be parsed
+    Invalid { var: &'static str, value: String },
+    /// `THRIFT_TLS` is enabled, but there is no TLS maker to build
+    TlsUnsupported,
+}
+
+impl std::fmt::Display for EnvConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing(var) => write!(f, "missing required environment variable `{var}`"),
+            Self::Invalid { var, value } => {
+                write!(
+                    f,
+                    "invalid value `{value}` for environment variable `{var}`"
+                )
+            }
+            Self::TlsUnsupported => write!(f, "`{THRIFT_TLS}` is enabled but TLS is not supported"),
+        }
+    }
+}
+
+impl std::error::Error for EnvConfigError {}
+
+/// A [`MakeThriftConnection`] configured from well-known environment variables
+///
+/// * [`THRIFT_ADDR`] -- the address to connect to (required)
+/// * [`THRIFT_CONNECT_TIMEOUT_MS`] -- see [`MakeThriftConnectionFromAddrs::with_connect_timeout`]
+/// * [`THRIFT_TLS`] -- must be disabled (or unset) for now,
+///   since the only underlying maker is [`MakeThriftConnectionFromAddrs`]
+///
+/// Configuration errors are reported when calling [`MakeThriftConnectionFromEnv::from_env`],
+/// not when the first connection is made
+///
+/// ```
+/// # use std::time::Duration;
+/// # use thrift_pool::{EnvConfigError, MakeThriftConnectionFromEnv};
+/// std::env::remove_var("THRIFT_ADDR");
+/// assert_eq!(
+///     MakeThriftConnectionFromEnv::<()>::from_env().unwrap_err(),
+///     EnvConfigError::Missing("THRIFT_ADDR")
+/// );
+///
+/// std::env::set_var("THRIFT_ADDR", "localhost:9090");
+/// std::env::set_var("THRIFT_CONNECT_TIMEOUT_MS", "250");
+/// std::env::set_var("THRIFT_TLS", "false");
+/// let maker = MakeThriftConnectionFromEnv::<()>::from_env().unwrap();
+/// assert_eq!(maker.inner().addrs(), "localhost:9090");
+/// assert_eq!(maker.inner().connect_timeout(), Some(Duration::from_millis(250)));
+///
+/// std::env::set_var("THRIFT_CONNECT_TIMEOUT_MS", "soon");
+/// assert!(matches!(
+///     MakeThriftConnectionFromEnv::<()>::from_env(),
+///     Err(EnvConfigError::Invalid { var: "THRIFT_CONNECT_TIMEOUT_MS", .. })
+/// ));
+/// ```
+pub struct MakeThriftConnectionFromEnv<T> {
+    inner: MakeThriftConnectionFromAddrs<T, String>,
+}
+
+impl<T> std::fmt::Debug for MakeThriftConnectionFromEnv<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MakeThriftConnectionFromEnv")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<T> Clone for MakeThriftConnectionFromEnv<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> MakeThriftConnectionFromEnv<T> {
+    /// Read the configuration from the process environment
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a required variable is missing or a variable can't be parsed
+    pub fn from_env() -> Result<Self, EnvConfigError> {
+        Self::from_vars(|var| std::env::var(var).ok())
+    }
+
+    /// Read the configuration using `get_var` to look up variables
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a required variable is missing or a variable can't be parsed
+    pub fn from_vars(get_var: impl Fn(&str) -> Option<String>) -> Result<Self, EnvConfigError> {
+        let addr = get_var(THRIFT_ADDR).ok_or(EnvConfigError::Missing(THRIFT_ADDR))?;
+
+        if let Some(value) = get_var(THRIFT_TLS) {
+            match value.to_ascii_lowercase().as_str() {
+                "" | "0" | "false" | "no" | "off" => {}
+                "1" | "true" | "yes" | "on" => return Err(EnvConfigError::TlsUnsupported),
+                _ => {
+                    return Err(EnvConfigError::Invalid {
+                        var: THRIFT_TLS,
+                        value,
+                    })
+                }
+            }
+        }
+
+        let mut inner = MakeThriftConnectionFromAddrs::new(addr);
+        if let Some(value) = get_var(THRIFT_CONNECT_TIMEOUT_MS) {
+            let millis = value.parse().map_err(|_| EnvConfigError::Invalid {
+                var: THRIFT_CONNECT_TIMEOUT_MS,
+                value,
+            })?;
+            inner = inner.with_connect_timeout(Duration::from_millis(millis));
+        }
+
+        Ok(Self { inner })
+    }
+
+    /// Check the address and the connect timeout for invalid values, without connecting nor
+    /// resolving the address, see [`MakeThriftConnectionFromAddrs::validate_config`]
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` describing the first invalid setting
+    ///
+    /// ```
+    /// # use thrift_pool::{ConfigError, MakeThriftConnectionFromEnv};
+    /// // parsed, but not an address
+    /// let maker = MakeThriftConnectionFromEnv::<()>::from_vars(|var| {
+    ///     (var == "THRIFT_ADDR").then(|| "localhost".to_owned())
+    /// })
+    /// .unwrap();
+    /// assert_eq!(
+    ///     maker.validate_config(),
+    ///     Err(ConfigError::InvalidAddr("localhost".to_owned()))
+    /// );
+    /// ```
+    pub fn validate_config(&self) -> Result<(), ConfigError> {
+        self.inner.validate_config()
+    }
+
+    /// The underlying maker
+    pub fn inner(&self) -> &MakeThriftConnectionFromAddrs<T, String> {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> MakeThriftConnectionFromAddrs<T, String> {
+        self.inner
+    }
+}
+
+impl<
+        RL: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RT: FromRead<Read = RL>,
+        IP: FromReadTransport<ReadTransport = RT>,
+        WL: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WT: FromWrite<Write = WL>,
+        OP: FromWriteTransport<WriteTransport = WT>,
+        T: FromProtocolWithSocket<InputProtocol = IP, OutputProtocol = OP>,
+    > MakeThriftConnectionFromEnv<T>
+{
+    pub fn into_connection_manager(self) -> ThriftConnectionManager<Self> {
+        ThriftConnectionManager::new(self)
+    }
+}
+
+impl<
+        RL: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RT: FromRead<Read = RL>,
+        IP: FromReadTransport<ReadTransport = RT>,
+        WL: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WT: FromWrite<Write = WL>,
+        OP: FromWriteTransport<WriteTransport = WT>,
+        T: FromProtocolWithSocket<InputProtocol = IP, OutputProtocol = OP>,
+    > MakeThriftConnection for MakeThriftConnectionFromEnv<T>
+{
+    type Error = thrift::Error;
+
+    type Output = T;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        self.inner.make_thrift_connection()
+    }
+}
+
+impl<T> TransportInfo for MakeThriftConnectionFromEnv<T> {
+    fn transport_kind(&self) -> TransportKind {
+        self.inner.transport_kind()
+    }
+}
@@ -0,0 +1,152 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::{MakeThriftConnection, ThriftConnection, TransportInfo, TransportKind};
+
+/// An error `E` implementing [`std::error::Error`], displayed as its [`Debug`](std::fmt::Debug)
+/// representation
+///
+/// `r2d2` requires the error of the connections to implement [`std::error::Error`],
+/// a plain `#[derive(Debug)]` enum doesn't: [`ErrorAdapterMaker`] wraps it in this
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorAdapter<E>(pub E);
+
+impl<E> ErrorAdapter<E> {
+    pub fn into_inner(self) -> E {
+        self.0
+    }
+}
+
+impl<E: std::fmt::Debug> std::fmt::Display for ErrorAdapter<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl<E: std::fmt::Debug> std::error::Error for ErrorAdapter<E> {}
+
+impl<E: std::fmt::Debug + Send + Sync + 'static> From<ErrorAdapter<E>> for thrift::Error {
+    fn from(e: ErrorAdapter<E>) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// A [`ThriftConnection`] whose error is wrapped in an [`ErrorAdapter`].
+/// Created by [`ErrorAdapterMaker`]
+///
+/// It derefs to `C`
+#[derive(Debug)]
+pub struct ErrorAdapterConnection<C>(C);
+
+impl<C> ErrorAdapterConnection<C> {
+    pub fn new(conn: C) -> Self {
+        Self(conn)
+    }
+
+    pub fn into_inner(self) -> C {
+        self.0
+    }
+}
+
+impl<C> Deref for ErrorAdapterConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<C> DerefMut for ErrorAdapterConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<C: ThriftConnection> ThriftConnection for ErrorAdapterConnection<C> {
+    type Error = ErrorAdapter<C::Error>;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.0.is_valid().map_err(ErrorAdapter)
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.0.has_broken()
+    }
+}
+
+/// A [`MakeThriftConnection`] wrapping the errors of the inner maker `M`, and of its
+/// connections, in [`ErrorAdapter`]s
+///
+/// This is the fix for "the trait bound `MyError: std::error::Error` is not satisfied"
+/// when building an `r2d2` pool of connections whose error is a plain enum
+///
+/// ```
+/// # use thrift_pool::{ErrorAdapterMaker, MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+/// // doesn't implement `std::error::Error`
+/// #[derive(Debug)]
+/// enum MyError {
+///     Refused,
+///     Invalid { code: i32 },
+/// }
+///
+/// struct Conn;
+///
+/// impl ThriftConnection for Conn {
+///     type Error = MyError;
+///     fn is_valid(&mut self) -> Result<(), MyError> {
+///         Err(MyError::Invalid { code: 3 })
+///     }
+/// }
+///
+/// struct Maker;
+///
+/// impl MakeThriftConnection for Maker {
+///     type Error = MyError;
+///     type Output = Conn;
+///     fn make_thrift_connection(&self) -> Result<Conn, MyError> {
+///         Ok(Conn)
+///     }
+/// }
+///
+/// let pool = r2d2::Pool::builder()
+///     .max_size(1)
+///     .test_on_check_out(false)
+///     .build_unchecked(ThriftConnectionManager::new(ErrorAdapterMaker::new(Maker)));
+/// let mut conn = pool.get().unwrap();
+///
+/// let err = conn.is_valid().unwrap_err();
+/// assert!(matches!(err.0, MyError::Invalid { code: 3 }));
+/// assert_eq!(err.to_string(), "Invalid { code: 3 }");
+/// let _: Box<dyn std::error::Error> = Box::new(err);
+/// # let _ = MyError::Refused;
+/// ```
+#[derive(Debug, Clone)]
+pub struct ErrorAdapterMaker<M>(M);
+
+impl<M> ErrorAdapterMaker<M> {
+    pub fn new(maker: M) -> Self {
+        Self(maker)
+    }
+
+    pub fn maker(&self) -> &M {
+        &self.0
+    }
+}
+
+impl<M: MakeThriftConnection> MakeThriftConnection for ErrorAdapterMaker<M> {
+    type Error = ErrorAdapter<M::Error>;
+
+    type Output = ErrorAdapterConnection<M::Output>;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        self.0
+            .make_thrift_connection()
+            .map(ErrorAdapterConnection)
+            .map_err(ErrorAdapter)
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for ErrorAdapterMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.0.transport_kind()
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard},
+    time::SystemTime,
+};
+
+use crate::{MakeThriftConnection, TransportInfo, TransportKind};
+
+type History = VecDeque<(SystemTime, String)>;
+
+/// A [`MakeThriftConnection`] that keeps the last `capacity` errors of the inner maker `M`,
+/// with the time they happened, e.g. to show on a debug page
+///
+/// Older errors are dropped as new ones come in. Clones share the same history
+///
+/// ```
+/// # use thrift_pool::{ErrorHistoryMaker, MakeThriftConnection};
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # struct Maker(AtomicUsize);
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = ();
+/// #     fn make_thrift_connection(&self) -> Result<(), thrift::Error> {
+/// #         let attempt = self.0.fetch_add(1, Ordering::Relaxed);
+/// #         Err(thrift::Error::User(format!("attempt {attempt} failed").into()))
+/// #     }
+/// # }
+/// // a maker whose every attempt fails
+/// let maker = ErrorHistoryMaker::new(Maker(AtomicUsize::new(0)), 3);
+/// for _ in 0..5 {
+///     assert!(maker.make_thrift_connection().is_err());
+/// }
+///
+/// let errors = maker.recent_errors();
+/// let messages = errors.iter().map(|(_, e)| e.as_str()).collect::<Vec<_>>();
+/// assert_eq!(messages, ["attempt 2 failed", "attempt 3 failed", "attempt 4 failed"]);
+/// assert!(errors.windows(2).all(|w| w[0].0 <= w[1].0));
+/// ```
+pub struct ErrorHistoryMaker<M> {
+    maker: M,
+    capacity: usize,
+    history: Arc<Mutex<History>>,
+}
+
+impl<M> ErrorHistoryMaker<M> {
+    /// Keep the last `capacity` errors
+    pub fn new(maker: M, capacity: usize) -> Self {
+        Self {
+            maker,
+            capacity,
+            history: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    /// The errors kept, oldest first
+    pub fn recent_errors(&self) -> Vec<(SystemTime, String)> {
+        self.history().iter().cloned().collect()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn history(&self) -> MutexGuard<'_, History> {
+        self.history.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<M: Clone> Clone for ErrorHistoryMaker<M> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            capacity: self.capacity,
+            history: self.history.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for ErrorHistoryMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ErrorHistoryMaker")
+            .field("maker", &self.maker)
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M> MakeThriftConnection for ErrorHistoryMaker<M>
+where
+    M: MakeThriftConnection,
+    M::Error: std::fmt::Display,
+{
+    type Error = M::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let result = self.maker.make_thrift_connection();
+        if let Err(e) = &result {
+            if self.capacity > 0 {
+                let mut history = self.history();
+                if history.len() == self.capacity {
+                    history.pop_front();
+                }
+                history.push_back((SystemTime::now(), e.to_string()));
+            }
+        }
+        result
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for ErrorHistoryMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,80 @@
+use std::{collections::HashMap, sync::Arc, time::SystemTime};
+
+use tokio::sync::broadcast;
+
+use crate::{ConnectionId, MakeThriftConnection};
+
+/// What happened to a connection, see [`PoolEvent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PoolEventKind {
+    /// The connection was created
+    Created,
+    /// The connection passed [`ThriftConnection::is_valid`](crate::ThriftConnection::is_valid)
+    /// (on checkout, unless the pool doesn't test connections then)
+    Validated,
+    /// The connection was found broken (when returned to the pool), and is dropped
+    Broken,
+    /// The connection failed [`ThriftConnection::is_valid`](crate::ThriftConnection::is_valid),
+    /// and is dropped
+    Discarded,
+}
+
+/// A connection lifecycle event sent by a [`ThriftConnectionManager`](crate::ThriftConnectionManager),
+/// see [`ThriftConnectionManager::with_events`](crate::ThriftConnectionManager::with_events)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolEvent {
+    pub kind: PoolEventKind,
+    pub connection_id: ConnectionId,
+    /// When the event happened
+    pub at: SystemTime,
+    /// The labels of the manager, see
+    /// [`ThriftConnectionManager::with_labels`](crate::ThriftConnectionManager::with_labels)
+    pub labels: Arc<HashMap<String, String>>,
+}
+
+/// The sending side of the events of a manager
+///
+/// The connection type is only named in the method, so that
+/// [`ThriftConnectionManager`](crate::ThriftConnectionManager) doesn't need to bound `T`
+pub(crate) trait EventSink<T>: Send + Sync {
+    fn emit(&self, kind: PoolEventKind, conn: &T::Output, labels: &Arc<HashMap<String, String>>)
+    where
+        T: MakeThriftConnection;
+
+    fn subscribe(&self) -> broadcast::Receiver<PoolEvent>;
+}
+
+pub(crate) struct Events<F> {
+    sender: broadcast::Sender<PoolEvent>,
+    connection_id: F,
+}
+
+impl<F> Events<F> {
+    pub(crate) fn new(capacity: usize, connection_id: F) -> Self {
+        Self {
+            sender: broadcast::channel(capacity).0,
+            connection_id,
+        }
+    }
+}
+
+impl<T, F> EventSink<T> for Events<F>
+where
+    T: MakeThriftConnection,
+    F: Fn(&T::Output) -> ConnectionId + Send + Sync,
+{
+    fn emit(&self, kind: PoolEventKind, conn: &T::Output, labels: &Arc<HashMap<String, String>>) {
+        // no receiver is not an error: nobody is listening yet
+        let _ = self.sender.send(PoolEvent {
+            kind,
+            connection_id: (self.connection_id)(conn),
+            at: SystemTime::now(),
+            labels: labels.clone(),
+        });
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<PoolEvent> {
+        self.sender.subscribe()
+    }
+}
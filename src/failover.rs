@@ -0,0 +1,188 @@
+use std::net::SocketAddr;
+
+use thrift::transport::{ReadHalf, TTcpChannel, WriteHalf};
+
+use crate::{
+    Endpoint, Failover, FromProtocolWithSocket, FromRead, FromReadTransport, FromWrite,
+    FromWriteTransport, MakeThriftConnection, MakeThriftConnectionBalanced,
+    MakeThriftConnectionFromAddrs, TransportInfo, TransportKind,
+};
+
+/// The error returned by [`FailoverMaker`] when every endpoint failed,
+/// with the error of each of them, in the order they were tried
+#[derive(Debug)]
+pub struct FailoverError {
+    pub attempts: Vec<(SocketAddr, thrift::Error)>,
+}
+
+impl std::fmt::Display for FailoverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "all {} endpoints failed", self.attempts.len())?;
+        for (addr, e) in &self.attempts {
+            match e {
+                // the message of a transport error isn't part of its `Display`
+                thrift::Error::Transport(e) if !e.message.is_empty() => {
+                    write!(f, "; {addr}: {e}: {}", e.message)?;
+                }
+                e => write!(f, "; {addr}: {e}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FailoverError {}
+
+impl From<FailoverError> for thrift::Error {
+    fn from(e: FailoverError) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// A [`MakeThriftConnection`] that tries its endpoints in order,
+/// and creates the connection with the first one that succeeds
+///
+/// It is a [`MakeThriftConnectionBalanced`] with the [`Failover`] policy, retrying a failed
+/// connect until every endpoint was tried: each connect starts from the first endpoint whose
+/// last connect didn't fail, and goes on from there. If every endpoint fails,
+/// a [`FailoverError`] (wrapped in a [`thrift::Error::User`]) lists the error of each of them
+///
+/// ```
+/// # use std::net::TcpListener;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{FailoverError, FailoverMaker, FromProtocol, MakeThriftConnection};
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // three endpoints, all down
+/// let addrs = (0..3)
+///     .map(|_| TcpListener::bind("127.0.0.1:0")?.local_addr())
+///     .collect::<Result<Vec<_>, _>>()?;
+///
+/// let maker = FailoverMaker::<Client>::from_addrs(addrs.clone());
+/// let err = match maker.make_thrift_connection() {
+///     Err(thrift::Error::User(e)) => e.downcast::<FailoverError>().unwrap(),
+///     _ => panic!("expected a FailoverError"),
+/// };
+/// assert_eq!(err.attempts.len(), 3);
+/// let message = err.to_string();
+/// assert!(message.starts_with("all 3 endpoints failed"));
+/// for addr in &addrs {
+///     assert!(message.contains(&addr.to_string()));
+/// }
+///
+/// // an endpoint that is up is used
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let maker = FailoverMaker::<Client>::from_addrs([addrs[0], listener.local_addr()?]);
+/// assert!(maker.make_thrift_connection().is_ok());
+/// # Ok(())
+/// # }
+/// ```
+pub struct FailoverMaker<T>(
+    MakeThriftConnectionBalanced<MakeThriftConnectionFromAddrs<T, SocketAddr>, Failover>,
+);
+
+impl<T> FailoverMaker<T> {
+    /// Try each of `makers` in order
+    ///
+    /// # Panics
+    ///
+    /// Panics if `makers` is empty
+    pub fn new(makers: Vec<MakeThriftConnectionFromAddrs<T, SocketAddr>>) -> Self {
+        Self(MakeThriftConnectionBalanced::new(
+            makers.into_iter().map(Endpoint::new).collect(),
+            Failover::default(),
+        ))
+    }
+
+    /// Try a [`MakeThriftConnectionFromAddrs`] per item of `addrs`, in order
+    ///
+    /// # Panics
+    ///
+    /// Panics if `addrs` is empty
+    pub fn from_addrs(addrs: impl IntoIterator<Item = SocketAddr>) -> Self {
+        Self::new(
+            addrs
+                .into_iter()
+                .map(MakeThriftConnectionFromAddrs::new)
+                .collect(),
+        )
+    }
+
+    pub fn makers(
+        &self,
+    ) -> impl ExactSizeIterator<Item = &MakeThriftConnectionFromAddrs<T, SocketAddr>> + '_ {
+        self.0.endpoints().iter().map(|endpoint| &endpoint.maker)
+    }
+}
+
+impl<T> Clone for FailoverMaker<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> std::fmt::Debug for FailoverMaker<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FailoverMaker")
+            .field("makers", &self.makers().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<
+        RL: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RT: FromRead<Read = RL>,
+        IP: FromReadTransport<ReadTransport = RT>,
+        WL: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WT: FromWrite<Write = WL>,
+        OP: FromWriteTransport<WriteTransport = WT>,
+        T: FromProtocolWithSocket<InputProtocol = IP, OutputProtocol = OP>,
+    > MakeThriftConnection for FailoverMaker<T>
+{
+    type Error = thrift::Error;
+
+    type Output = T;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let endpoints = self.0.endpoints();
+        let mut attempts = Vec::new();
+        // the policy picks the next endpoint after each failure
+        for _ in 0..endpoints.len() {
+            let endpoint = self
+                .0
+                .pick()
+                .expect("failover picks an endpoint when there is one");
+            match self.0.connect_to(endpoint) {
+                Ok(conn) => return Ok(conn),
+                Err(e) => attempts.push((*endpoints[endpoint].maker.addrs(), e)),
+            }
+        }
+        Err(FailoverError { attempts }.into())
+    }
+}
+
+impl<T> TransportInfo for FailoverMaker<T> {
+    fn transport_kind(&self) -> TransportKind {
+        TransportKind::Tcp
+    }
+}
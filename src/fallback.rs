@@ -0,0 +1,168 @@
+use thrift::TransportErrorKind;
+
+use crate::{MakeThriftConnection, TransportInfo, TransportKind};
+
+/// The default classification used by [`ProtocolFallbackMaker::new`]
+///
+/// A server that doesn't speak the client's protocol either answers with garbage
+/// (a [`thrift::Error::Protocol`] when decoding it) or gives up and closes the connection
+/// (the client then hits an end of file, or a reset if it had unread bytes in flight)
+pub fn is_protocol_mismatch(err: &thrift::Error) -> bool {
+    match err {
+        thrift::Error::Protocol(_) => true,
+        thrift::Error::Transport(e) => matches!(
+            e.kind,
+            TransportErrorKind::EndOfFile | TransportErrorKind::NotOpen
+        ),
+        _ => false,
+    }
+}
+
+/// A [`MakeThriftConnection`] that tries the `primary` maker first, and only if it fails
+/// with an error classified as a protocol mismatch, tries the `fallback` maker
+///
+/// This is meant for protocol migrations (e.g. binary to compact): `primary` and `fallback`
+/// build the same client type over different protocol pipelines. Both makers must have
+/// the same `Output` and `Error`, so clients that are generic over their protocols can use
+/// boxed protocols (`Box<dyn TInputProtocol + Send>`/`Box<dyn TOutputProtocol + Send>`).
+///
+/// A mismatch typically only shows up once something is exchanged with the server,
+/// so the makers should perform a handshake (see [`PreflightMaker`](crate::PreflightMaker))
+///
+#[cfg_attr(all(unix, feature = "testing"), doc = "```")]
+#[cfg_attr(not(all(unix, feature = "testing")), doc = "```ignore")]
+/// # use std::os::unix::net::UnixStream;
+/// # use std::path::PathBuf;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TCompactInputProtocol, TCompactOutputProtocol,
+/// #     TInputProtocol, TMessageIdentifier, TMessageType, TOutputProtocol, TStructIdentifier, TType,
+/// # };
+/// # use thrift::transport::{TBufferedReadTransport, TBufferedWriteTransport, TIoChannel};
+/// # use thrift_pool::{testing::spawn_test_server, MakeThriftConnection, PreflightMaker, ProtocolFallbackMaker};
+/// # struct Client {
+/// #     protocol: &'static str,
+/// #     i_prot: Box<dyn TInputProtocol + Send>,
+/// #     o_prot: Box<dyn TOutputProtocol + Send>,
+/// # }
+/// # impl Client {
+/// #     fn ping(&mut self) -> thrift::Result<()> {
+/// #         self.o_prot.write_message_begin(&TMessageIdentifier::new("ping", TMessageType::Call, 1))?;
+/// #         self.o_prot.write_struct_begin(&TStructIdentifier::new("ping_args"))?;
+/// #         self.o_prot.write_field_stop()?;
+/// #         self.o_prot.write_struct_end()?;
+/// #         self.o_prot.write_message_end()?;
+/// #         self.o_prot.flush()?;
+/// #         self.i_prot.read_message_begin()?;
+/// #         self.i_prot.skip(TType::Struct)?;
+/// #         self.i_prot.read_message_end()
+/// #     }
+/// # }
+/// # struct Maker {
+/// #     path: PathBuf,
+/// #     compact: bool,
+/// # }
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Client;
+/// #     fn make_thrift_connection(&self) -> thrift::Result<Client> {
+/// #         let (read, write) = UnixStream::connect(&self.path)?.split()?;
+/// #         let (read, write) = (TBufferedReadTransport::new(read), TBufferedWriteTransport::new(write));
+/// #         Ok(if self.compact {
+/// #             Client {
+/// #                 protocol: "compact",
+/// #                 i_prot: Box::new(TCompactInputProtocol::new(read)),
+/// #                 o_prot: Box::new(TCompactOutputProtocol::new(write)),
+/// #             }
+/// #         } else {
+/// #             Client {
+/// #                 protocol: "binary",
+/// #                 i_prot: Box::new(TBinaryInputProtocol::new(read, true)),
+/// #                 o_prot: Box::new(TBinaryOutputProtocol::new(write, true)),
+/// #             }
+/// #         })
+/// #     }
+/// # }
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // this server only speaks the binary protocol
+/// let server = spawn_test_server()?;
+/// let path = server.path().to_owned();
+///
+/// let maker = ProtocolFallbackMaker::new(
+///     PreflightMaker::new(Maker { path: path.clone(), compact: true }, Client::ping),
+///     PreflightMaker::new(Maker { path, compact: false }, Client::ping),
+/// );
+/// let conn = maker.make_thrift_connection()?;
+/// assert_eq!(conn.protocol, "binary");
+/// # Ok(())
+/// # }
+/// ```
+pub struct ProtocolFallbackMaker<P, F, C = fn(&thrift::Error) -> bool> {
+    primary: P,
+    fallback: F,
+    classifier: C,
+}
+
+impl<P, F> ProtocolFallbackMaker<P, F> {
+    /// Fall back when [`is_protocol_mismatch`] returns `true`
+    pub fn new(primary: P, fallback: F) -> Self {
+        Self {
+            primary,
+            fallback,
+            classifier: is_protocol_mismatch,
+        }
+    }
+}
+
+impl<P, F, C> ProtocolFallbackMaker<P, F, C> {
+    /// Fall back when `classifier` returns `true` for the error of `primary`
+    pub fn with_classifier(primary: P, fallback: F, classifier: C) -> Self {
+        Self {
+            primary,
+            fallback,
+            classifier,
+        }
+    }
+}
+
+impl<P: Clone, F: Clone, C: Clone> Clone for ProtocolFallbackMaker<P, F, C> {
+    fn clone(&self) -> Self {
+        Self {
+            primary: self.primary.clone(),
+            fallback: self.fallback.clone(),
+            classifier: self.classifier.clone(),
+        }
+    }
+}
+
+impl<P: std::fmt::Debug, F: std::fmt::Debug, C> std::fmt::Debug for ProtocolFallbackMaker<P, F, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProtocolFallbackMaker")
+            .field("primary", &self.primary)
+            .field("fallback", &self.fallback)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P, F, C> MakeThriftConnection for ProtocolFallbackMaker<P, F, C>
+where
+    P: MakeThriftConnection,
+    F: MakeThriftConnection<Output = P::Output, Error = P::Error>,
+    C: Fn(&P::Error) -> bool,
+{
+    type Error = P::Error;
+
+    type Output = P::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        match self.primary.make_thrift_connection() {
+            Err(e) if (self.classifier)(&e) => self.fallback.make_thrift_connection(),
+            result => result,
+        }
+    }
+}
+
+impl<P: TransportInfo, F, C> TransportInfo for ProtocolFallbackMaker<P, F, C> {
+    fn transport_kind(&self) -> TransportKind {
+        self.primary.transport_kind()
+    }
+}
@@ -0,0 +1,133 @@
+use std::ops::{Deref, DerefMut};
+
+use thrift::protocol::TOutputProtocol;
+
+use crate::{FromProtocol, ProtocolAccess, ThriftConnection};
+
+/// A [`ThriftConnection`] that flushes the output protocol of `C`
+/// whenever the connection is returned to the pool
+///
+/// Buffered write transports (like [`thrift::transport::TBufferedWriteTransport`])
+/// hold bytes until they are flushed. If a connection is returned to the pool in the middle
+/// of an operation, those bytes would be sent at the start of the next user's request,
+/// corrupting its stream. Both `r2d2` and `bb8` call [`ThriftConnection::has_broken`] when a
+/// connection is returned, so this wrapper flushes there, and reports the connection
+/// as broken (so the pool drops it) if the flush fails
+///
+/// Since it implements [`FromProtocol`], the wrapper can be used directly
+/// as the connection type of [`MakeThriftConnectionFromAddrs`](crate::MakeThriftConnectionFromAddrs).
+/// It derefs to `C`
+///
+/// ```
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{TBufferChannel, TBufferedWriteTransport};
+/// # use thrift_pool::{FlushingConnection, FromProtocol, ProtocolAccess, ThriftConnection};
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ProtocolAccess for MyThriftClient<Ip, Op> {
+/// #     fn input_protocol_mut(&mut self) -> &mut Ip {
+/// #         &mut self.i_prot
+/// #     }
+/// #     fn output_protocol_mut(&mut self) -> &mut Op {
+/// #         &mut self.o_prot
+/// #     }
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ThriftConnection for MyThriftClient<Ip, Op> {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// let make_conn = |channel: &TBufferChannel| {
+///     FlushingConnection::<MyThriftClient<_, _>>::from_protocol(
+///         TBinaryInputProtocol::new(channel.clone(), true),
+///         TBinaryOutputProtocol::new(TBufferedWriteTransport::new(channel.clone()), true),
+///     )
+/// };
+///
+/// // pending bytes are flushed when the connection is returned
+/// let channel = TBufferChannel::with_capacity(0, 16);
+/// let mut conn = make_conn(&channel);
+/// conn.output_protocol_mut().write_i32(42).unwrap();
+/// assert!(channel.write_bytes().is_empty());
+/// assert!(!conn.has_broken());
+/// assert_eq!(channel.write_bytes(), 42i32.to_be_bytes());
+///
+/// // the connection is reported broken if the flush fails
+/// let channel = TBufferChannel::with_capacity(0, 0);
+/// let mut conn = make_conn(&channel);
+/// conn.output_protocol_mut().write_i32(42).unwrap();
+/// assert!(conn.has_broken());
+/// ```
+#[derive(Debug)]
+pub struct FlushingConnection<C>(C);
+
+impl<C> FlushingConnection<C> {
+    pub fn new(conn: C) -> Self {
+        Self(conn)
+    }
+
+    pub fn into_inner(self) -> C {
+        self.0
+    }
+}
+
+impl<C> Deref for FlushingConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<C> DerefMut for FlushingConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<C: FromProtocol> FromProtocol for FlushingConnection<C> {
+    type InputProtocol = C::InputProtocol;
+
+    type OutputProtocol = C::OutputProtocol;
+
+    fn from_protocol(
+        input_protocol: Self::InputProtocol,
+        output_protocol: Self::OutputProtocol,
+    ) -> Self {
+        Self(C::from_protocol(input_protocol, output_protocol))
+    }
+}
+
+impl<C: ProtocolAccess> ProtocolAccess for FlushingConnection<C> {
+    fn input_protocol_mut(&mut self) -> &mut Self::InputProtocol {
+        self.0.input_protocol_mut()
+    }
+
+    fn output_protocol_mut(&mut self) -> &mut Self::OutputProtocol {
+        self.0.output_protocol_mut()
+    }
+}
+
+impl<C: ThriftConnection + ProtocolAccess> ThriftConnection for FlushingConnection<C> {
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.0.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.0.output_protocol_mut().flush().is_err() || self.0.has_broken()
+    }
+}
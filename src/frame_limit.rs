@@ -0,0 +1,158 @@
+use std::io::{self, Read};
+
+use crate::FromRead;
+
+/// The max frame size of [`MaxFrameSizeRead`] by default, that of the other Thrift libraries
+pub const DEFAULT_MAX_FRAME_SIZE: u32 = 16_384_000;
+
+/// The error returned by [`MaxFrameSizeRead`] reading the length prefix of a frame
+/// larger than its max frame size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTooLarge {
+    /// The size announced by the length prefix
+    pub size: u32,
+    pub max: u32,
+}
+
+impl FrameTooLarge {
+    /// Recover the [`FrameTooLarge`] that failed a call, if any
+    ///
+    /// Thrift turns [`io::Error`]s into [`thrift::TransportError`]s keeping only their message,
+    /// so the sizes are parsed back from it
+    pub fn classify(e: &thrift::Error) -> Option<Self> {
+        let thrift::Error::Transport(e) = e else {
+            return None;
+        };
+        let rest = e.message.strip_prefix("a frame of ")?;
+        let (size, rest) = rest.split_once(" bytes exceeds the max frame size of ")?;
+        let max = rest.strip_suffix(" bytes")?;
+        Some(Self {
+            size: size.parse().ok()?,
+            max: max.parse().ok()?,
+        })
+    }
+}
+
+impl std::fmt::Display for FrameTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "a frame of {} bytes exceeds the max frame size of {} bytes",
+            self.size, self.max
+        )
+    }
+}
+
+impl std::error::Error for FrameTooLarge {}
+
+impl From<FrameTooLarge> for io::Error {
+    fn from(e: FrameTooLarge) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// A [`Read`] layer rejecting frames larger than `MAX` bytes with [`FrameTooLarge`],
+/// before [`TFramedReadTransport`](thrift::transport::TFramedReadTransport) allocates them
+///
+/// The framed read transport allocates a buffer of the size announced by the length prefix of
+/// every frame: a broken or malicious server can make it allocate gigabytes. This layer goes
+/// between the [`ReadHalf`](thrift::transport::ReadHalf) and the framed read transport in the
+/// client type. It holds back each length prefix until it has checked it, so the transport
+/// never sees one too large. Like the other layers it is created by the maker without any
+/// state, so the max is a parameter of the type
+///
+/// ```
+/// # use thrift::protocol::{TBinaryInputProtocol, TInputProtocol};
+/// # use thrift::transport::{ReadHalf, TFramedReadTransport, TTcpChannel};
+/// # use thrift_pool::{FrameTooLarge, MaxFrameSizeRead};
+/// // as used in the client type of a `MakeThriftConnectionFromAddrs`
+/// type InputProtocol =
+///     TBinaryInputProtocol<TFramedReadTransport<MaxFrameSizeRead<ReadHalf<TTcpChannel>, 1024>>>;
+///
+/// let read = |bytes: &'static [u8]| {
+///     let read = MaxFrameSizeRead::<_, 1024>::new(bytes);
+///     TBinaryInputProtocol::new(TFramedReadTransport::new(read), true).read_i32()
+/// };
+///
+/// // a frame of 4 bytes
+/// assert_eq!(read(&[0, 0, 0, 4, 0, 0, 0, 42])?, 42);
+///
+/// // a frame of 2GB
+/// let e = read(&[0x7f, 0xff, 0xff, 0xff, 0, 0, 0, 42]).unwrap_err();
+/// assert_eq!(
+///     FrameTooLarge::classify(&e),
+///     Some(FrameTooLarge { size: 0x7fff_ffff, max: 1024 })
+/// );
+/// # Ok::<(), thrift::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct MaxFrameSizeRead<R, const MAX: u32 = DEFAULT_MAX_FRAME_SIZE> {
+    read: R,
+    /// The length prefix being read, then handed out
+    prefix: [u8; 4],
+    /// How many bytes of `prefix` were read
+    read_prefix: usize,
+    /// How many bytes of the checked `prefix` are still to hand out
+    pending_prefix: usize,
+    /// How many bytes of the current frame are still to read
+    remaining: usize,
+}
+
+impl<R, const MAX: u32> MaxFrameSizeRead<R, MAX> {
+    pub fn new(read: R) -> Self {
+        Self {
+            read,
+            prefix: [0; 4],
+            read_prefix: 0,
+            pending_prefix: 0,
+            remaining: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.read
+    }
+}
+
+impl<R: Read, const MAX: u32> Read for MaxFrameSizeRead<R, MAX> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pending_prefix == 0 {
+            if self.remaining > 0 {
+                let len = buf.len().min(self.remaining);
+                let n = self.read.read(&mut buf[..len])?;
+                self.remaining -= n;
+                return Ok(n);
+            }
+            while self.read_prefix < self.prefix.len() {
+                match self.read.read(&mut self.prefix[self.read_prefix..])? {
+                    // the transport finds out about a truncated prefix
+                    0 => return Ok(0),
+                    n => self.read_prefix += n,
+                }
+            }
+            let size = u32::from_be_bytes(self.prefix);
+            // a negative size is as large for the transport
+            if size > MAX || i32::try_from(size).is_err() {
+                return Err(FrameTooLarge { size, max: MAX }.into());
+            }
+            self.read_prefix = 0;
+            self.pending_prefix = self.prefix.len();
+            self.remaining = size as usize;
+        }
+        let start = self.prefix.len() - self.pending_prefix;
+        let n = buf.len().min(self.pending_prefix);
+        buf[..n].copy_from_slice(&self.prefix[start..start + n]);
+        self.pending_prefix -= n;
+        Ok(n)
+    }
+}
+
+impl<R: Read, const MAX: u32> FromRead for MaxFrameSizeRead<R, MAX> {
+    type Read = R;
+    fn from_read(read: R) -> Self {
+        Self::new(read)
+    }
+}
@@ -0,0 +1,181 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::{MakeThriftConnection, ThriftConnection, TransportInfo, TransportKind};
+
+/// A connection created by [`FrameSizeMaker`], carrying the max frame size
+/// agreed with the server. It derefs to `C`
+#[derive(Debug)]
+pub struct FrameSizeConnection<C> {
+    conn: C,
+    client_max_frame_size: Option<u32>,
+    server_max_frame_size: Option<u32>,
+}
+
+impl<C> FrameSizeConnection<C> {
+    /// The largest frame both ends accept: the smaller of the client's configured size
+    /// and the server's advertised one, or whichever is known
+    pub fn max_frame_size(&self) -> Option<u32> {
+        match (self.client_max_frame_size, self.server_max_frame_size) {
+            (Some(client), Some(server)) => Some(client.min(server)),
+            (client, server) => client.or(server),
+        }
+    }
+
+    /// The max frame size the server advertised, if it did
+    pub fn server_max_frame_size(&self) -> Option<u32> {
+        self.server_max_frame_size
+    }
+
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+impl<C> Deref for FrameSizeConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<C> DerefMut for FrameSizeConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<C: ThriftConnection> ThriftConnection for FrameSizeConnection<C> {
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.conn.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.conn.has_broken()
+    }
+}
+
+/// A [`MakeThriftConnection`] that asks every new connection of the inner maker `M`
+/// for the max frame size the server accepts
+///
+/// Thrift has no standard way to advertise it, so `probe` issues whatever call the service
+/// exposes for that, and returns `None` if the server doesn't say. The result is exposed by
+/// [`FrameSizeConnection::max_frame_size`], to size framed messages by. When the client's own
+/// size (see [`FrameSizeMaker::with_max_frame_size`]) is larger than the server's, the server's
+/// is used, and with the `tracing` feature a warning is emitted
+///
+/// ```
+/// # use thrift_pool::{FrameSizeMaker, MakeThriftConnection};
+/// // a connection to a server advertising a small frame size
+/// # struct Conn {
+/// #     advertised: Option<u32>,
+/// # }
+/// # struct Maker(Option<u32>);
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn { advertised: self.0 })
+/// #     }
+/// # }
+/// let probe = |conn: &mut Conn| Ok(conn.advertised);
+///
+/// // the client adapts to the server
+/// let maker = FrameSizeMaker::new(Maker(Some(1024)), probe).with_max_frame_size(16 * 1024 * 1024);
+/// let conn = maker.make_thrift_connection()?;
+/// assert_eq!(conn.server_max_frame_size(), Some(1024));
+/// assert_eq!(conn.max_frame_size(), Some(1024));
+///
+/// // a smaller client size is kept
+/// let maker = FrameSizeMaker::new(Maker(Some(1024)), probe).with_max_frame_size(512);
+/// assert_eq!(maker.make_thrift_connection()?.max_frame_size(), Some(512));
+///
+/// // a server that doesn't advertise anything
+/// let maker = FrameSizeMaker::new(Maker(None), probe).with_max_frame_size(512);
+/// let conn = maker.make_thrift_connection()?;
+/// assert_eq!(conn.server_max_frame_size(), None);
+/// assert_eq!(conn.max_frame_size(), Some(512));
+/// # Ok::<(), thrift::Error>(())
+/// ```
+pub struct FrameSizeMaker<M, F> {
+    maker: M,
+    probe: F,
+    max_frame_size: Option<u32>,
+}
+
+impl<M, F> FrameSizeMaker<M, F> {
+    pub fn new(maker: M, probe: F) -> Self {
+        Self {
+            maker,
+            probe,
+            max_frame_size: None,
+        }
+    }
+
+    /// The max frame size the client is configured with (defaults to none)
+    pub fn with_max_frame_size(mut self, max_frame_size: u32) -> Self {
+        self.max_frame_size = Some(max_frame_size);
+        self
+    }
+
+    pub fn max_frame_size(&self) -> Option<u32> {
+        self.max_frame_size
+    }
+}
+
+impl<M: Clone, F: Clone> Clone for FrameSizeMaker<M, F> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            probe: self.probe.clone(),
+            max_frame_size: self.max_frame_size,
+        }
+    }
+}
+
+impl<M: std::fmt::Debug, F> std::fmt::Debug for FrameSizeMaker<M, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrameSizeMaker")
+            .field("maker", &self.maker)
+            .field("max_frame_size", &self.max_frame_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M, F> MakeThriftConnection for FrameSizeMaker<M, F>
+where
+    M: MakeThriftConnection,
+    F: Fn(&mut M::Output) -> Result<Option<u32>, M::Error>,
+{
+    type Error = M::Error;
+
+    type Output = FrameSizeConnection<M::Output>;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let mut conn = self.maker.make_thrift_connection()?;
+        let server_max_frame_size = (self.probe)(&mut conn)?;
+        #[cfg(feature = "tracing")]
+        if let (Some(client), Some(server)) = (self.max_frame_size, server_max_frame_size) {
+            if client > server {
+                tracing::warn!(
+                    client_max_frame_size = client,
+                    server_max_frame_size = server,
+                    "the configured max frame size exceeds the server's, using the server's"
+                );
+            }
+        }
+        Ok(FrameSizeConnection {
+            conn,
+            client_max_frame_size: self.max_frame_size,
+            server_max_frame_size,
+        })
+    }
+}
+
+impl<M: TransportInfo, F> TransportInfo for FrameSizeMaker<M, F> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,82 @@
+use bb8::AddError;
+use tokio::task::JoinSet;
+
+/// Create up to `n` connections to `pool` concurrently, and add them to it as idle
+/// connections, returning how many were added
+///
+/// `bb8` grows a pool one connection per waiting checkout: calling this when demand spikes
+/// opens several connections at once instead. Connects go through the manager, so they
+/// follow [`ThriftConnectionManager::with_max_concurrent_connects`](crate::ThriftConnectionManager::with_max_concurrent_connects).
+/// The pool never grows past its max size: once it is full, the remaining connects
+/// are cancelled, and the connections they already made are dropped
+///
+/// ```
+/// # use std::time::Duration;
+/// # use thrift_pool::{grow, MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+/// # #[derive(Debug)]
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = bb8::Pool::builder()
+///     .max_size(5)
+///     .build(ThriftConnectionManager::new(Maker).with_max_concurrent_connects(2))
+///     .await?;
+/// assert_eq!(pool.state().connections, 0);
+///
+/// assert_eq!(grow(&pool, 3).await?, 3);
+/// assert_eq!(pool.state().connections, 3);
+/// assert_eq!(pool.state().idle_connections, 3);
+///
+/// // capped at the max size
+/// assert_eq!(grow(&pool, 3).await?, 2);
+/// assert_eq!(pool.state().connections, 5);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns the first error of a connect, once the other ones are done
+/// (their connections are still added)
+pub async fn grow<M: bb8::ManageConnection>(pool: &bb8::Pool<M>, n: u32) -> Result<u32, M::Error> {
+    let mut connects = JoinSet::new();
+    for _ in 0..n {
+        let pool = pool.clone();
+        connects.spawn(async move { pool.dedicated_connection().await });
+    }
+
+    let mut added = 0;
+    let mut first_err = None;
+    while let Some(res) = connects.join_next().await {
+        let conn = match res {
+            Ok(Ok(conn)) => conn,
+            Ok(Err(e)) => {
+                first_err.get_or_insert(e);
+                continue;
+            }
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            // cancelled once the pool is full
+            Err(_) => continue,
+        };
+        match pool.add(conn) {
+            Ok(()) => added += 1,
+            Err(AddError::NoCapacity(_)) => connects.abort_all(),
+            Err(AddError::Broken(_)) => {}
+        }
+    }
+    first_err.map_or(Ok(added), Err)
+}
@@ -0,0 +1,232 @@
+use std::{
+    io::{self, Read, Write},
+    net::ToSocketAddrs,
+};
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+use thrift::transport::{ReadHalf, TTcpChannel, WriteHalf};
+
+use crate::{
+    FromProtocolWithSocket, FromRead, FromReadTransport, FromWrite, FromWriteTransport,
+    MakeThriftConnection, MakeThriftConnectionFromAddrs, TransportInfo, TransportKind,
+};
+
+/// The size of the challenges and proofs of [`TokenHandshakeMaker`]
+const LEN: usize = 32;
+
+/// The error returned by [`TokenHandshakeMaker`] when the server's proof doesn't verify:
+/// it doesn't know the shared secret
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandshakeFailed;
+
+impl std::fmt::Display for HandshakeFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the server failed the token handshake")
+    }
+}
+
+impl std::error::Error for HandshakeFailed {}
+
+impl From<HandshakeFailed> for thrift::Error {
+    fn from(e: HandshakeFailed) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// HMAC-SHA256 of `role` followed by `challenge`
+fn proof(secret: &[u8], role: &[u8], challenge: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC takes keys of any size");
+    mac.update(role);
+    mac.update(challenge);
+    mac
+}
+
+fn challenge() -> io::Result<[u8; LEN]> {
+    let mut challenge = [0; LEN];
+    getrandom::fill(&mut challenge).map_err(io::Error::other)?;
+    Ok(challenge)
+}
+
+/// Run the server side of [`TokenHandshakeMaker`]'s handshake on an accepted `stream`
+///
+/// # Errors
+///
+/// Returns an [`io::ErrorKind::PermissionDenied`] error if the client's proof doesn't verify,
+/// or the error of reading or writing `stream`
+pub fn accept_token_handshake(stream: &mut (impl Read + Write), secret: &[u8]) -> io::Result<()> {
+    let mut client_challenge = [0; LEN];
+    stream.read_exact(&mut client_challenge)?;
+    let server_challenge = challenge()?;
+    let server_proof = proof(secret, b"server", &client_challenge).finalize();
+    stream.write_all(&server_proof.into_bytes())?;
+    stream.write_all(&server_challenge)?;
+    stream.flush()?;
+
+    let mut client_proof = [0; LEN];
+    stream.read_exact(&mut client_proof)?;
+    proof(secret, b"client", &server_challenge)
+        .verify_slice(&client_proof)
+        .map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the client failed the token handshake",
+            )
+        })
+}
+
+/// A [`MakeThriftConnection`] that authenticates the server, and authenticates itself
+/// to the server, with a secret they share, right after connecting and before the client
+/// is built
+///
+/// The handshake is a challenge-response on the raw socket (below any transport), so this
+/// wraps a [`MakeThriftConnectionFromAddrs`]:
+///
+/// 1. the client sends 32 random bytes
+/// 2. the server answers with the HMAC-SHA256 of `b"server"` followed by those bytes, under
+///    the secret, and 32 random bytes of its own
+/// 3. the client fails with [`HandshakeFailed`] if the server's HMAC doesn't verify, and
+///    otherwise answers with the HMAC of `b"client"` followed by the server's bytes
+///
+/// Neither side sends the secret itself, and random challenges prevent replaying a previous
+/// handshake. [`accept_token_handshake`] runs the server side. A server that never answers
+/// blocks the connect, unless [`MakeThriftConnectionFromAddrs::with_read_timeout`] is set.
+/// This only authenticates: the connection stays unencrypted
+///
+/// ```
+/// # use std::net::TcpListener;
+/// # use std::sync::mpsc;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{
+/// #     accept_token_handshake, FromProtocol, HandshakeFailed, MakeThriftConnection,
+/// #     MakeThriftConnectionFromAddrs, TokenHandshakeMaker,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // a server knowing the secret
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let addr = listener.local_addr()?;
+/// let (verdicts, verdict) = mpsc::channel();
+/// std::thread::spawn(move || {
+///     for stream in listener.incoming() {
+///         let ok = accept_token_handshake(&mut stream.unwrap(), b"s3cr3t").is_ok();
+///         verdicts.send(ok).unwrap();
+///     }
+/// });
+///
+/// let maker = MakeThriftConnectionFromAddrs::<Client, _>::new(addr);
+///
+/// let _conn = TokenHandshakeMaker::new(maker.clone(), b"s3cr3t".to_vec()).make_thrift_connection()?;
+/// assert!(verdict.recv()?);
+///
+/// // the server's proof doesn't verify with another secret
+/// let err = TokenHandshakeMaker::new(maker, b"wrong".to_vec())
+///     .make_thrift_connection()
+///     .err()
+///     .unwrap();
+/// let thrift::Error::User(e) = err else {
+///     panic!("unexpected error: {err}");
+/// };
+/// assert!(e.is::<HandshakeFailed>());
+/// # Ok(())
+/// # }
+/// ```
+pub struct TokenHandshakeMaker<T, S> {
+    maker: MakeThriftConnectionFromAddrs<T, S>,
+    secret: Vec<u8>,
+}
+
+impl<T, S> TokenHandshakeMaker<T, S> {
+    pub fn new(maker: MakeThriftConnectionFromAddrs<T, S>, secret: Vec<u8>) -> Self {
+        Self { maker, secret }
+    }
+
+    pub fn maker(&self) -> &MakeThriftConnectionFromAddrs<T, S> {
+        &self.maker
+    }
+
+    /// Run the client side of the handshake on `stream`
+    fn handshake(&self, stream: &mut (impl Read + Write)) -> thrift::Result<()> {
+        let client_challenge = challenge()?;
+        stream.write_all(&client_challenge)?;
+        stream.flush()?;
+
+        let mut reply = [0; 2 * LEN];
+        stream.read_exact(&mut reply)?;
+        let (server_proof, server_challenge) = reply.split_at(LEN);
+        proof(&self.secret, b"server", &client_challenge)
+            .verify_slice(server_proof)
+            .map_err(|_| HandshakeFailed)?;
+
+        let client_proof = proof(&self.secret, b"client", server_challenge).finalize();
+        stream.write_all(&client_proof.into_bytes())?;
+        stream.flush()?;
+        Ok(())
+    }
+}
+
+impl<T, S: Clone> Clone for TokenHandshakeMaker<T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            secret: self.secret.clone(),
+        }
+    }
+}
+
+impl<T, S: std::fmt::Debug> std::fmt::Debug for TokenHandshakeMaker<T, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the secret is left out
+        f.debug_struct("TokenHandshakeMaker")
+            .field("maker", &self.maker)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<
+        S: ToSocketAddrs + Clone,
+        RL: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RT: FromRead<Read = RL>,
+        IP: FromReadTransport<ReadTransport = RT>,
+        WL: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WT: FromWrite<Write = WL>,
+        OP: FromWriteTransport<WriteTransport = WT>,
+        T: FromProtocolWithSocket<InputProtocol = IP, OutputProtocol = OP>,
+    > MakeThriftConnection for TokenHandshakeMaker<T, S>
+{
+    type Error = thrift::Error;
+
+    type Output = T;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let mut stream = self.maker.open_stream()?;
+        self.handshake(&mut stream)?;
+        self.maker.connection_from_stream(stream)
+    }
+}
+
+impl<T, S> TransportInfo for TokenHandshakeMaker<T, S> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
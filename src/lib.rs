@@ -115,6 +115,8 @@
 //!   let manager = MakeThriftConnectionFromAddrs::<Client, _>::new("localhost:9090")
 //!                 .into_connection_manager();
 //!   
+//! # #[cfg(all(feature = "impl-bb8", feature = "impl-r2d2"))]
+//! # {
 //!   // we're able to create bb8 and r2d2 Connection Pools
 //!   let bb8 = bb8::Pool::builder().build(manager.clone()).await?;
 //!   let r2d2 = r2d2::Pool::builder().build(manager)?;
@@ -122,6 +124,7 @@
 //!   // get a connection
 //!   let conn1 = bb8.get().await?;
 //!   let conn2 = r2d2.get()?;
+//! # }
 //! #  Ok(())
 //! # }
 //! ```
@@ -131,18 +134,237 @@
 //! # Examples
 //!
 //! - [hbase-thrift](https://github.com/midnightexigent/hbase-thrift-rs) -- the project from which this
-//! library was extracted. implements Connection Pools for the client generated from the
-//! [`HBase` Thrift Spec](https://github.com/apache/hbase/tree/master/hbase-thrift/src/main/resources/org/apache/hadoop/hbase/thrift)
+//!   library was extracted. implements Connection Pools for the client generated from the
+//!   [`HBase` Thrift Spec](https://github.com/apache/hbase/tree/master/hbase-thrift/src/main/resources/org/apache/hadoop/hbase/thrift)
 //! - [thrift-pool-tutorial](https://github.com/midnightexigent/thrift-pool-tutorial-rs) -- implements
-//! Connection Pools for the client used in the official
-//! [thrift tutorial](https://github.com/apache/thrift/tree/master/tutorial)
+//!   Connection Pools for the client used in the official
+//!   [thrift tutorial](https://github.com/apache/thrift/tree/master/tutorial)
 
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+use std::sync::Arc;
 use std::{
     io::{self, Read, Write},
     marker::PhantomData,
-    net::ToSocketAddrs,
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::Duration,
 };
 
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+use std::collections::HashMap;
+
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+mod affinity;
+mod aging;
+#[cfg(feature = "impl-bb8")]
+mod async_conn;
+mod background;
+mod balance;
+mod boxed;
+mod budget;
+#[cfg(feature = "buffer-pool")]
+mod buffer_pool;
+#[cfg(feature = "impl-bb8")]
+mod cancel;
+mod channel;
+mod circuit;
+mod close;
+mod coalesce;
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+mod compat;
+#[cfg(feature = "serde")]
+mod config;
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+mod connect_limit;
+mod defaults;
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+mod drain;
+mod env;
+mod error_adapter;
+mod error_history;
+#[cfg(feature = "impl-bb8")]
+mod events;
+mod failover;
+mod fallback;
+mod flush;
+mod frame_limit;
+mod frame_size;
+#[cfg(feature = "impl-bb8")]
+mod grow;
+#[cfg(feature = "handshake")]
+mod handshake;
+mod limit;
+mod local_validity;
+mod middleware;
+#[cfg(feature = "impl-bb8")]
+mod migrate;
+mod multiplex;
+mod observe;
+#[cfg(feature = "otel")]
+mod otel;
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+mod panic_guard;
+mod pause;
+#[cfg(feature = "metrics")]
+mod pool_metrics;
+mod preamble;
+mod preflight;
+mod proxy;
+mod rate_limit;
+mod raw;
+mod read_only;
+mod reset;
+mod resolve;
+mod retry;
+mod rng;
+mod round_robin;
+#[cfg(feature = "impl-bb8")]
+mod run_error;
+mod shared;
+#[cfg(feature = "impl-bb8")]
+mod shrink;
+#[cfg(feature = "serde")]
+mod snapshot;
+#[cfg(feature = "ssh")]
+mod ssh;
+mod swap;
+#[cfg(feature = "wire-debug")]
+mod tap;
+#[cfg(feature = "impl-bb8")]
+mod tenant;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod timeout;
+mod traced;
+mod ttfb;
+#[cfg(unix)]
+mod unix;
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+mod validate;
+mod validate_config;
+mod validation_cache;
+mod version;
+mod warmup;
+mod weighted;
+mod zone;
+
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+pub use affinity::Affinity;
+pub use aging::{AgingConnection, AgingMaker};
+#[cfg(feature = "impl-bb8")]
+pub use async_conn::{AsyncThriftConnection, AsyncThriftConnectionManager, SpawnBlocking};
+pub use background::{BackgroundConnectFailed, BackgroundConnectMaker, BackgroundConnection};
+pub use balance::{
+    BalancedConnection, Failover, LatencyAware, LatencyObserver, LeastConnections, LoadBalancer,
+    MakeThriftConnectionBalanced, NoEndpointPicked, RoundRobin, WeightedRandom,
+};
+#[cfg(feature = "serde")]
+pub use balance::{BalancedSnapshot, LatencyAwareSnapshot};
+pub use boxed::{BoxedConnection, BoxedError, BoxedMaker};
+pub use budget::{BudgetExhausted, BudgetedConnection, BudgetedMaker, ConnectionBudget};
+#[cfg(feature = "buffer-pool")]
+pub use buffer_pool::{
+    buffer_pool_stats, set_buffer_pool_capacity, BufferPoolStats, PooledBufferedReadTransport,
+    PooledBufferedWriteTransport, POOLED_BUFFER_SIZE,
+};
+pub use channel::{ChannelConsumed, MakeThriftConnectionFromChannel};
+pub use circuit::{CircuitBreakerMaker, CircuitOpen, CircuitState};
+pub use close::{BrokenReason, CloseAwareConnection};
+pub use coalesce::{CoalescingConnection, CoalescingWrite};
+#[cfg(feature = "impl-bb8")]
+pub use compat::{ensure_bb8_compatible, Bb8Pool, Bb8PooledConnection};
+#[cfg(feature = "impl-r2d2")]
+pub use compat::{ensure_r2d2_compatible, R2d2Pool, R2d2PooledConnection};
+#[cfg(feature = "serde")]
+pub use config::{
+    BoxedInputProtocol, BoxedOutputProtocol, Framing, MakeThriftConnectionFromConfig, Protocol,
+    ThriftPoolConfig,
+};
+pub use defaults::{default_pool_size, DEFAULT_CONNECTION_TIMEOUT};
+#[cfg(feature = "impl-bb8")]
+pub use drain::drain_idle_bb8;
+#[cfg(feature = "impl-r2d2")]
+pub use drain::drain_idle_r2d2;
+pub use env::{
+    EnvConfigError, MakeThriftConnectionFromEnv, THRIFT_ADDR, THRIFT_CONNECT_TIMEOUT_MS, THRIFT_TLS,
+};
+pub use error_adapter::{ErrorAdapter, ErrorAdapterConnection, ErrorAdapterMaker};
+pub use error_history::ErrorHistoryMaker;
+#[cfg(feature = "impl-bb8")]
+pub use events::{PoolEvent, PoolEventKind};
+pub use failover::{FailoverError, FailoverMaker};
+pub use fallback::{is_protocol_mismatch, ProtocolFallbackMaker};
+pub use flush::FlushingConnection;
+pub use frame_limit::{FrameTooLarge, MaxFrameSizeRead, DEFAULT_MAX_FRAME_SIZE};
+pub use frame_size::{FrameSizeConnection, FrameSizeMaker};
+#[cfg(feature = "impl-bb8")]
+pub use grow::grow;
+#[cfg(feature = "handshake")]
+pub use handshake::{accept_token_handshake, HandshakeFailed, TokenHandshakeMaker};
+pub use limit::{ByteLimitExceeded, ByteLimitedRead};
+pub use local_validity::{LocalValidityConnection, LocalValidityMaker, StaleConnection};
+pub use middleware::MiddlewareMaker;
+#[cfg(feature = "impl-bb8")]
+pub use migrate::{migrate_connection, MigrateError};
+pub use multiplex::{MultiplexedPool, MultiplexedRead, MultiplexedWrite};
+pub use observe::{ConnectObserver, EndpointCounts, EndpointStats, ObservedMaker};
+#[cfg(feature = "otel")]
+pub use otel::OtelMaker;
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+pub use panic_guard::PanicGuard;
+pub use pause::{PausableMaker, Paused};
+#[cfg(feature = "metrics")]
+pub use pool_metrics::{
+    THRIFT_POOL_BROKEN_TOTAL, THRIFT_POOL_CONNECTS_TOTAL, THRIFT_POOL_CONNECT_DURATION_SECONDS,
+    THRIFT_POOL_VALIDATIONS_TOTAL,
+};
+pub use preamble::PreambleMaker;
+pub use preflight::PreflightMaker;
+pub use proxy::{ProxyProtocolMaker, ProxyProtocolVersion};
+pub use rate_limit::{RateLimitExceeded, RateLimitMode, RateLimitedMaker};
+pub use raw::RawChannelConnection;
+pub use read_only::ReadOnlyConnection;
+pub use reset::{InterruptedCall, ResettingConnection};
+#[cfg(feature = "impl-bb8")]
+pub use resolve::BackgroundResolvedAddrs;
+pub use resolve::{ResolvedAddrs, ResolverFallback};
+#[cfg(feature = "impl-bb8")]
+pub use retry::get_with_retry_bb8;
+#[cfg(feature = "impl-r2d2")]
+pub use retry::get_with_retry_r2d2;
+pub use retry::{ConnectErrorClass, RetryConfig, RetryingMaker};
+pub use round_robin::RoundRobinMaker;
+#[cfg(feature = "impl-bb8")]
+pub use run_error::ThriftPoolRunError;
+pub use shared::SharedMaker;
+#[cfg(feature = "impl-bb8")]
+pub use shrink::IdleShrinker;
+#[cfg(feature = "serde")]
+pub use snapshot::{AddrsSnapshot, Snapshot};
+#[cfg(feature = "ssh")]
+pub use ssh::{MakeThriftConnectionFromSshTunnel, SshAuth, SshParams, SshTunnelError};
+pub use swap::SwappableMaker;
+#[cfg(feature = "wire-debug")]
+pub use tap::{hexdump, StderrTap, TappingRead, TappingWrite, WireDirection, WireTap};
+#[cfg(feature = "impl-bb8")]
+pub use tenant::TenantPools;
+pub use timeout::{Deadline, TimeoutConnection};
+pub use traced::{ConnectionId, TracedConnection};
+pub use ttfb::{TtfbConnection, TtfbMaker, TtfbRead};
+#[cfg(unix)]
+pub use unix::MakeThriftConnectionFromUnixSocket;
+#[cfg(feature = "impl-bb8")]
+pub use validate::validate_all_bb8;
+#[cfg(feature = "impl-r2d2")]
+pub use validate::validate_all_r2d2;
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+pub use validate::ValidationReport;
+pub use validate_config::{ConfigError, ValidateAddrs};
+pub use validation_cache::{CachedValidationConnection, CachedValidationMaker};
+pub use version::{VersionCheckMaker, VersionMismatch};
+pub use warmup::{WarmingUp, WarmupConnection, WarmupMaker};
+pub use weighted::WeightedRandomMaker;
+pub use zone::{Endpoint, ZoneAwareMaker, ZONE_LABEL};
+
 use thrift::{
     protocol::{
         TBinaryInputProtocol, TBinaryOutputProtocol, TCompactInputProtocol, TCompactOutputProtocol,
@@ -154,6 +376,13 @@ use thrift::{
     },
 };
 
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+use connect_limit::ConnectLimit;
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+pub use connect_limit::Priority;
+#[cfg(feature = "impl-bb8")]
+use events::{EventSink, Events};
+
 /// Create self from a [`Read`]
 pub trait FromRead: TReadTransport {
     type Read: io::Read;
@@ -173,6 +402,16 @@ impl<R: Read> FromRead for TFramedReadTransport<R> {
     }
 }
 
+/// The makers build the read transport from a layer that is itself built from the
+/// [`ReadHalf`] of the channel. This is the "no layer" case,
+/// see [`ByteLimitedRead`] for an actual layer
+impl<C: Read> FromRead for ReadHalf<C> {
+    type Read = Self;
+    fn from_read(read: Self) -> Self {
+        read
+    }
+}
+
 /// Create self from a [`Write`]
 pub trait FromWrite: TWriteTransport {
     type Write: io::Write;
@@ -194,6 +433,15 @@ impl<W: Write> FromWrite for TFramedWriteTransport<W> {
     }
 }
 
+/// The makers build the write transport from a layer that is itself built from the
+/// [`WriteHalf`] of the channel. This is the "no layer" case
+impl<C: Write> FromWrite for WriteHalf<C> {
+    type Write = Self;
+    fn from_write(write: Self) -> Self {
+        write
+    }
+}
+
 /// Create self from a [`TReadTransport`]
 pub trait FromReadTransport: TInputProtocol {
     type ReadTransport: TReadTransport;
@@ -247,6 +495,173 @@ pub trait FromProtocol {
     ) -> Self;
 }
 
+/// Like [`FromProtocol`], but also receive the address the connection was made to
+///
+/// Implement this instead of [`FromProtocol`] for a client that needs to know which backend
+/// it is connected to (e.g. to log it). [`MakeThriftConnectionFromAddrs`] creates its clients
+/// through this trait, and every [`FromProtocol`] implements it by ignoring the address
+///
+/// ```
+/// # use std::net::{SocketAddr, TcpListener};
+/// # use thrift::protocol::{TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol};
+/// # use thrift::transport::{ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf};
+/// # use thrift_pool::{FromProtocolWithAddr, MakeThriftConnection, MakeThriftConnectionFromAddrs};
+/// struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+///     i_prot: Ip,
+///     o_prot: Op,
+///     peer: SocketAddr,
+/// }
+///
+/// impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocolWithAddr for MyThriftClient<Ip, Op> {
+///     type InputProtocol = Ip;
+///
+///     type OutputProtocol = Op;
+///
+///     fn from_protocol_with_addr(
+///         input_protocol: Self::InputProtocol,
+///         output_protocol: Self::OutputProtocol,
+///         addr: SocketAddr,
+///     ) -> Self {
+///         MyThriftClient {
+///             i_prot: input_protocol,
+///             o_prot: output_protocol,
+///             peer: addr,
+///         }
+///     }
+/// }
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let addr = listener.local_addr()?;
+///
+/// let client = MakeThriftConnectionFromAddrs::<Client, _>::new(addr).make_thrift_connection()?;
+/// assert_eq!(client.peer, addr);
+/// # Ok(())
+/// # }
+/// ```
+pub trait FromProtocolWithAddr {
+    type InputProtocol: TInputProtocol;
+    type OutputProtocol: TOutputProtocol;
+
+    fn from_protocol_with_addr(
+        input_protocol: Self::InputProtocol,
+        output_protocol: Self::OutputProtocol,
+        addr: SocketAddr,
+    ) -> Self;
+}
+
+impl<T: FromProtocol> FromProtocolWithAddr for T {
+    type InputProtocol = T::InputProtocol;
+    type OutputProtocol = T::OutputProtocol;
+
+    fn from_protocol_with_addr(
+        input_protocol: Self::InputProtocol,
+        output_protocol: Self::OutputProtocol,
+        _addr: SocketAddr,
+    ) -> Self {
+        T::from_protocol(input_protocol, output_protocol)
+    }
+}
+
+/// Like [`FromProtocolWithAddr`], but receive the socket of the connection
+///
+/// Implement this for a client that needs to act on its socket later (e.g. to change its
+/// timeouts, like [`TimeoutConnection`]), by keeping a handle from [`TcpStream::try_clone`].
+/// [`MakeThriftConnectionFromAddrs`] creates its clients through this trait,
+/// and every [`FromProtocolWithAddr`] implements it with the socket's peer address
+///
+/// # Errors
+///
+/// Returns `Err` if the client can't be created from the socket
+pub trait FromProtocolWithSocket: Sized {
+    type InputProtocol: TInputProtocol;
+    type OutputProtocol: TOutputProtocol;
+
+    fn from_protocol_with_socket(
+        input_protocol: Self::InputProtocol,
+        output_protocol: Self::OutputProtocol,
+        socket: &TcpStream,
+    ) -> thrift::Result<Self>;
+}
+
+impl<T: FromProtocolWithAddr> FromProtocolWithSocket for T {
+    type InputProtocol = T::InputProtocol;
+    type OutputProtocol = T::OutputProtocol;
+
+    fn from_protocol_with_socket(
+        input_protocol: Self::InputProtocol,
+        output_protocol: Self::OutputProtocol,
+        socket: &TcpStream,
+    ) -> thrift::Result<Self> {
+        Ok(T::from_protocol_with_addr(
+            input_protocol,
+            output_protocol,
+            socket.peer_addr()?,
+        ))
+    }
+}
+
+/// Give access to the protocols of a client created with [`FromProtocol`]
+///
+/// Pool guards (like [`r2d2::PooledConnection`] and/or [`bb8::PooledConnection`])
+/// deref to the client, so once this is implemented the protocols can be reached
+/// from a checked out connection with `conn.input_protocol_mut()`/`conn.output_protocol_mut()`
+///
+/// ```
+/// # use std::ops::DerefMut;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::TBufferChannel;
+/// # use thrift_pool::{FromProtocol, ProtocolAccess};
+/// #
+/// struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+///     i_prot: Ip,
+///     o_prot: Op,
+/// }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+///
+/// impl<Ip: TInputProtocol, Op: TOutputProtocol> ProtocolAccess for MyThriftClient<Ip, Op> {
+///     fn input_protocol_mut(&mut self) -> &mut Ip {
+///         &mut self.i_prot
+///     }
+///     fn output_protocol_mut(&mut self) -> &mut Op {
+///         &mut self.o_prot
+///     }
+/// }
+///
+/// // works the same for any pool guard
+/// fn write_raw<C: ProtocolAccess>(mut conn: impl DerefMut<Target = C>) -> thrift::Result<()> {
+///     conn.output_protocol_mut().write_i32(42)?;
+///     conn.output_protocol_mut().flush()
+/// }
+///
+/// let channel = TBufferChannel::with_capacity(0, 16);
+/// let client = MyThriftClient::from_protocol(
+///     TBinaryInputProtocol::new(channel.clone(), true),
+///     TBinaryOutputProtocol::new(channel.clone(), true),
+/// );
+/// write_raw(Box::new(client)).unwrap();
+/// assert_eq!(channel.write_bytes(), 42i32.to_be_bytes());
+/// ```
+pub trait ProtocolAccess: FromProtocol {
+    /// Get the [`TInputProtocol`] the client reads from
+    fn input_protocol_mut(&mut self) -> &mut Self::InputProtocol;
+
+    /// Get the [`TOutputProtocol`] the client writes to
+    fn output_protocol_mut(&mut self) -> &mut Self::OutputProtocol;
+}
+
 /// Checks the validity of the connection
 ///
 /// Used by [`ThriftConnectionManager`] to implement parts of
@@ -288,14 +703,82 @@ pub trait MakeThriftConnection {
     fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error>;
 }
 
+/// The kind of transport a maker connects over, see [`TransportInfo`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TransportKind {
+    /// Plaintext TCP
+    Tcp,
+    /// TLS over TCP
+    Tls,
+    /// A Unix domain socket
+    Unix,
+    /// A channel forwarded over SSH
+    SshTunnel,
+    /// Anything else, like in-memory connections
+    Other,
+}
+
+impl TransportKind {
+    /// A short lowercase name, suitable as a log field or metrics label
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tcp => "tcp",
+            Self::Tls => "tls",
+            Self::Unix => "unix",
+            Self::SshTunnel => "ssh_tunnel",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl std::fmt::Display for TransportKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Tells which [`TransportKind`] a maker (or manager) connects over
+///
+/// It is implemented by the makers of this crate, and by the decorators wrapping them
+/// (which report the kind of their inner maker), so code handed a built manager
+/// can label it. The trait is object-safe
+///
+/// ```
+/// # use thrift_pool::{
+/// #     BoxedMaker, MakeThriftConnectionFromAddrs, ObservedMaker, ThriftConnectionManager,
+/// #     TransportInfo, TransportKind,
+/// # };
+/// # struct Observer;
+/// # impl thrift_pool::ConnectObserver for Observer {}
+/// let maker = MakeThriftConnectionFromAddrs::<(), _>::new("localhost:9090");
+/// assert_eq!(maker.transport_kind(), TransportKind::Tcp);
+///
+/// let manager = ThriftConnectionManager::new(BoxedMaker::new(ObservedMaker::new(maker, Observer)));
+/// let info: &dyn TransportInfo = &manager;
+/// assert_eq!(info.transport_kind(), TransportKind::Tcp);
+/// assert_eq!(info.transport_kind().to_string(), "tcp");
+///
+/// # #[cfg(unix)]
+/// # {
+/// let maker = thrift_pool::MakeThriftConnectionFromUnixSocket::<(), _>::new("/tmp/thrift.sock");
+/// assert_eq!(maker.transport_kind(), TransportKind::Unix);
+/// # }
+/// ```
+pub trait TransportInfo {
+    fn transport_kind(&self) -> TransportKind;
+}
+
 /// A [`MakeThriftConnection`] that attempts to create new connections
-/// from a [`ToSocketAddrs`] and a [`FromProtocol`]
+/// from a [`ToSocketAddrs`] and a [`FromProtocol`] (or a [`FromProtocolWithAddr`]
+/// or a [`FromProtocolWithSocket`])
 ///
 /// The connection is created in accordance with the
 /// [thrift rust tutorial](https://github.com/apache/thrift/tree/master/tutorial):
 ///
 /// * Open a [`TTcpChannel`] and split it
-/// * Use the created `[ReadHalf]` and `[WriteHalf]` to create [`TReadTransport`] and [`TWriteTransport`]
+/// * Optionally wrap the created `[ReadHalf]` and `[WriteHalf]` in a layer (like [`ByteLimitedRead`])
+/// * Use those to create [`TReadTransport`] and [`TWriteTransport`]
 /// * Use those to create [`TInputProtocol`] and [`TOutputProtocol`]
 /// * Create a new client with `i_prot` and `o_prot` -- It needs to implement [`FromProtocol`]
 ///
@@ -350,13 +833,27 @@ pub trait MakeThriftConnection {
 /// ```
 pub struct MakeThriftConnectionFromAddrs<T, S> {
     addrs: S,
+    nonblocking: bool,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    #[cfg(target_os = "linux")]
+    tcp_user_timeout: Option<Duration>,
+    ip_tos: Option<u32>,
     conn: PhantomData<T>,
 }
 
 impl<T, S: std::fmt::Debug> std::fmt::Debug for MakeThriftConnectionFromAddrs<T, S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("MakeThriftConnectionFromAddrs")
-            .field("addrs", &self.addrs)
+        let mut f = f.debug_struct("MakeThriftConnectionFromAddrs");
+        f.field("addrs", &self.addrs)
+            .field("nonblocking", &self.nonblocking)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("read_timeout", &self.read_timeout)
+            .field("write_timeout", &self.write_timeout);
+        #[cfg(target_os = "linux")]
+        f.field("tcp_user_timeout", &self.tcp_user_timeout);
+        f.field("ip_tos", &self.ip_tos)
             .field("conn", &self.conn)
             .finish()
     }
@@ -365,6 +862,13 @@ impl<T, S: Clone> Clone for MakeThriftConnectionFromAddrs<T, S> {
     fn clone(&self) -> Self {
         Self {
             addrs: self.addrs.clone(),
+            nonblocking: self.nonblocking,
+            connect_timeout: self.connect_timeout,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+            #[cfg(target_os = "linux")]
+            tcp_user_timeout: self.tcp_user_timeout,
+            ip_tos: self.ip_tos,
             conn: PhantomData,
         }
     }
@@ -374,32 +878,434 @@ impl<T, S> MakeThriftConnectionFromAddrs<T, S> {
     pub fn new(addrs: S) -> Self {
         Self {
             addrs,
+            nonblocking: false,
+            connect_timeout: None,
+            read_timeout: None,
+            write_timeout: None,
+            #[cfg(target_os = "linux")]
+            tcp_user_timeout: None,
+            ip_tos: None,
             conn: PhantomData,
         }
     }
+
+    /// Set whether the socket should be put in nonblocking mode after connecting (defaults to `false`)
+    ///
+    /// Thrift's transports perform blocking reads: a nonblocking socket makes
+    /// them fail with [`io::ErrorKind::WouldBlock`] whenever no data is available yet.
+    /// The mode is applied explicitly on every new socket, so it doesn't depend
+    /// on whatever state the underlying file descriptor was in
+    pub fn with_nonblocking(mut self, nonblocking: bool) -> Self {
+        self.nonblocking = nonblocking;
+        self
+    }
+
+    /// Give up on connecting to an address after `connect_timeout` (defaults to no timeout)
+    ///
+    /// When `addrs` resolves to several addresses, the timeout applies to each of them
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Set the read timeout of every new socket (defaults to no timeout)
+    ///
+    /// A read that waits longer than this fails with [`io::ErrorKind::WouldBlock`]
+    /// or [`io::ErrorKind::TimedOut`] (depending on the platform).
+    /// See [`TimeoutConnection`] to override it for a single operation
+    pub fn with_read_timeout(mut self, read_timeout: Duration) -> Self {
+        self.read_timeout = Some(read_timeout);
+        self
+    }
+
+    /// Set the write timeout of every new socket (defaults to no timeout)
+    ///
+    /// See [`MakeThriftConnectionFromAddrs::with_read_timeout`]
+    pub fn with_write_timeout(mut self, write_timeout: Duration) -> Self {
+        self.write_timeout = Some(write_timeout);
+        self
+    }
+
+    /// Set the `TCP_USER_TIMEOUT` of every new socket (defaults to the system's, usually none)
+    ///
+    /// This bounds how long sent data may remain unacknowledged before the connection
+    /// fails with [`io::ErrorKind::TimedOut`]. Keepalive probes only notice a dead peer
+    /// while the connection is idle, and retransmissions of unacknowledged data can last
+    /// for many minutes: this makes requests in flight to a dead peer fail much sooner
+    ///
+    /// ```
+    /// # use std::net::TcpListener;
+    /// # use std::time::Duration;
+    /// # use thrift_pool::MakeThriftConnectionFromAddrs;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    /// let maker = MakeThriftConnectionFromAddrs::<(), _>::new(listener.local_addr()?)
+    ///     .with_tcp_user_timeout(Duration::from_secs(5));
+    ///
+    /// let stream = maker.open_stream()?;
+    /// let user_timeout = socket2::SockRef::from(&stream).tcp_user_timeout()?;
+    /// assert_eq!(user_timeout, Some(Duration::from_secs(5)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn with_tcp_user_timeout(mut self, tcp_user_timeout: Duration) -> Self {
+        self.tcp_user_timeout = Some(tcp_user_timeout);
+        self
+    }
+
+    /// Mark the packets of every new socket with the type of service (IPv4) or traffic class
+    /// (IPv6) `ip_tos` (defaults to the system's, usually 0), e.g. for QoS
+    ///
+    /// The DSCP is the 6 high bits of the byte, so DSCP `46` (expedited forwarding) is
+    /// `46 << 2`. Platforms differ: IPv4 sockets are marked on Unix and Windows (which may
+    /// ignore `IP_TOS` depending on its version and QoS policies), IPv6 sockets only on Linux,
+    /// Android, macOS and the BSDs. Elsewhere connects fail with
+    /// [`io::ErrorKind::Unsupported`] rather than silently leaving the traffic unmarked
+    ///
+    /// ```
+    /// # use std::net::TcpListener;
+    /// # use thrift_pool::MakeThriftConnectionFromAddrs;
+    /// # #[cfg(target_os = "linux")]
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    /// let maker = MakeThriftConnectionFromAddrs::<(), _>::new(listener.local_addr()?)
+    ///     .with_ip_tos(46 << 2);
+    ///
+    /// let stream = maker.open_stream()?;
+    /// assert_eq!(socket2::SockRef::from(&stream).tos_v4()?, 46 << 2);
+    /// # Ok(())
+    /// # }
+    /// # #[cfg(not(target_os = "linux"))]
+    /// # fn main() {}
+    /// ```
+    pub fn with_ip_tos(mut self, ip_tos: u32) -> Self {
+        self.ip_tos = Some(ip_tos);
+        self
+    }
+
+    /// The addresses connections are made to
+    pub fn addrs(&self) -> &S {
+        &self.addrs
+    }
+
+    /// The configured connect timeout, if any
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout
+    }
+
+    /// The configured read timeout, if any
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// The configured write timeout, if any
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    /// The configured `TCP_USER_TIMEOUT`, if any
+    #[cfg(target_os = "linux")]
+    pub fn tcp_user_timeout(&self) -> Option<Duration> {
+        self.tcp_user_timeout
+    }
+
+    /// The configured type of service, if any
+    pub fn ip_tos(&self) -> Option<u32> {
+        self.ip_tos
+    }
+
+    /// Apply the configured socket options to a newly opened `stream`
+    fn configure_stream(&self, stream: &TcpStream) -> io::Result<()> {
+        stream.set_nonblocking(self.nonblocking)?;
+        stream.set_read_timeout(self.read_timeout)?;
+        stream.set_write_timeout(self.write_timeout)?;
+        #[cfg(target_os = "linux")]
+        if let Some(tcp_user_timeout) = self.tcp_user_timeout {
+            socket2::SockRef::from(stream).set_tcp_user_timeout(Some(tcp_user_timeout))?;
+        }
+        if let Some(ip_tos) = self.ip_tos {
+            set_ip_tos(stream, ip_tos)?;
+        }
+        Ok(())
+    }
+
+    /// The same maker connecting to `addrs`, and the addresses it replaced
+    fn with_addrs<A>(self, addrs: A) -> (S, MakeThriftConnectionFromAddrs<T, A>) {
+        let maker = MakeThriftConnectionFromAddrs {
+            addrs,
+            nonblocking: self.nonblocking,
+            connect_timeout: self.connect_timeout,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+            #[cfg(target_os = "linux")]
+            tcp_user_timeout: self.tcp_user_timeout,
+            ip_tos: self.ip_tos,
+            conn: PhantomData,
+        };
+        (self.addrs, maker)
+    }
+}
+
+/// Set the type of service (IPv4) or traffic class (IPv6) of `stream`,
+/// see [`MakeThriftConnectionFromAddrs::with_ip_tos`]
+fn set_ip_tos(stream: &TcpStream, ip_tos: u32) -> io::Result<()> {
+    match stream.local_addr()? {
+        #[cfg(any(unix, windows))]
+        #[cfg(not(any(target_os = "fuchsia", target_os = "solaris", target_os = "haiku")))]
+        SocketAddr::V4(_) => socket2::SockRef::from(stream).set_tos_v4(ip_tos),
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "dragonfly",
+        ))]
+        SocketAddr::V6(_) => socket2::SockRef::from(stream).set_tclass_v6(ip_tos),
+        #[allow(unreachable_patterns)]
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "setting the type of service is not supported for this socket on this platform",
+        )),
+    }
+}
+
+impl<T, S: ToSocketAddrs> MakeThriftConnectionFromAddrs<T, S> {
+    /// Open a [`TcpStream`] to `addrs` and apply the configured socket options
+    ///
+    /// This is the first step of [`MakeThriftConnection::make_thrift_connection`]
+    ///
+    /// ```
+    /// # use std::io::{ErrorKind, Read};
+    /// # use std::net::TcpListener;
+    /// # use thrift_pool::MakeThriftConnectionFromAddrs;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    /// let addr = listener.local_addr()?;
+    ///
+    /// let maker = MakeThriftConnectionFromAddrs::<(), _>::new(addr).with_nonblocking(true);
+    /// let mut stream = maker.open_stream()?;
+    /// let err = stream.read(&mut [0; 1]).unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    ///
+    /// let maker = maker.with_nonblocking(false);
+    /// let stream = maker.open_stream()?;
+    /// stream.set_read_timeout(Some(std::time::Duration::from_millis(10)))?;
+    /// let err = (&stream).read(&mut [0; 1]).unwrap_err();
+    /// assert!(matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the connection can't be established
+    /// or the socket options can't be applied
+    pub fn open_stream(&self) -> thrift::Result<TcpStream> {
+        let stream = match self.connect_timeout {
+            None => TcpStream::connect(&self.addrs)?,
+            Some(timeout) => {
+                let mut last_err = None;
+                let mut stream = None;
+                for addr in self.addrs.to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&addr, timeout) {
+                        Ok(s) => {
+                            stream = Some(s);
+                            break;
+                        }
+                        Err(e) => last_err = Some(e),
+                    }
+                }
+                stream.ok_or_else(|| {
+                    last_err.unwrap_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "could not resolve to any addresses",
+                        )
+                    })
+                })?
+            }
+        };
+        self.configure_stream(&stream)?;
+        Ok(stream)
+    }
+
+    /// Resolve `addrs` now, and connect to the resulting addresses from then on
+    ///
+    /// By default `addrs` is resolved on every connect, which follows DNS changes
+    /// (or service discovery, see [`ResolverFallback`]) but costs a lookup per connection,
+    /// and fails connecting whenever the resolver does. Resolving once makes connects
+    /// deterministic and independent of the resolver, at the cost of never noticing
+    /// addresses that change: recreate the maker (e.g. with a [`SwappableMaker`]) for that
+    ///
+    /// ```
+    /// # use std::io;
+    /// # use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+    /// # use std::sync::atomic::{AtomicUsize, Ordering};
+    /// # use std::sync::Arc;
+    /// # use thrift_pool::MakeThriftConnectionFromAddrs;
+    /// // a resolver counting its lookups
+    /// #[derive(Clone)]
+    /// struct Counting(SocketAddr, Arc<AtomicUsize>);
+    ///
+    /// impl ToSocketAddrs for Counting {
+    ///     type Iter = std::option::IntoIter<SocketAddr>;
+    ///     fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+    ///         self.1.fetch_add(1, Ordering::SeqCst);
+    ///         Ok(Some(self.0).into_iter())
+    ///     }
+    /// }
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    /// let lookups = Arc::new(AtomicUsize::new(0));
+    /// let resolver = Counting(listener.local_addr()?, lookups.clone());
+    ///
+    /// let maker = MakeThriftConnectionFromAddrs::<(), _>::new(resolver).resolve_now()?;
+    /// assert_eq!(lookups.load(Ordering::SeqCst), 1);
+    /// assert_eq!(maker.addrs().as_slice(), [listener.local_addr()?]);
+    ///
+    /// for _ in 0..3 {
+    ///     maker.open_stream()?;
+    /// }
+    /// assert_eq!(lookups.load(Ordering::SeqCst), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if `addrs` can't be resolved, or resolves to no address
+    pub fn resolve_now(self) -> io::Result<MakeThriftConnectionFromResolvedAddrs<T>> {
+        let addrs = ResolvedAddrs::resolve(&self.addrs)?;
+        Ok(self.with_addrs(addrs).1)
+    }
+
+    /// Resolve `addrs` without blocking the async runtime, and connect to the resulting
+    /// addresses from then on, refreshing them every `refresh_every` in the background
+    ///
+    /// Resolving on every connect blocks the thread making it, which in the `bb8` path is
+    /// a thread of the runtime: a slow resolver then stalls every task scheduled on it. The
+    /// lookups are instead made on blocking threads (see [`BackgroundResolvedAddrs::resolve`]),
+    /// so connects only use the last resolution. The `r2d2` path connects from its own threads,
+    /// and can keep resolving on every connect
+    ///
+    /// ```
+    /// # use std::io;
+    /// # use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+    /// # use std::sync::atomic::{AtomicUsize, Ordering};
+    /// # use std::sync::Arc;
+    /// # use std::time::Duration;
+    /// # use thrift_pool::MakeThriftConnectionFromAddrs;
+    /// // a resolver taking 100ms per lookup
+    /// struct Slow(SocketAddr);
+    ///
+    /// impl ToSocketAddrs for Slow {
+    ///     type Iter = std::option::IntoIter<SocketAddr>;
+    ///     fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+    ///         std::thread::sleep(Duration::from_millis(100));
+    ///         Ok(Some(self.0).into_iter())
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    ///
+    /// // a task that needs the runtime's only thread every 10ms
+    /// let ticks = Arc::new(AtomicUsize::new(0));
+    /// tokio::spawn({
+    ///     let ticks = ticks.clone();
+    ///     async move {
+    ///         loop {
+    ///             tokio::time::sleep(Duration::from_millis(10)).await;
+    ///             ticks.fetch_add(1, Ordering::SeqCst);
+    ///         }
+    ///     }
+    /// });
+    ///
+    /// let maker = MakeThriftConnectionFromAddrs::<(), _>::new(Slow(listener.local_addr()?))
+    ///     .resolve_in_background(Duration::from_secs(60))
+    ///     .await?;
+    /// // the task kept running during the lookup
+    /// assert!(ticks.load(Ordering::SeqCst) >= 5);
+    /// assert_eq!(*maker.addrs().current(), [listener.local_addr()?]);
+    /// maker.open_stream()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if `addrs` can't be resolved, or resolves to no address
+    #[cfg(feature = "impl-bb8")]
+    pub async fn resolve_in_background(
+        self,
+        refresh_every: Duration,
+    ) -> io::Result<MakeThriftConnectionFromAddrs<T, BackgroundResolvedAddrs>>
+    where
+        S: Send + Sync + 'static,
+    {
+        let (addrs, maker) = self.with_addrs(());
+        let addrs = BackgroundResolvedAddrs::resolve(addrs, refresh_every).await?;
+        Ok(maker.with_addrs(addrs).1)
+    }
 }
 
+/// A [`MakeThriftConnectionFromAddrs`] connecting to addresses resolved once,
+/// see [`MakeThriftConnectionFromAddrs::resolve_now`]
+pub type MakeThriftConnectionFromResolvedAddrs<T> = MakeThriftConnectionFromAddrs<T, ResolvedAddrs>;
+
 impl<
         S: ToSocketAddrs + Clone,
-        RT: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RL: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RT: FromRead<Read = RL>,
         IP: FromReadTransport<ReadTransport = RT>,
-        WT: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WL: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WT: FromWrite<Write = WL>,
         OP: FromWriteTransport<WriteTransport = WT>,
-        T: FromProtocol<InputProtocol = IP, OutputProtocol = OP>,
+        T: FromProtocolWithSocket<InputProtocol = IP, OutputProtocol = OP>,
     > MakeThriftConnectionFromAddrs<T, S>
 {
     pub fn into_connection_manager(self) -> ThriftConnectionManager<Self> {
         ThriftConnectionManager::new(self)
     }
+
+    /// Create a client over an open `stream`
+    ///
+    /// This is the second step of [`MakeThriftConnection::make_thrift_connection`], after
+    /// [`MakeThriftConnectionFromAddrs::open_stream`]. Calling both separately allows
+    /// using the stream in between (e.g. to send a preamble, like [`ProxyProtocolMaker`] does)
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the stream can't be split or the client can't be created
+    pub fn connection_from_stream(&self, stream: TcpStream) -> thrift::Result<T> {
+        let socket = stream.try_clone()?;
+        let channel = TTcpChannel::with_stream(stream);
+        let (read, write) = channel.split()?;
+
+        let read_transport = RT::from_read(RL::from_read(read));
+        let input_protocol = IP::from_read_transport(read_transport);
+
+        let write_transport = WT::from_write(WL::from_write(write));
+        let output_protocol = OP::from_write_transport(write_transport);
+
+        T::from_protocol_with_socket(input_protocol, output_protocol, &socket)
+    }
 }
 
 impl<
         S: ToSocketAddrs + Clone,
-        RT: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RL: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RT: FromRead<Read = RL>,
         IP: FromReadTransport<ReadTransport = RT>,
-        WT: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WL: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WT: FromWrite<Write = WL>,
         OP: FromWriteTransport<WriteTransport = WT>,
-        T: FromProtocol<InputProtocol = IP, OutputProtocol = OP>,
+        T: FromProtocolWithSocket<InputProtocol = IP, OutputProtocol = OP>,
     > MakeThriftConnection for MakeThriftConnectionFromAddrs<T, S>
 {
     type Error = thrift::Error;
@@ -407,40 +1313,680 @@ impl<
     type Output = T;
 
     fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
-        let mut channel = TTcpChannel::new();
-        channel.open(self.addrs.clone())?;
-        let (read, write) = channel.split()?;
-
-        let read_transport = RT::from_read(read);
-        let input_protocol = IP::from_read_transport(read_transport);
-
-        let write_transport = WT::from_write(write);
-        let output_protocol = OP::from_write_transport(write_transport);
+        self.connection_from_stream(self.open_stream()?)
+    }
+}
 
-        Ok(T::from_protocol(input_protocol, output_protocol))
+impl<T, S> TransportInfo for MakeThriftConnectionFromAddrs<T, S> {
+    fn transport_kind(&self) -> TransportKind {
+        TransportKind::Tcp
     }
 }
 
 /// An implementor of [`bb8::ManageConnection`] and/or [`r2d2::ManageConnection`].
 /// `T` should a [`MakeThriftConnection`] and `T::Output` should be a [`ThriftConnection`]
-pub struct ThriftConnectionManager<T>(T);
+pub struct ThriftConnectionManager<T> {
+    make_thrift_connection: T,
+    #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+    broken_policy: Option<Arc<dyn BrokenPolicy<T>>>,
+    #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+    connect_limit: Option<Arc<ConnectLimit>>,
+    #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+    connect_priority: Priority,
+    #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+    labels: Arc<HashMap<String, String>>,
+    #[cfg(feature = "impl-bb8")]
+    events: Option<Arc<dyn EventSink<T>>>,
+}
+
+/// A replacement for [`ThriftConnection::has_broken`], see
+/// [`ThriftConnectionManager::with_broken_policy`]
+///
+/// The connection type is only named in the method, so that [`ThriftConnectionManager`]
+/// doesn't need to bound `T`
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+trait BrokenPolicy<T>: Send + Sync {
+    fn has_broken(&self, conn: &mut T::Output) -> bool
+    where
+        T: MakeThriftConnection;
+}
+
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+impl<T: MakeThriftConnection, F: Fn(&mut T::Output) -> bool + Send + Sync> BrokenPolicy<T> for F {
+    fn has_broken(&self, conn: &mut T::Output) -> bool {
+        self(conn)
+    }
+}
+
+impl<T: TransportInfo> TransportInfo for ThriftConnectionManager<T> {
+    fn transport_kind(&self) -> TransportKind {
+        self.make_thrift_connection.transport_kind()
+    }
+}
 
 impl<T: Clone> Clone for ThriftConnectionManager<T> {
     fn clone(&self) -> Self {
-        Self(self.0.clone())
+        Self {
+            make_thrift_connection: self.make_thrift_connection.clone(),
+            #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+            broken_policy: self.broken_policy.clone(),
+            #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+            connect_limit: self.connect_limit.clone(),
+            #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+            connect_priority: self.connect_priority,
+            #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+            labels: self.labels.clone(),
+            #[cfg(feature = "impl-bb8")]
+            events: self.events.clone(),
+        }
     }
 }
 impl<T: std::fmt::Debug> std::fmt::Debug for ThriftConnectionManager<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple("ThriftConnectionManager")
-            .field(&self.0)
+            .field(&self.make_thrift_connection)
             .finish()
     }
 }
 
 impl<T> ThriftConnectionManager<T> {
     pub fn new(make_thrift_connection: T) -> Self {
-        Self(make_thrift_connection)
+        Self {
+            make_thrift_connection,
+            #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+            broken_policy: None,
+            #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+            connect_limit: None,
+            #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+            connect_priority: Priority::Normal,
+            #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+            labels: Arc::default(),
+            #[cfg(feature = "impl-bb8")]
+            events: None,
+        }
+    }
+
+    /// Use `policy` instead of [`ThriftConnection::has_broken`] to tell whether a connection
+    /// has broken
+    ///
+    /// This allows pools of the same connection type to detect broken connections differently
+    ///
+    /// ```
+    /// # use r2d2::ManageConnection;
+    /// # use thrift_pool::{MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+    /// # struct Conn {
+    /// #     failed_calls: u32,
+    /// # }
+    /// # impl ThriftConnection for Conn {
+    /// #     type Error = thrift::Error;
+    /// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+    /// #         Ok(())
+    /// #     }
+    /// # }
+    /// # #[derive(Clone)]
+    /// # struct Maker;
+    /// # impl MakeThriftConnection for Maker {
+    /// #     type Error = thrift::Error;
+    /// #     type Output = Conn;
+    /// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+    /// #         Ok(Conn { failed_calls: 0 })
+    /// #     }
+    /// # }
+    /// let aggressive = ThriftConnectionManager::new(Maker)
+    ///     .with_broken_policy(|conn: &mut Conn| conn.failed_calls > 0);
+    /// let lenient = ThriftConnectionManager::new(Maker)
+    ///     .with_broken_policy(|conn: &mut Conn| conn.failed_calls > 10);
+    ///
+    /// let mut conn = Conn { failed_calls: 1 };
+    /// assert!(aggressive.has_broken(&mut conn));
+    /// assert!(!lenient.has_broken(&mut conn));
+    /// ```
+    #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+    pub fn with_broken_policy(
+        mut self,
+        policy: impl Fn(&mut T::Output) -> bool + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: MakeThriftConnection,
+    {
+        self.broken_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Only let `max_concurrent_connects` calls to [`MakeThriftConnection::make_thrift_connection`]
+    /// run at once, queueing the others (defaults to no limit)
+    ///
+    /// This smooths the load of a pool growing from 0 to many connections (e.g. costly
+    /// handshakes all happening at once). Clones share the same limit, see
+    /// [`ThriftConnectionManager::with_connect_priority`] to let some of them go first.
+    /// `r2d2` connects wait on a condition variable, and `bb8` connects on a
+    /// [`tokio::sync::Notify`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_concurrent_connects` is zero
+    ///
+    /// ```
+    /// # use std::sync::atomic::{AtomicUsize, Ordering};
+    /// # use std::sync::Arc;
+    /// # use std::time::Duration;
+    /// # use r2d2::ManageConnection;
+    /// # use thrift_pool::{MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+    /// # struct Conn;
+    /// # impl ThriftConnection for Conn {
+    /// #     type Error = thrift::Error;
+    /// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+    /// #         Ok(())
+    /// #     }
+    /// # }
+    /// // a slow maker that records how many connects run at once
+    /// #[derive(Default)]
+    /// struct Stats {
+    ///     running: AtomicUsize,
+    ///     max_running: AtomicUsize,
+    /// }
+    /// struct Maker(Arc<Stats>);
+    ///
+    /// impl MakeThriftConnection for Maker {
+    ///     type Error = thrift::Error;
+    ///     type Output = Conn;
+    ///     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+    ///         let running = self.0.running.fetch_add(1, Ordering::SeqCst) + 1;
+    ///         self.0.max_running.fetch_max(running, Ordering::SeqCst);
+    ///         std::thread::sleep(Duration::from_millis(20));
+    ///         self.0.running.fetch_sub(1, Ordering::SeqCst);
+    ///         Ok(Conn)
+    ///     }
+    /// }
+    ///
+    /// let stats = Arc::new(Stats::default());
+    /// let manager = ThriftConnectionManager::new(Maker(stats.clone())).with_max_concurrent_connects(2);
+    ///
+    /// std::thread::scope(|s| {
+    ///     let threads: Vec<_> = (0..8).map(|_| s.spawn(|| manager.connect())).collect();
+    ///     for thread in threads {
+    ///         assert!(thread.join().unwrap().is_ok());
+    ///     }
+    /// });
+    /// assert_eq!(stats.max_running.load(Ordering::SeqCst), 2);
+    /// ```
+    #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+    pub fn with_max_concurrent_connects(mut self, max_concurrent_connects: usize) -> Self {
+        assert!(
+            max_concurrent_connects > 0,
+            "max_concurrent_connects must be positive"
+        );
+        self.connect_limit = Some(Arc::new(ConnectLimit::new(max_concurrent_connects)));
+        self
+    }
+
+    /// Let the connects of this manager go before those of lower priority clones
+    /// (defaults to [`Priority::Normal`])
+    ///
+    /// This only matters with [`ThriftConnectionManager::with_max_concurrent_connects`]:
+    /// when a slot frees up, it goes to a waiting connect of the highest priority,
+    /// e.g. so that the pool used for health checks doesn't starve behind the one serving
+    /// user requests, built from a clone of the same manager
+    ///
+    /// ```
+    /// # use std::sync::{Arc, Mutex};
+    /// # use std::time::Duration;
+    /// # use r2d2::ManageConnection;
+    /// # use thrift_pool::{MakeThriftConnection, Priority, ThriftConnection, ThriftConnectionManager};
+    /// # struct Conn;
+    /// # impl ThriftConnection for Conn {
+    /// #     type Error = thrift::Error;
+    /// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+    /// #         Ok(())
+    /// #     }
+    /// # }
+    /// // a slow maker that records who connected, in order
+    /// #[derive(Clone)]
+    /// struct Maker(Arc<Mutex<Vec<String>>>);
+    ///
+    /// impl MakeThriftConnection for Maker {
+    ///     type Error = thrift::Error;
+    ///     type Output = Conn;
+    ///     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+    ///         let name = std::thread::current().name().unwrap().to_owned();
+    ///         self.0.lock().unwrap().push(name);
+    ///         std::thread::sleep(Duration::from_millis(50));
+    ///         Ok(Conn)
+    ///     }
+    /// }
+    ///
+    /// let order = Arc::new(Mutex::new(Vec::new()));
+    /// let requests = ThriftConnectionManager::new(Maker(order.clone())).with_max_concurrent_connects(1);
+    /// let health_checks = requests.clone().with_connect_priority(Priority::High);
+    ///
+    /// let connect = |name: &str, manager: &ThriftConnectionManager<Maker>| {
+    ///     let manager = manager.clone();
+    ///     std::thread::Builder::new()
+    ///         .name(name.to_owned())
+    ///         .spawn(move || manager.connect().map(drop))
+    ///         .unwrap()
+    /// };
+    /// let mut threads = vec![connect("request 0", &requests)];
+    /// std::thread::sleep(Duration::from_millis(10));
+    /// threads.push(connect("request 1", &requests));
+    /// threads.push(connect("request 2", &requests));
+    /// std::thread::sleep(Duration::from_millis(10));
+    /// threads.push(connect("health check", &health_checks));
+    /// for thread in threads {
+    ///     assert!(thread.join().unwrap().is_ok());
+    /// }
+    ///
+    /// // the health check jumped the queue
+    /// let order = order.lock().unwrap();
+    /// assert_eq!(order[..2], ["request 0", "health check"]);
+    /// ```
+    #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+    pub fn with_connect_priority(mut self, priority: Priority) -> Self {
+        self.connect_priority = priority;
+        self
+    }
+
+    #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+    pub fn connect_priority(&self) -> Priority {
+        self.connect_priority
+    }
+
+    /// Attach `labels` (e.g. `role` = `scanner`) to the metrics, tracing events and
+    /// [`PoolEvent`]s of the connections of this manager, replacing the previous ones
+    /// (defaults to none)
+    ///
+    /// This tells apart the pools of a process, e.g. to slice metrics by the role of each.
+    /// Metrics carry each label next to their own (so none should be named `outcome`).
+    /// With the `tracing` feature, the manager emits debug events when connects succeed or
+    /// fail and when connections are found broken or invalid, carrying the labels in a
+    /// `labels` field, and connects run inside a `thrift_pool_connect` span carrying it too
+    ///
+    /// ```
+    /// # #[cfg(all(feature = "tracing", feature = "impl-r2d2"))]
+    /// # fn main() -> Result<(), thrift::Error> {
+    /// # use std::sync::{Arc, Mutex};
+    /// # use r2d2::ManageConnection;
+    /// # use thrift_pool::{MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+    /// # use tracing::field::{Field, Visit};
+    /// # use tracing::span::{Attributes, Id, Record};
+    /// # use tracing::{Event, Metadata};
+    /// # struct Conn;
+    /// # impl ThriftConnection for Conn {
+    /// #     type Error = thrift::Error;
+    /// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+    /// #         Ok(())
+    /// #     }
+    /// #     fn has_broken(&mut self) -> bool {
+    /// #         true
+    /// #     }
+    /// # }
+    /// # struct Maker;
+    /// # impl MakeThriftConnection for Maker {
+    /// #     type Error = thrift::Error;
+    /// #     type Output = Conn;
+    /// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+    /// #         Ok(Conn)
+    /// #     }
+    /// # }
+    /// # // a subscriber collecting the `labels` field of the events
+    /// # #[derive(Clone, Default)]
+    /// # struct Labels(Arc<Mutex<Vec<String>>>);
+    /// # impl Visit for Labels {
+    /// #     fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+    /// #         if field.name() == "labels" {
+    /// #             self.0.lock().unwrap().push(format!("{value:?}"));
+    /// #         }
+    /// #     }
+    /// # }
+    /// # impl tracing::Subscriber for Labels {
+    /// #     fn enabled(&self, _: &Metadata<'_>) -> bool {
+    /// #         true
+    /// #     }
+    /// #     fn new_span(&self, _: &Attributes<'_>) -> Id {
+    /// #         Id::from_u64(1)
+    /// #     }
+    /// #     fn record(&self, _: &Id, _: &Record<'_>) {}
+    /// #     fn record_follows_from(&self, _: &Id, _: &Id) {}
+    /// #     fn event(&self, event: &Event<'_>) {
+    /// #         event.record(&mut self.clone());
+    /// #     }
+    /// #     fn enter(&self, _: &Id) {}
+    /// #     fn exit(&self, _: &Id) {}
+    /// # }
+    /// let manager = ThriftConnectionManager::new(Maker).with_label("role", "scanner");
+    ///
+    /// let labels = Labels::default();
+    /// tracing::subscriber::with_default(labels.clone(), || {
+    ///     let mut conn = manager.connect()?;
+    ///     assert!(manager.has_broken(&mut conn));
+    ///     Ok::<_, thrift::Error>(())
+    /// })?;
+    ///
+    /// // connected, then found broken
+    /// let labels = labels.0.lock().unwrap();
+    /// assert_eq!(labels[..], [r#"{"role": "scanner"}"#; 2]);
+    /// # Ok(())
+    /// # }
+    /// # #[cfg(not(all(feature = "tracing", feature = "impl-r2d2")))]
+    /// # fn main() {}
+    /// ```
+    #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+    pub fn with_labels(mut self, labels: HashMap<String, String>) -> Self {
+        self.labels = Arc::new(labels);
+        self
+    }
+
+    /// Add the label `key` = `value`, see [`ThriftConnectionManager::with_labels`]
+    #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.labels).insert(key.into(), value.into());
+        self
+    }
+
+    #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+    pub fn labels(&self) -> &HashMap<String, String> {
+        &self.labels
+    }
+
+    /// Send a [`PoolEvent`] on a [`tokio::sync::broadcast`] channel of `capacity` events
+    /// whenever a connection is created, validated, found broken or discarded,
+    /// see [`ThriftConnectionManager::subscribe`]
+    ///
+    /// `connection_id` tells the connections apart, e.g. [`TracedConnection::connection_id`].
+    /// Clones share the same channel. Events are sent whether or not anyone is subscribed,
+    /// and a receiver lagging more than `capacity` events behind misses the oldest ones
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero
+    ///
+    /// ```
+    /// # use std::sync::atomic::{AtomicBool, Ordering};
+    /// # use std::sync::Arc;
+    /// # use thrift_pool::{
+    /// #     MakeThriftConnection, PoolEventKind, ThriftConnection, ThriftConnectionManager,
+    /// #     TracedConnection,
+    /// # };
+    /// # #[derive(Debug)]
+    /// # struct Conn(Arc<AtomicBool>);
+    /// # impl ThriftConnection for Conn {
+    /// #     type Error = thrift::Error;
+    /// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+    /// #         Ok(())
+    /// #     }
+    /// #     fn has_broken(&mut self) -> bool {
+    /// #         self.0.load(Ordering::SeqCst)
+    /// #     }
+    /// # }
+    /// // connections that break once `broken` is set
+    /// struct Maker(Arc<AtomicBool>);
+    ///
+    /// impl MakeThriftConnection for Maker {
+    ///     type Error = thrift::Error;
+    ///     type Output = TracedConnection<Conn>;
+    ///     fn make_thrift_connection(&self) -> Result<Self::Output, thrift::Error> {
+    ///         Ok(TracedConnection::new(Conn(self.0.clone())))
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let broken = Arc::new(AtomicBool::new(false));
+    /// let manager = ThriftConnectionManager::new(Maker(broken.clone()))
+    ///     .with_events(16, TracedConnection::connection_id);
+    /// let mut events = manager.subscribe().unwrap();
+    /// let pool = bb8::Pool::builder().max_size(1).build(manager).await?;
+    ///
+    /// // created, then reused
+    /// let first = pool.get().await?.connection_id();
+    /// let _ = pool.get().await?;
+    /// // broken when returned, so a new one is created
+    /// let conn = pool.get().await?;
+    /// broken.store(true, Ordering::SeqCst);
+    /// drop(conn);
+    /// broken.store(false, Ordering::SeqCst);
+    /// let second = pool.get().await?.connection_id();
+    ///
+    /// let mut received = Vec::new();
+    /// while let Ok(event) = events.try_recv() {
+    ///     received.push((event.kind, event.connection_id));
+    /// }
+    /// assert_eq!(
+    ///     received,
+    ///     [
+    ///         (PoolEventKind::Created, first),
+    ///         (PoolEventKind::Validated, first),
+    ///         (PoolEventKind::Validated, first),
+    ///         (PoolEventKind::Validated, first),
+    ///         (PoolEventKind::Broken, first),
+    ///         (PoolEventKind::Created, second),
+    ///         (PoolEventKind::Validated, second),
+    ///     ]
+    /// );
+    /// assert_ne!(first, second);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "impl-bb8")]
+    pub fn with_events(
+        mut self,
+        capacity: usize,
+        connection_id: impl Fn(&T::Output) -> ConnectionId + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: MakeThriftConnection,
+    {
+        self.events = Some(Arc::new(Events::new(capacity, connection_id)));
+        self
+    }
+
+    /// A receiver of the events sent after this call, or `None` without
+    /// [`ThriftConnectionManager::with_events`]
+    #[cfg(feature = "impl-bb8")]
+    pub fn subscribe(&self) -> Option<tokio::sync::broadcast::Receiver<PoolEvent>> {
+        self.events.as_ref().map(|events| events.subscribe())
+    }
+
+    /// The underlying [`MakeThriftConnection`]
+    pub fn inner(&self) -> &T {
+        &self.make_thrift_connection
+    }
+
+    pub fn into_inner(self) -> T {
+        self.make_thrift_connection
+    }
+}
+
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+impl<T: MakeThriftConnection> ThriftConnectionManager<T>
+where
+    T::Output: ThriftConnection,
+{
+    /// Try to make `n` connections one after the other, returning the outcome of each
+    ///
+    /// Unlike filling a pool, this doesn't stop at the first error: it tells e.g. how many
+    /// connections a backend accepts before refusing more. The connects are recorded like those
+    /// of the pool (metrics, events, ...), but aren't queued by
+    /// [`ThriftConnectionManager::with_max_concurrent_connects`] since they run one at a time
+    ///
+    /// ```
+    /// # use std::sync::atomic::{AtomicUsize, Ordering};
+    /// # use thrift_pool::{MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+    /// # struct Conn;
+    /// # impl ThriftConnection for Conn {
+    /// #     type Error = thrift::Error;
+    /// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+    /// #         Ok(())
+    /// #     }
+    /// # }
+    /// // a backend accepting 3 connections
+    /// struct Maker(AtomicUsize);
+    ///
+    /// impl MakeThriftConnection for Maker {
+    ///     type Error = thrift::Error;
+    ///     type Output = Conn;
+    ///     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+    ///         if self.0.fetch_add(1, Ordering::SeqCst) < 3 {
+    ///             Ok(Conn)
+    ///         } else {
+    ///             Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into())
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let manager = ThriftConnectionManager::new(Maker(AtomicUsize::new(0)));
+    /// let outcomes = manager.make_many(5);
+    /// assert_eq!(outcomes.len(), 5);
+    /// assert_eq!(outcomes.iter().filter(|outcome| outcome.is_ok()).count(), 3);
+    /// assert!(outcomes[3..].iter().all(Result::is_err));
+    /// ```
+    pub fn make_many(&self, n: usize) -> Vec<Result<T::Output, T::Error>> {
+        (0..n).map(|_| self.make()).collect()
+    }
+
+    /// Like [`ThriftConnectionManager::make_many`], but making the connections concurrently,
+    /// each on a blocking thread of the tokio runtime
+    ///
+    /// The outcomes are returned in the order the connects were started. This is queued by
+    /// [`ThriftConnectionManager::with_max_concurrent_connects`] like the connects of the pool
+    ///
+    /// # Panics
+    ///
+    /// Resumes the panic of a connect that panicked
+    ///
+    /// ```
+    /// # use std::sync::atomic::{AtomicUsize, Ordering};
+    /// # use std::sync::Arc;
+    /// # use thrift_pool::{MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+    /// # struct Conn;
+    /// # impl ThriftConnection for Conn {
+    /// #     type Error = thrift::Error;
+    /// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+    /// #         Ok(())
+    /// #     }
+    /// # }
+    /// // a backend accepting 3 connections
+    /// #[derive(Clone)]
+    /// struct Maker(Arc<AtomicUsize>);
+    ///
+    /// impl MakeThriftConnection for Maker {
+    ///     type Error = thrift::Error;
+    ///     type Output = Conn;
+    ///     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+    ///         if self.0.fetch_add(1, Ordering::SeqCst) < 3 {
+    ///             Ok(Conn)
+    ///         } else {
+    ///             Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into())
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let manager = ThriftConnectionManager::new(Maker(Arc::default())).with_max_concurrent_connects(2);
+    /// let outcomes = manager.make_many_concurrent(5).await;
+    /// assert_eq!(outcomes.len(), 5);
+    /// assert_eq!(outcomes.iter().filter(|outcome| outcome.is_ok()).count(), 3);
+    /// # }
+    /// ```
+    #[cfg(feature = "impl-bb8")]
+    pub async fn make_many_concurrent(&self, n: usize) -> Vec<Result<T::Output, T::Error>>
+    where
+        T: Clone + Send + Sync + 'static,
+        T::Output: Send + 'static,
+        T::Error: Send + 'static,
+    {
+        let connects: Vec<_> = (0..n)
+            .map(|_| {
+                let manager = self.clone();
+                tokio::spawn(async move {
+                    let _permit = match &manager.connect_limit {
+                        Some(limit) => Some(limit.acquire_async(manager.connect_priority).await),
+                        None => None,
+                    };
+                    let make = manager.clone();
+                    tokio::task::spawn_blocking(move || make.make()).await
+                })
+            })
+            .collect();
+        let mut outcomes = Vec::with_capacity(n);
+        for connect in connects {
+            match connect.await {
+                Ok(Ok(outcome)) => outcomes.push(outcome),
+                Ok(Err(e)) | Err(e) => std::panic::resume_unwind(e.into_panic()),
+            }
+        }
+        outcomes
+    }
+
+    fn is_broken(&self, conn: &mut T::Output) -> bool {
+        let broken = validate::is_evicting()
+            || match &self.broken_policy {
+                Some(policy) => policy.has_broken(conn),
+                None => conn.has_broken(),
+            };
+        #[cfg(feature = "metrics")]
+        if broken {
+            pool_metrics::record_broken(&self.labels);
+        }
+        #[cfg(feature = "tracing")]
+        if broken {
+            tracing::debug!(labels = ?self.labels, "thrift connection found broken");
+        }
+        #[cfg(feature = "impl-bb8")]
+        if broken {
+            self.emit(events::PoolEventKind::Broken, conn);
+        }
+        broken
+    }
+
+    fn validate(&self, conn: &mut T::Output) -> Result<(), <T::Output as ThriftConnection>::Error> {
+        let result = conn.is_valid();
+        #[cfg(feature = "metrics")]
+        pool_metrics::record_validation(result.is_ok(), &self.labels);
+        #[cfg(feature = "tracing")]
+        if result.is_err() {
+            tracing::debug!(labels = ?self.labels, "thrift connection failed validation");
+        }
+        #[cfg(feature = "impl-bb8")]
+        self.emit(
+            match result {
+                Ok(()) => events::PoolEventKind::Validated,
+                Err(_) => events::PoolEventKind::Discarded,
+            },
+            conn,
+        );
+        result
+    }
+
+    fn make(&self) -> Result<T::Output, T::Error> {
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("thrift_pool_connect", labels = ?self.labels).entered();
+        let result = self.make_thrift_connection.make_thrift_connection();
+        #[cfg(feature = "tracing")]
+        {
+            match &result {
+                Ok(_) => tracing::debug!(labels = ?self.labels, "thrift pool connected"),
+                Err(_) => tracing::debug!(labels = ?self.labels, "thrift pool failed to connect"),
+            }
+            span.exit();
+        }
+        #[cfg(feature = "metrics")]
+        pool_metrics::record_connect(result.is_ok(), start.elapsed(), &self.labels);
+        #[cfg(feature = "impl-bb8")]
+        if let Ok(conn) = &result {
+            self.emit(events::PoolEventKind::Created, conn);
+        }
+        result
+    }
+
+    #[cfg(feature = "impl-bb8")]
+    fn emit(&self, kind: events::PoolEventKind, conn: &T::Output) {
+        if let Some(events) = &self.events {
+            events.emit(kind, conn, &self.labels);
+        }
     }
 }
 
@@ -457,15 +2003,23 @@ impl<
     type Error = E;
 
     async fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        self.0.make_thrift_connection()
+        let _permit = match &self.connect_limit {
+            Some(limit) => Some(limit.acquire_async(self.connect_priority).await),
+            None => None,
+        };
+        self.make()
     }
 
     fn has_broken(&self, conn: &mut Self::Connection) -> bool {
-        conn.has_broken()
+        self.is_broken(conn)
     }
 
     async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
-        conn.is_valid()
+        // a connection checked out only to be evicted isn't worth a call
+        if shrink::is_shedding() {
+            return Ok(());
+        }
+        self.validate(conn)
     }
 }
 
@@ -481,14 +2035,18 @@ impl<
     type Error = E;
 
     fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        self.0.make_thrift_connection()
+        let _permit = self
+            .connect_limit
+            .as_deref()
+            .map(|limit| limit.acquire(self.connect_priority));
+        self.make()
     }
 
     fn has_broken(&self, conn: &mut Self::Connection) -> bool {
-        conn.has_broken()
+        self.is_broken(conn)
     }
 
     fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
-        conn.is_valid()
+        self.validate(conn)
     }
 }
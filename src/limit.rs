@@ -0,0 +1,136 @@
+use std::io::{self, Read};
+
+use crate::FromRead;
+
+/// The error (wrapped in an [`io::Error`]) returned by [`ByteLimitedRead`]
+/// once its limit is reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteLimitExceeded {
+    pub limit: u64,
+}
+
+impl std::fmt::Display for ByteLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "connection exceeded its limit of {} bytes read",
+            self.limit
+        )
+    }
+}
+
+impl std::error::Error for ByteLimitExceeded {}
+
+/// A [`Read`] layer that fails once more than `LIMIT` bytes were read through it
+///
+/// This protects the client against a server (malicious or buggy) streaming unbounded data.
+/// The first `LIMIT` bytes are passed through, any read after that fails with an
+/// [`io::ErrorKind::Other`] error wrapping [`ByteLimitExceeded`], which thrift
+/// reports as a [`thrift::Error::Transport`] carrying its message. The count covers the whole life of the connection.
+///
+/// It is a layer: use it between the [`ReadHalf`](thrift::transport::ReadHalf)
+/// and the read transport in the client type
+///
+/// ```
+/// # use std::io::Write;
+/// # use std::net::TcpListener;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{
+/// #     ByteLimitedRead, FromProtocol, MakeThriftConnection, MakeThriftConnectionFromAddrs,
+/// #     ProtocolAccess,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ProtocolAccess for MyThriftClient<Ip, Op> {
+/// #     fn input_protocol_mut(&mut self) -> &mut Ip {
+/// #         &mut self.i_prot
+/// #     }
+/// #     fn output_protocol_mut(&mut self) -> &mut Op {
+/// #         &mut self.o_prot
+/// #     }
+/// # }
+/// // reading more than 64 bytes from a connection fails
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ByteLimitedRead<ReadHalf<TTcpChannel>, 64>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // a server streaming 100 bytes
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let addr = listener.local_addr()?;
+/// std::thread::spawn(move || {
+///     let (mut stream, _) = listener.accept().unwrap();
+///     stream.write_all(&[0; 100]).unwrap();
+///     std::thread::park();
+/// });
+///
+/// let mut conn = MakeThriftConnectionFromAddrs::<Client, _>::new(addr).make_thrift_connection()?;
+/// for _ in 0..16 {
+///     conn.input_protocol_mut().read_i32()?;
+/// }
+/// let err = conn.input_protocol_mut().read_i32().unwrap_err();
+/// match err {
+///     thrift::Error::Transport(e) => assert!(e.message.contains("limit of 64 bytes")),
+///     e => panic!("unexpected error: {e}"),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ByteLimitedRead<R, const LIMIT: u64> {
+    read: R,
+    count: u64,
+}
+
+impl<R, const LIMIT: u64> ByteLimitedRead<R, LIMIT> {
+    pub fn new(read: R) -> Self {
+        Self { read, count: 0 }
+    }
+
+    /// How many bytes were read so far
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn into_inner(self) -> R {
+        self.read
+    }
+}
+
+impl<R: Read, const LIMIT: u64> Read for ByteLimitedRead<R, LIMIT> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let remaining = LIMIT - self.count;
+        if remaining == 0 {
+            return Err(io::Error::other(ByteLimitExceeded { limit: LIMIT }));
+        }
+        let len = usize::try_from(remaining).map_or(buf.len(), |r| r.min(buf.len()));
+        let n = self.read.read(&mut buf[..len])?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read, const LIMIT: u64> FromRead for ByteLimitedRead<R, LIMIT> {
+    type Read = R;
+    fn from_read(read: R) -> Self {
+        Self::new(read)
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use crate::{MakeThriftConnection, ThriftConnection, TransportInfo, TransportKind};
+
+/// The error returned by [`LocalValidityConnection::is_valid`](ThriftConnection::is_valid)
+/// when its predicate finds the connection stale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaleConnection;
+
+impl std::fmt::Display for StaleConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the connection is stale")
+    }
+}
+
+impl std::error::Error for StaleConnection {}
+
+impl From<StaleConnection> for thrift::Error {
+    fn from(e: StaleConnection) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// A [`ThriftConnection`] validated by a predicate on its local state,
+/// instead of a round-trip to the server. Created by [`LocalValidityMaker`]
+///
+/// [`ThriftConnection::is_valid`] returns [`StaleConnection`] when the predicate returns
+/// `false`, without calling the `is_valid` of `C`: no network I/O is done. This suits
+/// checks that don't need the server (e.g. the connection's auth token expired),
+/// and makes checkouts cheaper than a ping, at the cost of not noticing a dead peer
+/// (which still shows up as [`ThriftConnection::has_broken`] or failed calls)
+///
+/// It derefs to `C`
+pub struct LocalValidityConnection<C, F> {
+    conn: C,
+    is_fresh: Arc<F>,
+}
+
+impl<C, F> LocalValidityConnection<C, F> {
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+impl<C: std::fmt::Debug, F> std::fmt::Debug for LocalValidityConnection<C, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalValidityConnection")
+            .field("conn", &self.conn)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C, F> Deref for LocalValidityConnection<C, F> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<C, F> DerefMut for LocalValidityConnection<C, F> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<C, F> ThriftConnection for LocalValidityConnection<C, F>
+where
+    C: ThriftConnection,
+    C::Error: From<StaleConnection>,
+    F: Fn(&C) -> bool,
+{
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        if (self.is_fresh)(&self.conn) {
+            Ok(())
+        } else {
+            Err(StaleConnection.into())
+        }
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.conn.has_broken()
+    }
+}
+
+/// A [`MakeThriftConnection`] wrapping the connections of the inner maker `M`
+/// in [`LocalValidityConnection`]s validated by `is_fresh`
+///
+/// ```
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::sync::Arc;
+/// # use thrift_pool::{LocalValidityMaker, MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+/// // connections authenticated with a token, which can be rotated
+/// struct Conn {
+///     token: usize,
+/// }
+///
+/// impl ThriftConnection for Conn {
+///     type Error = thrift::Error;
+///     fn is_valid(&mut self) -> Result<(), Self::Error> {
+///         unreachable!("no round-trip")
+///     }
+/// }
+///
+/// struct Maker(Arc<AtomicUsize>);
+///
+/// impl MakeThriftConnection for Maker {
+///     type Error = thrift::Error;
+///     type Output = Conn;
+///     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+///         Ok(Conn { token: self.0.load(Ordering::SeqCst) })
+///     }
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let current_token = Arc::new(AtomicUsize::new(0));
+/// let maker = LocalValidityMaker::new(Maker(current_token.clone()), {
+///     let current_token = current_token.clone();
+///     move |conn: &Conn| conn.token == current_token.load(Ordering::SeqCst)
+/// });
+/// let pool = r2d2::Pool::builder()
+///     .max_size(1)
+///     .build(ThriftConnectionManager::new(maker))?;
+///
+/// assert_eq!(pool.get()?.token, 0);
+///
+/// // the token is rotated: the connection is stale, and replaced on checkout
+/// current_token.store(1, Ordering::SeqCst);
+/// assert_eq!(pool.get()?.token, 1);
+/// assert_eq!(pool.state().connections, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct LocalValidityMaker<M, F> {
+    maker: M,
+    is_fresh: Arc<F>,
+}
+
+impl<M, F> LocalValidityMaker<M, F> {
+    /// Consider connections valid as long as `is_fresh` returns `true`
+    pub fn new(maker: M, is_fresh: F) -> Self {
+        Self {
+            maker,
+            is_fresh: Arc::new(is_fresh),
+        }
+    }
+
+    pub fn maker(&self) -> &M {
+        &self.maker
+    }
+}
+
+impl<M: Clone, F> Clone for LocalValidityMaker<M, F> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            is_fresh: self.is_fresh.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug, F> std::fmt::Debug for LocalValidityMaker<M, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalValidityMaker")
+            .field("maker", &self.maker)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M, F> MakeThriftConnection for LocalValidityMaker<M, F>
+where
+    M: MakeThriftConnection,
+    F: Fn(&M::Output) -> bool,
+{
+    type Error = M::Error;
+
+    type Output = LocalValidityConnection<M::Output, F>;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        Ok(LocalValidityConnection {
+            conn: self.maker.make_thrift_connection()?,
+            is_fresh: self.is_fresh.clone(),
+        })
+    }
+}
+
+impl<M: TransportInfo, F> TransportInfo for LocalValidityMaker<M, F> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    io::{Read, Write},
+    net::ToSocketAddrs,
+};
+
+use thrift::transport::{ReadHalf, TIoChannel, TTcpChannel, WriteHalf};
+
+use crate::{
+    FromProtocolWithSocket, FromRead, FromReadTransport, FromWrite, FromWriteTransport,
+    MakeThriftConnection, MakeThriftConnectionFromAddrs, TransportInfo, TransportKind,
+};
+
+type NoReadMiddleware = fn(ReadHalf<TTcpChannel>) -> ReadHalf<TTcpChannel>;
+type NoWriteMiddleware = fn(WriteHalf<TTcpChannel>) -> WriteHalf<TTcpChannel>;
+
+impl<T, S> MakeThriftConnectionFromAddrs<T, S> {
+    /// Wrap the [`ReadHalf`] of every new channel with `read_middleware`,
+    /// whose output the read transport is then built from
+    ///
+    /// Unlike the layers (see [`ByteLimitedRead`](crate::ByteLimitedRead)), which are built
+    /// from the type alone, the middleware is a closure, so each wrapper can be given state
+    /// (e.g. a shared counter). The transport of `T` must be built from the output of
+    /// `read_middleware`, e.g. `TBufferedReadTransport<MyRead<ReadHalf<TTcpChannel>>>`.
+    /// See [`MiddlewareMaker`]
+    pub fn with_read_middleware<FR, R>(
+        self,
+        read_middleware: FR,
+    ) -> MiddlewareMaker<T, S, FR, NoWriteMiddleware>
+    where
+        FR: Fn(ReadHalf<TTcpChannel>) -> R,
+        R: Read,
+    {
+        MiddlewareMaker {
+            maker: self,
+            read_middleware,
+            write_middleware: |write| write,
+        }
+    }
+
+    /// Wrap the [`WriteHalf`] of every new channel with `write_middleware`,
+    /// see [`MakeThriftConnectionFromAddrs::with_read_middleware`]
+    pub fn with_write_middleware<FW, W>(
+        self,
+        write_middleware: FW,
+    ) -> MiddlewareMaker<T, S, NoReadMiddleware, FW>
+    where
+        FW: Fn(WriteHalf<TTcpChannel>) -> W,
+        W: Write,
+    {
+        MiddlewareMaker {
+            maker: self,
+            read_middleware: |read| read,
+            write_middleware,
+        }
+    }
+}
+
+/// A [`MakeThriftConnectionFromAddrs`] that passes the halves of every new channel through
+/// user-supplied [`Read`]/[`Write`] wrappers (rate limiting, logging, byte counting, ...)
+/// before building the transports
+///
+/// Created by [`MakeThriftConnectionFromAddrs::with_read_middleware`] and/or
+/// [`MakeThriftConnectionFromAddrs::with_write_middleware`]. Several wrappers are stacked
+/// by nesting them in one closure
+///
+/// ```
+/// # use std::io::{self, Read, Write};
+/// # use std::net::TcpListener;
+/// # use std::sync::atomic::{AtomicU64, Ordering};
+/// # use std::sync::Arc;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{FromProtocol, MakeThriftConnection, MakeThriftConnectionFromAddrs};
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// // counts the bytes going through it
+/// struct Counting<T> {
+///     inner: T,
+///     count: Arc<AtomicU64>,
+/// }
+///
+/// impl<R: Read> Read for Counting<R> {
+///     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+///         let n = self.inner.read(buf)?;
+///         self.count.fetch_add(n as u64, Ordering::Relaxed);
+///         Ok(n)
+///     }
+/// }
+///
+/// impl<W: Write> Write for Counting<W> {
+///     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+///         let n = self.inner.write(buf)?;
+///         self.count.fetch_add(n as u64, Ordering::Relaxed);
+///         Ok(n)
+///     }
+///     fn flush(&mut self) -> io::Result<()> {
+///         self.inner.flush()
+///     }
+/// }
+///
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<Counting<ReadHalf<TTcpChannel>>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<Counting<WriteHalf<TTcpChannel>>>>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let addr = listener.local_addr()?;
+///
+/// let read = Arc::new(AtomicU64::new(0));
+/// let written = Arc::new(AtomicU64::new(0));
+/// let (read_count, written_count) = (read.clone(), written.clone());
+/// let maker = MakeThriftConnectionFromAddrs::<Client, _>::new(addr)
+///     .with_read_middleware(move |inner| Counting {
+///         inner,
+///         count: read_count.clone(),
+///     })
+///     .with_write_middleware(move |inner| Counting {
+///         inner,
+///         count: written_count.clone(),
+///     });
+///
+/// let mut conn = maker.make_thrift_connection()?;
+/// let (mut server, _) = listener.accept()?;
+///
+/// conn.o_prot.write_i32(42)?;
+/// conn.o_prot.flush()?;
+/// server.read_exact(&mut [0; 4])?;
+/// assert_eq!(written.load(Ordering::Relaxed), 4);
+///
+/// server.write_all(&7i64.to_be_bytes())?;
+/// assert_eq!(conn.i_prot.read_i64()?, 7);
+/// assert_eq!(read.load(Ordering::Relaxed), 8);
+/// # Ok(())
+/// # }
+/// ```
+pub struct MiddlewareMaker<T, S, FR, FW> {
+    maker: MakeThriftConnectionFromAddrs<T, S>,
+    read_middleware: FR,
+    write_middleware: FW,
+}
+
+impl<T, S, FR, FW> MiddlewareMaker<T, S, FR, FW> {
+    /// Replace the read middleware,
+    /// see [`MakeThriftConnectionFromAddrs::with_read_middleware`]
+    pub fn with_read_middleware<FR2, R>(
+        self,
+        read_middleware: FR2,
+    ) -> MiddlewareMaker<T, S, FR2, FW>
+    where
+        FR2: Fn(ReadHalf<TTcpChannel>) -> R,
+        R: Read,
+    {
+        MiddlewareMaker {
+            maker: self.maker,
+            read_middleware,
+            write_middleware: self.write_middleware,
+        }
+    }
+
+    /// Replace the write middleware,
+    /// see [`MakeThriftConnectionFromAddrs::with_write_middleware`]
+    pub fn with_write_middleware<FW2, W>(
+        self,
+        write_middleware: FW2,
+    ) -> MiddlewareMaker<T, S, FR, FW2>
+    where
+        FW2: Fn(WriteHalf<TTcpChannel>) -> W,
+        W: Write,
+    {
+        MiddlewareMaker {
+            maker: self.maker,
+            read_middleware: self.read_middleware,
+            write_middleware,
+        }
+    }
+
+    pub fn maker(&self) -> &MakeThriftConnectionFromAddrs<T, S> {
+        &self.maker
+    }
+}
+
+impl<T, S: Clone, FR: Clone, FW: Clone> Clone for MiddlewareMaker<T, S, FR, FW> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            read_middleware: self.read_middleware.clone(),
+            write_middleware: self.write_middleware.clone(),
+        }
+    }
+}
+
+impl<T, S: std::fmt::Debug, FR, FW> std::fmt::Debug for MiddlewareMaker<T, S, FR, FW> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MiddlewareMaker")
+            .field("maker", &self.maker)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, FR, R, RT, IP, FW, W, WT, OP, T> MakeThriftConnection for MiddlewareMaker<T, S, FR, FW>
+where
+    S: ToSocketAddrs,
+    FR: Fn(ReadHalf<TTcpChannel>) -> R,
+    R: Read,
+    RT: FromRead<Read = R>,
+    IP: FromReadTransport<ReadTransport = RT>,
+    FW: Fn(WriteHalf<TTcpChannel>) -> W,
+    W: Write,
+    WT: FromWrite<Write = W>,
+    OP: FromWriteTransport<WriteTransport = WT>,
+    T: FromProtocolWithSocket<InputProtocol = IP, OutputProtocol = OP>,
+{
+    type Error = thrift::Error;
+
+    type Output = T;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let stream = self.maker.open_stream()?;
+        let socket = stream.try_clone()?;
+        let (read, write) = TTcpChannel::with_stream(stream).split()?;
+
+        let read_transport = RT::from_read((self.read_middleware)(read));
+        let input_protocol = IP::from_read_transport(read_transport);
+
+        let write_transport = WT::from_write((self.write_middleware)(write));
+        let output_protocol = OP::from_write_transport(write_transport);
+
+        T::from_protocol_with_socket(input_protocol, output_protocol, &socket)
+    }
+}
+
+impl<T, S, FR, FW> TransportInfo for MiddlewareMaker<T, S, FR, FW> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
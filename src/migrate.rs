@@ -0,0 +1,91 @@
+use crate::{MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+
+/// The error returned by [`migrate_connection`], giving the connection back when possible
+#[derive(Debug)]
+pub enum MigrateError<C, E> {
+    /// The connection failed [`ThriftConnection::is_valid`], and was dropped
+    Invalid(E),
+    /// The connection reports it [has broken](ThriftConnection::has_broken)
+    Broken(C),
+    /// The new pool is full
+    NoCapacity(C),
+}
+
+impl<C, E: std::fmt::Display> std::fmt::Display for MigrateError<C, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid(e) => write!(f, "the connection is invalid: {e}"),
+            Self::Broken(_) => f.write_str("the connection has broken"),
+            Self::NoCapacity(_) => f.write_str("the pool is full"),
+        }
+    }
+}
+
+impl<C: std::fmt::Debug, E: std::error::Error + 'static> std::error::Error for MigrateError<C, E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Invalid(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Validate `conn` and, if it is healthy, add it to the idle connections of `new_pool`
+///
+/// This is meant for rolling config changes, where connections are moved to a new pool
+/// instead of being reopened. What can be moved is limited by what the pools allow:
+///
+/// * [`bb8::Pool::add`] is the only public way to insert a connection into a pool, which is
+///   why this is only available for `bb8`; `r2d2` always creates its connections itself
+/// * Neither pool lets a connection be taken out of a [`bb8::PooledConnection`] (or
+///   [`r2d2::PooledConnection`]): dropping it always returns it to the pool it came from.
+///   So the connection has to be owned, like the ones from [`bb8::Pool::dedicated_connection`]
+///
+/// ```
+/// # use thrift_pool::{migrate_connection, MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+/// # #[derive(Debug)]
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old_pool = bb8::Pool::builder().build(ThriftConnectionManager::new(Maker)).await?;
+/// let new_pool = bb8::Pool::builder().build(ThriftConnectionManager::new(Maker)).await?;
+///
+/// let conn = old_pool.dedicated_connection().await?;
+/// migrate_connection(conn, &new_pool)?;
+/// assert_eq!(new_pool.state().idle_connections, 1);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// See [`MigrateError`]
+pub fn migrate_connection<T>(
+    mut conn: T::Output,
+    new_pool: &bb8::Pool<ThriftConnectionManager<T>>,
+) -> Result<(), MigrateError<T::Output, T::Error>>
+where
+    T: MakeThriftConnection + Send + Sync + 'static,
+    T::Error: Send + std::fmt::Debug + 'static,
+    T::Output: ThriftConnection<Error = T::Error> + Send + 'static,
+{
+    conn.is_valid().map_err(MigrateError::Invalid)?;
+    new_pool.add(conn).map_err(|e| match e {
+        bb8::AddError::Broken(conn) => MigrateError::Broken(conn),
+        bb8::AddError::NoCapacity(conn) => MigrateError::NoCapacity(conn),
+    })
+}
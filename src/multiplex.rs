@@ -0,0 +1,425 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    marker::PhantomData,
+    net::{Shutdown, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, MutexGuard, Weak,
+    },
+};
+
+use thrift::{
+    protocol::{TBinaryInputProtocol, TBinaryOutputProtocol},
+    TransportErrorKind,
+};
+
+use crate::{FrameTooLarge, FromProtocol, DEFAULT_MAX_FRAME_SIZE};
+
+const VERSION_1: u32 = 0x8001_0000;
+const VERSION_MASK: u32 = 0xffff_0000;
+const ONEWAY: u8 = 4;
+
+/// Find the sequence id in a message encoded with the strict binary protocol
+fn sequence_number_offset(message: &[u8]) -> io::Result<usize> {
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let header = message
+        .get(..8)
+        .ok_or_else(|| invalid("message too short for a binary message header"))?;
+    let version = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    if version & VERSION_MASK != VERSION_1 {
+        return Err(invalid(
+            "only the strict binary protocol can be multiplexed",
+        ));
+    }
+    let name_len = i32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+    let offset = usize::try_from(name_len)
+        .ok()
+        .and_then(|n| n.checked_add(8))
+        .filter(|offset| message.len() >= offset + 4)
+        .ok_or_else(|| invalid("message too short for its method name"))?;
+    Ok(offset)
+}
+
+fn read_sequence_number(message: &[u8], offset: usize) -> i32 {
+    let bytes = &message[offset..offset + 4];
+    i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn write_sequence_number(message: &mut [u8], offset: usize, sequence_number: i32) {
+    message[offset..offset + 4].copy_from_slice(&sequence_number.to_be_bytes());
+}
+
+/// A physical connection, shared by the logical channels multiplexed over it
+#[derive(Debug)]
+struct Physical {
+    stream: Mutex<TcpStream>,
+    /// Where to send the reply to each call in flight, by sequence id on the wire
+    pending: Mutex<HashMap<i32, Sender<Vec<u8>>>>,
+    next_sequence_number: AtomicI32,
+    closed: AtomicBool,
+}
+
+impl Physical {
+    fn open(addrs: impl ToSocketAddrs) -> thrift::Result<Arc<Self>> {
+        let stream = TcpStream::connect(addrs)?;
+        let mut reader = stream.try_clone()?;
+        let physical = Arc::new(Self {
+            stream: Mutex::new(stream),
+            pending: Mutex::new(HashMap::new()),
+            next_sequence_number: AtomicI32::new(0),
+            closed: AtomicBool::new(false),
+        });
+
+        // the reader only holds a weak reference, so the connection is closed
+        // once the pool and all its channels are dropped
+        let weak = Arc::downgrade(&physical);
+        std::thread::spawn(move || Self::demultiplex(&mut reader, &weak));
+        Ok(physical)
+    }
+
+    fn demultiplex(reader: &mut TcpStream, physical: &Weak<Self>) {
+        loop {
+            let frame = read_frame(reader);
+            let Some(physical) = physical.upgrade() else {
+                return;
+            };
+            let Ok(reply) = frame else {
+                // an empty reply wakes up the channels waiting for one with an error
+                let mut pending = physical.pending();
+                physical.closed.store(true, Ordering::SeqCst);
+                for (_, sender) in pending.drain() {
+                    let _ = sender.send(Vec::new());
+                }
+                return;
+            };
+            let Ok(offset) = sequence_number_offset(&reply) else {
+                continue;
+            };
+            let sequence_number = read_sequence_number(&reply, offset);
+            let sender = physical.pending().remove(&sequence_number);
+            if let Some(sender) = sender {
+                let _ = sender.send(reply);
+            }
+        }
+    }
+
+    /// The senders of the calls in flight, even if a caller panicked while holding them
+    fn pending(&self) -> MutexGuard<'_, HashMap<i32, Sender<Vec<u8>>>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Send `message`, with its sequence id replaced by a unique one on this connection
+    ///
+    /// Returns said sequence id
+    fn send(&self, message: &mut [u8], reply_to: &Sender<Vec<u8>>) -> io::Result<i32> {
+        let offset = sequence_number_offset(message)?;
+        let len = u32::try_from(message.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "message too long"))?;
+        let sequence_number = self.next_sequence_number.fetch_add(1, Ordering::SeqCst);
+        write_sequence_number(message, offset, sequence_number);
+
+        let oneway = message[3] == ONEWAY;
+        if !oneway {
+            let mut pending = self.pending();
+            if self.closed.load(Ordering::SeqCst) {
+                return Err(io::ErrorKind::NotConnected.into());
+            }
+            pending.insert(sequence_number, reply_to.clone());
+        }
+
+        let mut frame = Vec::with_capacity(4 + message.len());
+        frame.extend_from_slice(&len.to_be_bytes());
+        frame.extend_from_slice(message);
+
+        let written = {
+            let mut stream = self.stream.lock().unwrap_or_else(|e| e.into_inner());
+            stream.write_all(&frame).and_then(|()| stream.flush())
+        };
+        if written.is_err() && !oneway {
+            // no reply will come for a call that wasn't sent
+            self.pending().remove(&sequence_number);
+        }
+        written?;
+        Ok(sequence_number)
+    }
+}
+
+impl Drop for Physical {
+    fn drop(&mut self) {
+        if let Ok(stream) = self.stream.get_mut() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Read a frame of at most [`DEFAULT_MAX_FRAME_SIZE`] bytes, so that a corrupt length prefix
+/// can't make it allocate more
+fn read_frame(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let size = u32::from_be_bytes(len);
+    if size > DEFAULT_MAX_FRAME_SIZE {
+        return Err(FrameTooLarge {
+            size,
+            max: DEFAULT_MAX_FRAME_SIZE,
+        }
+        .into());
+    }
+    let mut frame = vec![0; size as usize];
+    reader.read_exact(&mut frame)?;
+    Ok(frame)
+}
+
+/// Sequence ids on the wire, mapped to the ones the client used
+type SequenceNumbers = Arc<Mutex<HashMap<i32, i32>>>;
+
+/// The read half of a logical channel of a [`MultiplexedPool`]
+#[derive(Debug)]
+pub struct MultiplexedRead {
+    replies: Receiver<Vec<u8>>,
+    sequence_numbers: SequenceNumbers,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for MultiplexedRead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.buf.len() {
+            let mut reply = self.replies.recv().unwrap_or_default();
+            if reply.is_empty() {
+                return Err(io::ErrorKind::ConnectionReset.into());
+            }
+            let offset = sequence_number_offset(&reply)?;
+            let wire = read_sequence_number(&reply, offset);
+            let original = self
+                .sequence_numbers
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&wire);
+            if let Some(original) = original {
+                write_sequence_number(&mut reply, offset, original);
+            }
+            self.buf = reply;
+            self.pos = 0;
+        }
+        let n = (&self.buf[self.pos..]).read(buf)?;
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// The write half of a logical channel of a [`MultiplexedPool`]
+///
+/// Every message is buffered until the client flushes it
+#[derive(Debug)]
+pub struct MultiplexedWrite {
+    physical: Arc<Physical>,
+    reply_to: Sender<Vec<u8>>,
+    sequence_numbers: SequenceNumbers,
+    buf: Vec<u8>,
+}
+
+impl Write for MultiplexedWrite {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let mut message = std::mem::take(&mut self.buf);
+        let offset = sequence_number_offset(&message)?;
+        let original = read_sequence_number(&message, offset);
+        let wire = self.physical.send(&mut message, &self.reply_to)?;
+        self.sequence_numbers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(wire, original);
+        Ok(())
+    }
+}
+
+/// **Experimental**: a pool of clients that share a few physical connections
+///
+/// Instead of opening one TCP connection per client, `connections` are opened upfront
+/// and every client returned by [`get`](Self::get) is a logical channel, multiplexed
+/// with the other channels over one of them (in a round robin fashion).
+/// Calls are told apart by their sequence id: each call gets a unique one on its physical
+/// connection, which is swapped back to the client's own in the reply, so the server has
+/// to be able to answer the calls of a connection concurrently, in any order.
+///
+/// The scope is deliberately narrow:
+///
+/// * Only the framed transport and the strict binary protocol are supported, since the
+///   sequence id has to be found (and rewritten) in every message
+/// * A channel has at most one call in flight at a time, which is how thrift clients work
+/// * Physical connections aren't reopened: once one is closed, the channels using it fail
+///   with [`thrift::TransportErrorKind::NotOpen`] and [`get`](Self::get) skips it
+/// * A reply larger than [`DEFAULT_MAX_FRAME_SIZE`] closes its physical connection,
+///   rather than being allocated
+///
+/// ```
+/// # use std::io::{Read, Write};
+/// # use std::net::TcpListener;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TMessageIdentifier,
+/// #     TMessageType, TOutputProtocol, TStructIdentifier, TType,
+/// # };
+/// # use thrift_pool::{FromProtocol, MultiplexedPool, MultiplexedRead, MultiplexedWrite};
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> MyThriftClient<Ip, Op> {
+/// #     fn ping(&mut self) -> thrift::Result<i32> {
+/// #         self.o_prot.write_message_begin(&TMessageIdentifier::new("ping", TMessageType::Call, 1))?;
+/// #         self.o_prot.write_struct_begin(&TStructIdentifier::new("ping_args"))?;
+/// #         self.o_prot.write_field_stop()?;
+/// #         self.o_prot.write_struct_end()?;
+/// #         self.o_prot.write_message_end()?;
+/// #         self.o_prot.flush()?;
+/// #         let reply = self.i_prot.read_message_begin()?;
+/// #         self.i_prot.skip(TType::Struct)?;
+/// #         self.i_prot.read_message_end()?;
+/// #         Ok(reply.sequence_number)
+/// #     }
+/// # }
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<MultiplexedRead>,
+///     TBinaryOutputProtocol<MultiplexedWrite>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # fn read_frame(stream: &mut impl Read) -> std::io::Result<Vec<u8>> {
+/// #     let mut len = [0; 4];
+/// #     stream.read_exact(&mut len)?;
+/// #     let mut frame = vec![0; u32::from_be_bytes(len) as usize];
+/// #     stream.read_exact(&mut frame)?;
+/// #     Ok(frame)
+/// # }
+/// // a server that accepts a single connection, and answers its first 2 calls in reverse order
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let addr = listener.local_addr()?;
+/// std::thread::spawn(move || {
+///     let (mut stream, _) = listener.accept().unwrap();
+///     let calls = [read_frame(&mut stream).unwrap(), read_frame(&mut stream).unwrap()];
+///     for mut call in calls.into_iter().rev() {
+///         // an empty reply, with the same name and sequence id
+///         call[3] = 2;
+///         stream.write_all(&(call.len() as u32).to_be_bytes()).unwrap();
+///         stream.write_all(&call).unwrap();
+///     }
+/// });
+///
+/// let pool = MultiplexedPool::<Client>::new(addr, 1)?;
+/// let (mut a, mut b) = (pool.get()?, pool.get()?);
+/// std::thread::scope(|s| {
+///     let a = s.spawn(move || a.ping());
+///     let b = s.spawn(move || b.ping());
+///     // each client gets the reply to its own call, with its own sequence id
+///     assert_eq!(a.join().unwrap()?, 1);
+///     assert_eq!(b.join().unwrap()?, 1);
+///     Ok::<_, thrift::Error>(())
+/// })?;
+///
+/// // a server announcing a 4GB reply, and never sending it
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let addr = listener.local_addr()?;
+/// std::thread::spawn(move || {
+///     let (mut stream, _) = listener.accept().unwrap();
+///     read_frame(&mut stream).unwrap();
+///     stream.write_all(&u32::MAX.to_be_bytes()).unwrap();
+///     std::thread::sleep(std::time::Duration::from_secs(60));
+/// });
+///
+/// // fails the call right away
+/// let pool = MultiplexedPool::<Client>::new(addr, 1)?;
+/// assert!(pool.get()?.ping().is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub struct MultiplexedPool<T> {
+    connections: Vec<Arc<Physical>>,
+    next: AtomicUsize,
+    conn: PhantomData<T>,
+}
+
+impl<T> std::fmt::Debug for MultiplexedPool<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultiplexedPool")
+            .field("connections", &self.connections.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> MultiplexedPool<T> {
+    /// Open `connections` physical connections to `addrs`
+    ///
+    /// # Panics
+    ///
+    /// If `connections` is 0
+    pub fn new(addrs: impl ToSocketAddrs + Clone, connections: usize) -> thrift::Result<Self> {
+        assert!(connections > 0, "a MultiplexedPool needs a connection");
+        Ok(Self {
+            connections: (0..connections)
+                .map(|_| Physical::open(addrs.clone()))
+                .collect::<thrift::Result<_>>()?,
+            next: AtomicUsize::new(0),
+            conn: PhantomData,
+        })
+    }
+}
+
+impl<T> MultiplexedPool<T>
+where
+    T: FromProtocol<
+        InputProtocol = TBinaryInputProtocol<MultiplexedRead>,
+        OutputProtocol = TBinaryOutputProtocol<MultiplexedWrite>,
+    >,
+{
+    /// Create a new client, as a logical channel over one of the physical connections
+    ///
+    /// This fails if all of them are closed
+    pub fn get(&self) -> thrift::Result<T> {
+        let start = self.next.fetch_add(1, Ordering::SeqCst);
+        let physical = (0..self.connections.len())
+            .map(|i| &self.connections[(start + i) % self.connections.len()])
+            .find(|physical| !physical.closed.load(Ordering::SeqCst))
+            .ok_or_else(|| {
+                thrift::Error::Transport(thrift::TransportError::new(
+                    TransportErrorKind::NotOpen,
+                    "all the connections of the pool are closed",
+                ))
+            })?;
+
+        let (reply_to, replies) = mpsc::channel();
+        let sequence_numbers = SequenceNumbers::default();
+        let read = MultiplexedRead {
+            replies,
+            sequence_numbers: sequence_numbers.clone(),
+            buf: Vec::new(),
+            pos: 0,
+        };
+        let write = MultiplexedWrite {
+            physical: physical.clone(),
+            reply_to,
+            sequence_numbers,
+            buf: Vec::new(),
+        };
+        Ok(T::from_protocol(
+            TBinaryInputProtocol::new(read, true),
+            TBinaryOutputProtocol::new(write, true),
+        ))
+    }
+}
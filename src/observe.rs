@@ -0,0 +1,247 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{MakeThriftConnection, TransportInfo, TransportKind};
+
+/// Callbacks fired by [`ObservedMaker`] on every connect attempt
+///
+/// All methods do nothing by default, so implementors only
+/// need to override the events they care about
+pub trait ConnectObserver {
+    /// Called after a connection was successfully created
+    fn on_connect_success(&self) {}
+
+    /// Called after a connection creation failed
+    fn on_connect_failure(&self) {}
+
+    /// Called after each connect attempt (successful or not) with how long it took
+    ///
+    /// Suitable for feeding a histogram
+    fn on_connect_duration(&self, _duration: Duration) {}
+
+    /// Called after a connection to `addr` was successfully created,
+    /// when the [`ObservedMaker`] knows its endpoint (see [`ObservedMaker::with_endpoint`])
+    fn on_endpoint_success(&self, _addr: SocketAddr) {}
+
+    /// Called after a connection creation to `addr` failed,
+    /// when the [`ObservedMaker`] knows its endpoint (see [`ObservedMaker::with_endpoint`])
+    fn on_endpoint_failure(&self, _addr: SocketAddr) {}
+}
+
+impl<O: ConnectObserver + ?Sized> ConnectObserver for Arc<O> {
+    fn on_connect_success(&self) {
+        (**self).on_connect_success();
+    }
+
+    fn on_connect_failure(&self) {
+        (**self).on_connect_failure();
+    }
+
+    fn on_connect_duration(&self, duration: Duration) {
+        (**self).on_connect_duration(duration);
+    }
+
+    fn on_endpoint_success(&self, addr: SocketAddr) {
+        (**self).on_endpoint_success(addr);
+    }
+
+    fn on_endpoint_failure(&self, addr: SocketAddr) {
+        (**self).on_endpoint_failure(addr);
+    }
+}
+
+/// The connect attempts to an endpoint counted by [`EndpointStats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EndpointCounts {
+    pub successes: u64,
+    pub failures: u64,
+}
+
+/// A [`ConnectObserver`] counting successful and failed connects per endpoint
+///
+/// Clones share the same counts, so one collector can observe the makers of every endpoint
+/// (each one an [`ObservedMaker::with_endpoint`]), e.g. behind a
+/// [`RoundRobinMaker`](crate::RoundRobinMaker)
+///
+/// ```
+/// # use std::net::{SocketAddr, TcpListener};
+/// # use thrift_pool::{
+/// #     EndpointCounts, EndpointStats, MakeThriftConnection, ObservedMaker, RoundRobinMaker,
+/// # };
+/// # struct Maker(bool);
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = ();
+/// #     fn make_thrift_connection(&self) -> Result<(), thrift::Error> {
+/// #         if self.0 {
+/// #             Ok(())
+/// #         } else {
+/// #             Err("connection refused".into())
+/// #         }
+/// #     }
+/// # }
+/// let up: SocketAddr = "10.0.0.1:9090".parse()?;
+/// let down: SocketAddr = "10.0.0.2:9090".parse()?;
+///
+/// let stats = EndpointStats::default();
+/// let maker = RoundRobinMaker::new(vec![
+///     ObservedMaker::new(Maker(true), stats.clone()).with_endpoint(up),
+///     ObservedMaker::new(Maker(false), stats.clone()).with_endpoint(down),
+/// ]);
+/// for _ in 0..4 {
+///     let _ = maker.make_thrift_connection();
+/// }
+///
+/// let snapshot = stats.snapshot();
+/// assert_eq!(snapshot[&up], EndpointCounts { successes: 2, failures: 0 });
+/// assert_eq!(snapshot[&down], EndpointCounts { successes: 0, failures: 2 });
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EndpointStats {
+    counts: Arc<Mutex<HashMap<SocketAddr, EndpointCounts>>>,
+}
+
+impl EndpointStats {
+    /// The counts of every endpoint seen so far
+    pub fn snapshot(&self) -> HashMap<SocketAddr, EndpointCounts> {
+        self.counts().clone()
+    }
+
+    fn counts(&self) -> std::sync::MutexGuard<'_, HashMap<SocketAddr, EndpointCounts>> {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl ConnectObserver for EndpointStats {
+    fn on_endpoint_success(&self, addr: SocketAddr) {
+        self.counts().entry(addr).or_default().successes += 1;
+    }
+
+    fn on_endpoint_failure(&self, addr: SocketAddr) {
+        self.counts().entry(addr).or_default().failures += 1;
+    }
+}
+
+/// A [`MakeThriftConnection`] that reports every connect attempt
+/// of the inner maker `M` to a [`ConnectObserver`]
+///
+/// The measured duration covers the whole of `M::make_thrift_connection`:
+/// with [`MakeThriftConnectionFromAddrs`](crate::MakeThriftConnectionFromAddrs) that is address resolution
+/// and the TCP connect, and it also includes any handshake done by decorators
+/// wrapped inside this one (such as [`PreflightMaker`](crate::PreflightMaker))
+///
+/// ```
+/// # use std::sync::{Arc, Mutex};
+/// # use std::time::Duration;
+/// # use thrift_pool::{ConnectObserver, MakeThriftConnection, ObservedMaker};
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = ();
+/// #     fn make_thrift_connection(&self) -> Result<(), thrift::Error> {
+/// #         std::thread::sleep(Duration::from_millis(5));
+/// #         Ok(())
+/// #     }
+/// # }
+/// #[derive(Default)]
+/// struct Recorder(Mutex<Vec<Duration>>);
+///
+/// impl ConnectObserver for Recorder {
+///     fn on_connect_duration(&self, duration: Duration) {
+///         self.0.lock().unwrap().push(duration);
+///     }
+/// }
+///
+/// let recorder = Arc::new(Recorder::default());
+/// let maker = ObservedMaker::new(Maker, recorder.clone());
+/// for _ in 0..3 {
+///     maker.make_thrift_connection().unwrap();
+/// }
+///
+/// let durations = recorder.0.lock().unwrap();
+/// assert_eq!(durations.len(), 3);
+/// assert!(durations.iter().all(|d| *d >= Duration::from_millis(5)));
+/// ```
+pub struct ObservedMaker<M, O> {
+    maker: M,
+    observer: O,
+    endpoint: Option<SocketAddr>,
+}
+
+impl<M, O> ObservedMaker<M, O> {
+    pub fn new(maker: M, observer: O) -> Self {
+        Self {
+            maker,
+            observer,
+            endpoint: None,
+        }
+    }
+
+    /// Also report attempts to the observer as made to `addr`, see
+    /// [`ConnectObserver::on_endpoint_success`] and [`ConnectObserver::on_endpoint_failure`]
+    pub fn with_endpoint(mut self, addr: SocketAddr) -> Self {
+        self.endpoint = Some(addr);
+        self
+    }
+
+    pub fn endpoint(&self) -> Option<SocketAddr> {
+        self.endpoint
+    }
+
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+}
+
+impl<M: Clone, O: Clone> Clone for ObservedMaker<M, O> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            observer: self.observer.clone(),
+            endpoint: self.endpoint,
+        }
+    }
+}
+
+impl<M: std::fmt::Debug, O> std::fmt::Debug for ObservedMaker<M, O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObservedMaker")
+            .field("maker", &self.maker)
+            .field("endpoint", &self.endpoint)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M: MakeThriftConnection, O: ConnectObserver> MakeThriftConnection for ObservedMaker<M, O> {
+    type Error = M::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let start = Instant::now();
+        let result = self.maker.make_thrift_connection();
+        self.observer.on_connect_duration(start.elapsed());
+        match result {
+            Ok(_) => self.observer.on_connect_success(),
+            Err(_) => self.observer.on_connect_failure(),
+        }
+        if let Some(addr) = self.endpoint {
+            match result {
+                Ok(_) => self.observer.on_endpoint_success(addr),
+                Err(_) => self.observer.on_endpoint_failure(addr),
+            }
+        }
+        result
+    }
+}
+
+impl<M: TransportInfo, O> TransportInfo for ObservedMaker<M, O> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,139 @@
+use opentelemetry::{
+    trace::{Span, SpanKind, Status, Tracer},
+    KeyValue,
+};
+
+use crate::{MakeThriftConnection, TransportInfo, TransportKind};
+
+/// A [`MakeThriftConnection`] that creates an OpenTelemetry span
+/// for every connect attempt of the inner maker `M`
+///
+/// Spans are named `thrift_pool.connect`, of kind [`SpanKind::Client`], and carry an `outcome`
+/// attribute (`success` or `failure`), plus `net.peer.name` and `net.peer.port` when set with
+/// [`OtelMaker::with_peer`]. On failure, the error is recorded as an exception
+/// and the status of the span is set to [`Status::Error`].
+/// See [`ObservedMaker`](crate::ObservedMaker) for other ways to instrument connections
+///
+/// ```
+/// # use opentelemetry::trace::{Status, TracerProvider};
+/// # use opentelemetry::KeyValue;
+/// # use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+/// # use std::sync::atomic::{AtomicBool, Ordering};
+/// # use thrift_pool::{MakeThriftConnection, OtelMaker};
+/// // a maker that fails every other attempt
+/// # #[derive(Default)]
+/// # struct Maker(AtomicBool);
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = ();
+/// #     fn make_thrift_connection(&self) -> Result<(), thrift::Error> {
+/// #         if self.0.fetch_xor(true, Ordering::SeqCst) {
+/// #             Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into())
+/// #         } else {
+/// #             Ok(())
+/// #         }
+/// #     }
+/// # }
+/// let exporter = InMemorySpanExporter::default();
+/// let provider = SdkTracerProvider::builder()
+///     .with_simple_exporter(exporter.clone())
+///     .build();
+///
+/// let maker = OtelMaker::new(Maker::default(), provider.tracer("thrift-pool"))
+///     .with_peer("backend.internal", 9090);
+/// assert!(maker.make_thrift_connection().is_ok());
+/// assert!(maker.make_thrift_connection().is_err());
+///
+/// let spans = exporter.get_finished_spans()?;
+/// assert_eq!(spans.len(), 2);
+/// assert!(spans[0].attributes.contains(&KeyValue::new("net.peer.name", "backend.internal")));
+/// assert!(spans[0].attributes.contains(&KeyValue::new("net.peer.port", 9090)));
+/// assert!(spans[0].attributes.contains(&KeyValue::new("outcome", "success")));
+/// assert!(spans[1].attributes.contains(&KeyValue::new("outcome", "failure")));
+/// assert!(matches!(spans[1].status, Status::Error { .. }));
+/// assert_eq!(spans[1].events.len(), 1);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct OtelMaker<M, T> {
+    maker: M,
+    tracer: T,
+    peer: Option<(String, u16)>,
+}
+
+impl<M, T> OtelMaker<M, T> {
+    pub fn new(maker: M, tracer: T) -> Self {
+        Self {
+            maker,
+            tracer,
+            peer: None,
+        }
+    }
+
+    /// Add the `net.peer.name` and `net.peer.port` attributes to every span
+    pub fn with_peer(mut self, name: impl Into<String>, port: u16) -> Self {
+        self.peer = Some((name.into(), port));
+        self
+    }
+}
+
+impl<M: Clone, T: Clone> Clone for OtelMaker<M, T> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            tracer: self.tracer.clone(),
+            peer: self.peer.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug, T> std::fmt::Debug for OtelMaker<M, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OtelMaker")
+            .field("maker", &self.maker)
+            .field("peer", &self.peer)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M, T> MakeThriftConnection for OtelMaker<M, T>
+where
+    M: MakeThriftConnection,
+    M::Error: std::error::Error,
+    T: Tracer,
+{
+    type Error = M::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let mut attributes = Vec::new();
+        if let Some((name, port)) = &self.peer {
+            attributes.push(KeyValue::new("net.peer.name", name.clone()));
+            attributes.push(KeyValue::new("net.peer.port", i64::from(*port)));
+        }
+        let mut span = self
+            .tracer
+            .span_builder("thrift_pool.connect")
+            .with_kind(SpanKind::Client)
+            .with_attributes(attributes)
+            .start(&self.tracer);
+
+        let result = self.maker.make_thrift_connection();
+        match &result {
+            Ok(_) => span.set_attribute(KeyValue::new("outcome", "success")),
+            Err(e) => {
+                span.set_attribute(KeyValue::new("outcome", "failure"));
+                span.record_error(e);
+                span.set_status(Status::error(e.to_string()));
+            }
+        }
+        span.end();
+        result
+    }
+}
+
+impl<M: TransportInfo, T> TransportInfo for OtelMaker<M, T> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
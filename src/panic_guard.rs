@@ -0,0 +1,116 @@
+use std::{
+    ops::{Deref, DerefMut},
+    thread,
+};
+
+use crate::validate::evict;
+
+/// A checked out connection `P` (e.g. an [`r2d2::PooledConnection`] and/or a
+/// [`bb8::PooledConnection`]) that is discarded if dropped while its thread panics
+///
+/// A panic can leave a connection in the middle of a call, with half a request written or
+/// a reply left unread, which the next user of the connection would read. When the guard is
+/// dropped by the unwinding, the connection is evicted: [`ThriftConnectionManager`](crate::ThriftConnectionManager)
+/// reports it as broken, so the pool drops it instead of taking it back.
+/// Otherwise the connection goes back to the pool as usual. It derefs to `P`
+///
+/// Only a panic unwinding past the guard is seen:
+/// - with `panic = "abort"`, the process ends before anything is returned to the pool.
+/// - a panic caught by [`std::panic::catch_unwind`] before it reaches the guard (e.g. in
+///   a closure borrowing it through [`AssertUnwindSafe`](std::panic::AssertUnwindSafe)) is over
+///   by the time the guard is dropped: call [`PanicGuard::evict`] after catching it. Moving the
+///   guard into the closure instead discards the connection on a panic
+///
+/// ```
+/// # use thrift_pool::{MakeThriftConnection, PanicGuard, ThriftConnection, ThriftConnectionManager};
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = r2d2::Pool::builder()
+///     .max_size(1)
+///     .min_idle(Some(0))
+///     .build(ThriftConnectionManager::new(Maker))?;
+///
+/// // a clean use returns the connection to the pool
+/// drop(PanicGuard::new(pool.get()?));
+/// assert_eq!(pool.state().idle_connections, 1);
+///
+/// // a panic discards it
+/// let panicked = std::thread::spawn({
+///     let pool = pool.clone();
+///     move || {
+///         let _conn = PanicGuard::new(pool.get().unwrap());
+///         panic!("halfway through a call");
+///     }
+/// })
+/// .join();
+/// assert!(panicked.is_err());
+/// assert_eq!(pool.state().connections, 0);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PanicGuard<P> {
+    /// Only taken by `into_inner` and `evict`
+    conn: Option<P>,
+}
+
+impl<P> PanicGuard<P> {
+    pub fn new(conn: P) -> Self {
+        Self { conn: Some(conn) }
+    }
+
+    /// Unwrap the connection, which isn't guarded anymore
+    pub fn into_inner(mut self) -> P {
+        self.conn.take().expect("the connection is only taken once")
+    }
+
+    /// Return the connection to its pool as broken, so that it is dropped,
+    /// e.g. after catching a panic
+    pub fn evict(mut self) {
+        if let Some(conn) = self.conn.take() {
+            evict(conn);
+        }
+    }
+}
+
+impl<P> Deref for PanicGuard<P> {
+    type Target = P;
+
+    fn deref(&self) -> &Self::Target {
+        self.conn
+            .as_ref()
+            .expect("the connection is only taken on drop")
+    }
+}
+
+impl<P> DerefMut for PanicGuard<P> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.conn
+            .as_mut()
+            .expect("the connection is only taken on drop")
+    }
+}
+
+impl<P> Drop for PanicGuard<P> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            if thread::panicking() {
+                evict(conn);
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::{MakeThriftConnection, TransportInfo, TransportKind};
+
+/// The error returned by [`PausableMaker`] while it is paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Paused;
+
+impl std::fmt::Display for Paused {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("connection creation is paused")
+    }
+}
+
+impl std::error::Error for Paused {}
+
+impl From<Paused> for thrift::Error {
+    fn from(e: Paused) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// A [`MakeThriftConnection`] whose connection creation can be paused and resumed
+///
+/// While paused, new connections fail with [`Paused`]; connections that were already created
+/// (and checked out) are not affected. Clones share the same state, so pausing any clone
+/// (e.g. one kept aside before handing the maker to a pool) pauses them all
+///
+/// ```
+/// # use thrift_pool::{MakeThriftConnection, PausableMaker};
+/// # #[derive(Clone)]
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = ();
+/// #     fn make_thrift_connection(&self) -> Result<(), thrift::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// let maker = PausableMaker::new(Maker);
+/// let handle = maker.clone();
+///
+/// handle.pause();
+/// assert!(maker.is_paused());
+/// assert!(maker.make_thrift_connection().is_err());
+///
+/// handle.resume();
+/// assert!(maker.make_thrift_connection().is_ok());
+/// ```
+#[derive(Debug)]
+pub struct PausableMaker<M> {
+    maker: M,
+    paused: Arc<AtomicBool>,
+}
+
+impl<M> PausableMaker<M> {
+    pub fn new(maker: M) -> Self {
+        Self {
+            maker,
+            paused: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Make new connections fail with [`Paused`]
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Allow new connections again
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+}
+
+impl<M: Clone> Clone for PausableMaker<M> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            paused: self.paused.clone(),
+        }
+    }
+}
+
+impl<M> MakeThriftConnection for PausableMaker<M>
+where
+    M: MakeThriftConnection,
+    M::Error: From<Paused>,
+{
+    type Error = M::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        if self.is_paused() {
+            return Err(Paused.into());
+        }
+        self.maker.make_thrift_connection()
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for PausableMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
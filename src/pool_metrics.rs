@@ -0,0 +1,130 @@
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+use std::collections::HashMap;
+
+/// Counter of the connections [`ThriftConnectionManager`](crate::ThriftConnectionManager)
+/// tried to create, labeled with `outcome` (`success` or `failure`)
+///
+/// With the `metrics` feature, the manager records the metrics named by the
+/// `THRIFT_POOL_*` constants through the [`metrics`] facade, to whatever recorder
+/// the application installed, along with the labels of
+/// [`ThriftConnectionManager::with_labels`](crate::ThriftConnectionManager::with_labels)
+///
+/// ```
+/// # use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+/// # use thrift_pool::{
+/// #     MakeThriftConnection, ThriftConnection, ThriftConnectionManager,
+/// #     THRIFT_POOL_CONNECTS_TOTAL, THRIFT_POOL_CONNECT_DURATION_SECONDS,
+/// #     THRIFT_POOL_VALIDATIONS_TOTAL,
+/// # };
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// # #[cfg(feature = "impl-r2d2")]
+/// # fn main() -> Result<(), thrift::Error> {
+/// use r2d2::ManageConnection;
+///
+/// let recorder = DebuggingRecorder::new();
+/// let snapshotter = recorder.snapshotter();
+/// let manager = ThriftConnectionManager::new(Maker).with_label("role", "scanner");
+///
+/// metrics::with_local_recorder(&recorder, || {
+///     let mut conn = manager.connect()?;
+///     manager.is_valid(&mut conn)?;
+///     manager.is_valid(&mut conn)
+/// })?;
+///
+/// let metrics = snapshotter.snapshot().into_vec();
+/// let value = |name: &str| {
+///     metrics
+///         .iter()
+///         .find(|(key, ..)| key.key().name() == name)
+///         .map(|(.., value)| value)
+/// };
+/// assert_eq!(value(THRIFT_POOL_CONNECTS_TOTAL), Some(&DebugValue::Counter(1)));
+/// assert!(metrics.iter().all(|(key, ..)| key
+///     .key()
+///     .labels()
+///     .any(|label| (label.key(), label.value()) == ("role", "scanner"))));
+/// assert_eq!(value(THRIFT_POOL_VALIDATIONS_TOTAL), Some(&DebugValue::Counter(2)));
+/// assert!(matches!(
+///     value(THRIFT_POOL_CONNECT_DURATION_SECONDS),
+///     Some(DebugValue::Histogram(durations)) if durations.len() == 1
+/// ));
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "impl-r2d2"))]
+/// # fn main() {}
+/// ```
+pub const THRIFT_POOL_CONNECTS_TOTAL: &str = "thrift_pool_connects_total";
+
+/// Histogram of the time taken to create connections, in seconds,
+/// labeled with `outcome` (`success` or `failure`),
+/// see [`THRIFT_POOL_CONNECTS_TOTAL`]
+pub const THRIFT_POOL_CONNECT_DURATION_SECONDS: &str = "thrift_pool_connect_duration_seconds";
+
+/// Counter of [`ThriftConnection::is_valid`](crate::ThriftConnection::is_valid) checks,
+/// labeled with `outcome` (`success` or `failure`), see [`THRIFT_POOL_CONNECTS_TOTAL`]
+pub const THRIFT_POOL_VALIDATIONS_TOTAL: &str = "thrift_pool_validations_total";
+
+/// Counter of the connections found broken, see [`THRIFT_POOL_CONNECTS_TOTAL`]
+pub const THRIFT_POOL_BROKEN_TOTAL: &str = "thrift_pool_broken_total";
+
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+fn outcome(success: bool) -> &'static str {
+    if success {
+        "success"
+    } else {
+        "failure"
+    }
+}
+
+/// The `outcome` label (if any) followed by the labels of the manager
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+fn metric_labels(
+    outcome: Option<&'static str>,
+    labels: &HashMap<String, String>,
+) -> Vec<metrics::Label> {
+    outcome
+        .map(|outcome| metrics::Label::new("outcome", outcome))
+        .into_iter()
+        .chain(
+            labels
+                .iter()
+                .map(|(key, value)| metrics::Label::new(key.clone(), value.clone())),
+        )
+        .collect()
+}
+
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+pub(crate) fn record_connect(
+    success: bool,
+    duration: std::time::Duration,
+    labels: &HashMap<String, String>,
+) {
+    let labels = metric_labels(Some(outcome(success)), labels);
+    metrics::counter!(THRIFT_POOL_CONNECTS_TOTAL, labels.clone()).increment(1);
+    metrics::histogram!(THRIFT_POOL_CONNECT_DURATION_SECONDS, labels).record(duration);
+}
+
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+pub(crate) fn record_validation(success: bool, labels: &HashMap<String, String>) {
+    let labels = metric_labels(Some(outcome(success)), labels);
+    metrics::counter!(THRIFT_POOL_VALIDATIONS_TOTAL, labels).increment(1);
+}
+
+#[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+pub(crate) fn record_broken(labels: &HashMap<String, String>) {
+    metrics::counter!(THRIFT_POOL_BROKEN_TOTAL, metric_labels(None, labels)).increment(1);
+}
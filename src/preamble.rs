@@ -0,0 +1,133 @@
+use std::{io::Write, net::ToSocketAddrs};
+
+use thrift::transport::{ReadHalf, TTcpChannel, WriteHalf};
+
+use crate::{
+    FromProtocolWithSocket, FromRead, FromReadTransport, FromWrite, FromWriteTransport,
+    MakeThriftConnection, MakeThriftConnectionFromAddrs, TransportInfo, TransportKind,
+};
+
+/// A [`MakeThriftConnection`] that writes a fixed preamble to the socket right after
+/// connecting, before the client is built
+///
+/// This is for custom servers expecting some magic bytes before the thrift stream begins.
+/// The preamble is written to the raw socket, below any transport
+/// (it isn't framed nor buffered), so this wraps a [`MakeThriftConnectionFromAddrs`]
+///
+/// ```
+/// # use std::io::Read;
+/// # use std::net::TcpListener;
+/// # use std::sync::mpsc;
+/// # use std::time::Duration;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{FromProtocol, MakeThriftConnection, MakeThriftConnectionFromAddrs, PreambleMaker};
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // a server that only accepts connections starting with the magic bytes
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let addr = listener.local_addr()?;
+/// let (accepted, verdicts) = mpsc::channel();
+/// std::thread::spawn(move || {
+///     for stream in listener.incoming() {
+///         let mut stream = stream.unwrap();
+///         stream.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+///         let mut magic = [0; 4];
+///         let ok = stream.read_exact(&mut magic).is_ok() && &magic == b"MAGC";
+///         accepted.send(ok).unwrap();
+///     }
+/// });
+///
+/// let maker = MakeThriftConnectionFromAddrs::<Client, _>::new(addr);
+///
+/// let _conn = maker.make_thrift_connection()?;
+/// assert!(!verdicts.recv()?);
+///
+/// let _conn = PreambleMaker::new(maker, b"MAGC".to_vec()).make_thrift_connection()?;
+/// assert!(verdicts.recv()?);
+/// # Ok(())
+/// # }
+/// ```
+pub struct PreambleMaker<T, S> {
+    maker: MakeThriftConnectionFromAddrs<T, S>,
+    preamble: Vec<u8>,
+}
+
+impl<T, S> PreambleMaker<T, S> {
+    pub fn new(maker: MakeThriftConnectionFromAddrs<T, S>, preamble: Vec<u8>) -> Self {
+        Self { maker, preamble }
+    }
+
+    pub fn preamble(&self) -> &[u8] {
+        &self.preamble
+    }
+
+    pub fn maker(&self) -> &MakeThriftConnectionFromAddrs<T, S> {
+        &self.maker
+    }
+}
+
+impl<T, S: Clone> Clone for PreambleMaker<T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            preamble: self.preamble.clone(),
+        }
+    }
+}
+
+impl<T, S: std::fmt::Debug> std::fmt::Debug for PreambleMaker<T, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PreambleMaker")
+            .field("maker", &self.maker)
+            .field("preamble", &self.preamble)
+            .finish()
+    }
+}
+
+impl<
+        S: ToSocketAddrs + Clone,
+        RL: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RT: FromRead<Read = RL>,
+        IP: FromReadTransport<ReadTransport = RT>,
+        WL: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WT: FromWrite<Write = WL>,
+        OP: FromWriteTransport<WriteTransport = WT>,
+        T: FromProtocolWithSocket<InputProtocol = IP, OutputProtocol = OP>,
+    > MakeThriftConnection for PreambleMaker<T, S>
+{
+    type Error = thrift::Error;
+
+    type Output = T;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let mut stream = self.maker.open_stream()?;
+        stream.write_all(&self.preamble)?;
+        self.maker.connection_from_stream(stream)
+    }
+}
+
+impl<T, S> TransportInfo for PreambleMaker<T, S> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
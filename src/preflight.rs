@@ -0,0 +1,128 @@
+use crate::{MakeThriftConnection, TransportInfo, TransportKind};
+
+/// A [`MakeThriftConnection`] that runs `preflight` on every connection
+/// right after the inner maker `M` created it
+///
+/// Use this to consume a greeting/version banner or negotiate with the server
+/// before the connection is handed to the pool. If `preflight` returns `Err`,
+/// the connection is dropped and the error is returned instead
+///
+/// ```
+/// # use std::io::Write;
+/// # use std::net::TcpListener;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{
+/// #     FromProtocol, MakeThriftConnection, MakeThriftConnectionFromAddrs, PreflightMaker,
+/// #     ProtocolAccess,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ProtocolAccess for MyThriftClient<Ip, Op> {
+/// #     fn input_protocol_mut(&mut self) -> &mut Ip {
+/// #         &mut self.i_prot
+/// #     }
+/// #     fn output_protocol_mut(&mut self) -> &mut Op {
+/// #         &mut self.o_prot
+/// #     }
+/// # }
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // a server that greets every client with a banner
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let addr = listener.local_addr()?;
+/// std::thread::spawn(move || {
+///     for (i, stream) in listener.incoming().enumerate() {
+///         let banner: &[u8] = if i == 0 { b"thrift-v1" } else { b"thrift-v2" };
+///         let mut stream = stream.unwrap();
+///         stream.write_all(&(banner.len() as i32).to_be_bytes()).unwrap();
+///         stream.write_all(banner).unwrap();
+///         std::mem::forget(stream);
+///     }
+/// });
+///
+/// let maker = PreflightMaker::new(
+///     MakeThriftConnectionFromAddrs::<Client, _>::new(addr),
+///     |conn: &mut Client| {
+///         let banner = conn.input_protocol_mut().read_string()?;
+///         if banner == "thrift-v1" {
+///             Ok(())
+///         } else {
+///             Err(thrift::Error::from(std::io::Error::other(format!(
+///                 "unsupported server version: {banner}"
+///             ))))
+///         }
+///     },
+/// );
+///
+/// assert!(maker.make_thrift_connection().is_ok());
+/// assert!(maker.make_thrift_connection().is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub struct PreflightMaker<M, F> {
+    maker: M,
+    preflight: F,
+}
+
+impl<M, F> PreflightMaker<M, F> {
+    pub fn new(maker: M, preflight: F) -> Self {
+        Self { maker, preflight }
+    }
+}
+
+impl<M: Clone, F: Clone> Clone for PreflightMaker<M, F> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            preflight: self.preflight.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug, F> std::fmt::Debug for PreflightMaker<M, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PreflightMaker")
+            .field("maker", &self.maker)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M, F> MakeThriftConnection for PreflightMaker<M, F>
+where
+    M: MakeThriftConnection,
+    F: Fn(&mut M::Output) -> Result<(), M::Error>,
+{
+    type Error = M::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let mut conn = self.maker.make_thrift_connection()?;
+        (self.preflight)(&mut conn)?;
+        Ok(conn)
+    }
+}
+
+impl<M: TransportInfo, F> TransportInfo for PreflightMaker<M, F> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,251 @@
+use std::{
+    io::{self, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+};
+
+use thrift::transport::{ReadHalf, TTcpChannel, WriteHalf};
+
+use crate::{
+    FromProtocolWithSocket, FromRead, FromReadTransport, FromWrite, FromWriteTransport,
+    MakeThriftConnection, MakeThriftConnectionFromAddrs, TransportInfo, TransportKind,
+};
+
+/// The version of the [PROXY protocol](https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt)
+/// header sent by [`ProxyProtocolMaker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyProtocolVersion {
+    /// The human-readable header, e.g. `PROXY TCP4 10.0.0.1 10.0.0.2 51234 9090\r\n`
+    V1,
+    /// The binary header
+    V2,
+}
+
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+impl ProxyProtocolVersion {
+    /// The header announcing a TCP connection from `source` to `destination`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`io::ErrorKind::InvalidInput`] if `source` and `destination`
+    /// aren't of the same address family
+    pub fn header(self, source: SocketAddr, destination: SocketAddr) -> io::Result<Vec<u8>> {
+        match self {
+            ProxyProtocolVersion::V1 => {
+                let family = match (source, destination) {
+                    (SocketAddr::V4(_), SocketAddr::V4(_)) => "TCP4",
+                    (SocketAddr::V6(_), SocketAddr::V6(_)) => "TCP6",
+                    _ => return Err(mixed_families()),
+                };
+                Ok(format!(
+                    "PROXY {family} {} {} {} {}\r\n",
+                    source.ip(),
+                    destination.ip(),
+                    source.port(),
+                    destination.port()
+                )
+                .into_bytes())
+            }
+            ProxyProtocolVersion::V2 => {
+                let mut header = V2_SIGNATURE.to_vec();
+                // version 2, PROXY command
+                header.push(0x21);
+                match (source, destination) {
+                    (SocketAddr::V4(source), SocketAddr::V4(destination)) => {
+                        // TCP over IPv4
+                        header.push(0x11);
+                        header.extend_from_slice(&12u16.to_be_bytes());
+                        header.extend_from_slice(&source.ip().octets());
+                        header.extend_from_slice(&destination.ip().octets());
+                    }
+                    (SocketAddr::V6(source), SocketAddr::V6(destination)) => {
+                        // TCP over IPv6
+                        header.push(0x21);
+                        header.extend_from_slice(&36u16.to_be_bytes());
+                        header.extend_from_slice(&source.ip().octets());
+                        header.extend_from_slice(&destination.ip().octets());
+                    }
+                    _ => return Err(mixed_families()),
+                }
+                header.extend_from_slice(&source.port().to_be_bytes());
+                header.extend_from_slice(&destination.port().to_be_bytes());
+                Ok(header)
+            }
+        }
+    }
+}
+
+fn mixed_families() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "the source and destination of a PROXY header must be of the same address family",
+    )
+}
+
+/// A [`MakeThriftConnection`] that sends a PROXY protocol header right after connecting,
+/// before any thrift message
+///
+/// This is for servers behind a load balancer that expects one (e.g. HAProxy with
+/// `accept-proxy`), when the client is itself a proxy forwarding connections for someone else.
+/// By default the header announces the addresses of the socket;
+/// [`ProxyProtocolMaker::with_addrs`] claims other ones
+///
+/// ```
+/// # use std::io::{BufRead, BufReader, Read};
+/// # use std::net::{SocketAddr, TcpListener};
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{
+/// #     FromProtocol, MakeThriftConnection, MakeThriftConnectionFromAddrs, ProxyProtocolMaker,
+/// #     ProxyProtocolVersion,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let addr = listener.local_addr()?;
+/// let maker = MakeThriftConnectionFromAddrs::<Client, _>::new(addr);
+///
+/// // v1: the addresses of the socket
+/// let proxy = ProxyProtocolMaker::new(maker.clone(), ProxyProtocolVersion::V1);
+/// let _conn = proxy.make_thrift_connection()?;
+/// let (server, peer) = listener.accept()?;
+/// let mut line = String::new();
+/// BufReader::new(server).read_line(&mut line)?;
+/// assert_eq!(
+///     line,
+///     format!("PROXY TCP4 127.0.0.1 127.0.0.1 {} {}\r\n", peer.port(), addr.port())
+/// );
+///
+/// // v2: claimed addresses
+/// let source: SocketAddr = "192.0.2.1:51234".parse()?;
+/// let destination: SocketAddr = "198.51.100.2:9090".parse()?;
+/// let proxy = ProxyProtocolMaker::new(maker, ProxyProtocolVersion::V2)
+///     .with_addrs(source, destination);
+/// let _conn = proxy.make_thrift_connection()?;
+/// let (mut server, _) = listener.accept()?;
+/// let mut header = [0; 16];
+/// server.read_exact(&mut header)?;
+/// assert_eq!(&header[..12], b"\r\n\r\n\0\r\nQUIT\n");
+/// assert_eq!(header[12], 0x21); // version 2, PROXY
+/// assert_eq!(header[13], 0x11); // TCP over IPv4
+/// let mut addrs = vec![0; u16::from_be_bytes([header[14], header[15]]).into()];
+/// server.read_exact(&mut addrs)?;
+/// assert_eq!(addrs, [192, 0, 2, 1, 198, 51, 100, 2, 0xc8, 0x22, 0x23, 0x82]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct ProxyProtocolMaker<T, S> {
+    maker: MakeThriftConnectionFromAddrs<T, S>,
+    version: ProxyProtocolVersion,
+    addrs: Option<(SocketAddr, SocketAddr)>,
+}
+
+impl<T, S> ProxyProtocolMaker<T, S> {
+    /// Send a `version` header announcing the local and peer addresses of each new socket
+    pub fn new(maker: MakeThriftConnectionFromAddrs<T, S>, version: ProxyProtocolVersion) -> Self {
+        Self {
+            maker,
+            version,
+            addrs: None,
+        }
+    }
+
+    /// Announce a connection from `source` to `destination` instead of the addresses
+    /// of the socket
+    ///
+    /// Connecting fails with [`io::ErrorKind::InvalidInput`] if they aren't
+    /// of the same address family
+    pub fn with_addrs(mut self, source: SocketAddr, destination: SocketAddr) -> Self {
+        self.addrs = Some((source, destination));
+        self
+    }
+
+    pub fn version(&self) -> ProxyProtocolVersion {
+        self.version
+    }
+
+    /// The claimed source and destination, if any
+    pub fn addrs(&self) -> Option<(SocketAddr, SocketAddr)> {
+        self.addrs
+    }
+
+    pub fn maker(&self) -> &MakeThriftConnectionFromAddrs<T, S> {
+        &self.maker
+    }
+
+    fn header(&self, stream: &TcpStream) -> io::Result<Vec<u8>> {
+        let (source, destination) = match self.addrs {
+            Some(addrs) => addrs,
+            None => (stream.local_addr()?, stream.peer_addr()?),
+        };
+        self.version.header(source, destination)
+    }
+}
+
+impl<T, S: Clone> Clone for ProxyProtocolMaker<T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            version: self.version,
+            addrs: self.addrs,
+        }
+    }
+}
+
+impl<T, S: std::fmt::Debug> std::fmt::Debug for ProxyProtocolMaker<T, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProxyProtocolMaker")
+            .field("maker", &self.maker)
+            .field("version", &self.version)
+            .field("addrs", &self.addrs)
+            .finish()
+    }
+}
+
+impl<
+        S: ToSocketAddrs + Clone,
+        RL: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RT: FromRead<Read = RL>,
+        IP: FromReadTransport<ReadTransport = RT>,
+        WL: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WT: FromWrite<Write = WL>,
+        OP: FromWriteTransport<WriteTransport = WT>,
+        T: FromProtocolWithSocket<InputProtocol = IP, OutputProtocol = OP>,
+    > MakeThriftConnection for ProxyProtocolMaker<T, S>
+{
+    type Error = thrift::Error;
+
+    type Output = T;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let mut stream = self.maker.open_stream()?;
+        let header = self.header(&stream)?;
+        stream.write_all(&header)?;
+        self.maker.connection_from_stream(stream)
+    }
+}
+
+impl<T, S> TransportInfo for ProxyProtocolMaker<T, S> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{MakeThriftConnection, TransportInfo, TransportKind};
+
+/// What [`RateLimitedMaker`] does when no connection can be made right now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitMode {
+    /// Wait until the rate limit allows a new connection
+    Block,
+    /// Fail immediately with [`RateLimitExceeded`]
+    Reject,
+}
+
+/// The error returned by [`RateLimitedMaker`] in [`RateLimitMode::Reject`]
+/// when the rate limit is exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitExceeded;
+
+impl std::fmt::Display for RateLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("connection rate limit exceeded")
+    }
+}
+
+impl std::error::Error for RateLimitExceeded {}
+
+impl From<RateLimitExceeded> for thrift::Error {
+    fn from(e: RateLimitExceeded) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// A [`MakeThriftConnection`] that caps how many connections
+/// the inner maker `M` creates per second
+///
+/// This is a token bucket: up to `burst` connections can be made at once,
+/// after which new connections are allowed at `per_second`.
+/// Clones share the same bucket, so the limit holds across
+/// every clone of the connection manager.
+///
+/// Note that [`RateLimitMode::Block`] sleeps the current thread,
+/// which also applies when used from the `bb8` (async) path
+///
+/// ```
+/// # use std::time::{Duration, Instant};
+/// # use thrift_pool::{MakeThriftConnection, RateLimitMode, RateLimitedMaker};
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = ();
+/// #     fn make_thrift_connection(&self) -> Result<(), thrift::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// let maker = RateLimitedMaker::new(Maker, 1.0, 2, RateLimitMode::Reject);
+/// assert!(maker.make_thrift_connection().is_ok());
+/// assert!(maker.make_thrift_connection().is_ok());
+/// assert!(maker.make_thrift_connection().is_err());
+///
+/// let maker = RateLimitedMaker::new(Maker, 20.0, 1, RateLimitMode::Block);
+/// let start = Instant::now();
+/// for _ in 0..3 {
+///     maker.make_thrift_connection().unwrap();
+/// }
+/// assert!(start.elapsed() >= Duration::from_millis(90));
+/// ```
+pub struct RateLimitedMaker<M> {
+    maker: M,
+    per_second: f64,
+    burst: u32,
+    mode: RateLimitMode,
+    bucket: Arc<Mutex<TokenBucket>>,
+}
+
+impl<M> RateLimitedMaker<M> {
+    /// Allow `per_second` new connections per second, with bursts of up to `burst` connections
+    ///
+    /// # Panics
+    ///
+    /// Panics if `per_second` isn't strictly positive or `burst` is 0
+    pub fn new(maker: M, per_second: f64, burst: u32, mode: RateLimitMode) -> Self {
+        assert!(per_second > 0.0, "per_second must be strictly positive");
+        assert!(burst > 0, "burst must be at least 1");
+        Self {
+            maker,
+            per_second,
+            burst,
+            mode,
+            bucket: Arc::new(Mutex::new(TokenBucket {
+                tokens: f64::from(burst),
+                last_refill: Instant::now(),
+            })),
+        }
+    }
+
+    /// Take a token, or return how long to wait until one is available
+    fn try_acquire(&self) -> Result<(), Duration> {
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(f64::from(self.burst));
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.per_second,
+            ))
+        }
+    }
+}
+
+impl<M: Clone> Clone for RateLimitedMaker<M> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            per_second: self.per_second,
+            burst: self.burst,
+            mode: self.mode,
+            bucket: self.bucket.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for RateLimitedMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimitedMaker")
+            .field("maker", &self.maker)
+            .field("per_second", &self.per_second)
+            .field("burst", &self.burst)
+            .field("mode", &self.mode)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M> MakeThriftConnection for RateLimitedMaker<M>
+where
+    M: MakeThriftConnection,
+    M::Error: From<RateLimitExceeded>,
+{
+    type Error = M::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        loop {
+            match (self.try_acquire(), self.mode) {
+                (Ok(()), _) => return self.maker.make_thrift_connection(),
+                (Err(_), RateLimitMode::Reject) => return Err(RateLimitExceeded.into()),
+                (Err(wait), RateLimitMode::Block) => thread::sleep(wait),
+            }
+        }
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for RateLimitedMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    io,
+    net::{Shutdown, TcpStream},
+    ops::{Deref, DerefMut},
+};
+
+use thrift::protocol::TOutputProtocol;
+
+use crate::{FromProtocolWithAddr, FromProtocolWithSocket, ProtocolAccess, ThriftConnection};
+
+/// A [`ThriftConnection`] that keeps a handle on its socket, for operations thrift doesn't
+/// cover (e.g. querying `TCP_INFO` or changing socket options mid-stream)
+///
+/// Since it implements [`FromProtocolWithSocket`], the wrapper can be used directly
+/// as the connection type of [`MakeThriftConnectionFromAddrs`](crate::MakeThriftConnectionFromAddrs).
+/// It derefs to `C`
+///
+/// ```
+/// # use std::net::TcpListener;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{
+/// #     FromProtocol, MakeThriftConnection, MakeThriftConnectionFromAddrs, RawChannelConnection,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// type Client = RawChannelConnection<
+///     MyThriftClient<
+///         TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///         TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+///     >,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let addr = listener.local_addr()?;
+///
+/// let mut conn = MakeThriftConnectionFromAddrs::<Client, _>::new(addr).make_thrift_connection()?;
+/// conn.raw_channel_mut().set_nodelay(true)?;
+/// assert!(conn.raw_channel_mut().nodelay()?);
+/// assert_eq!(conn.raw_channel_mut().peer_addr()?, addr);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RawChannelConnection<C> {
+    conn: C,
+    socket: TcpStream,
+}
+
+impl<C> RawChannelConnection<C> {
+    /// Wrap `conn`, keeping a handle on `socket` (from [`TcpStream::try_clone`])
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the socket can't be cloned
+    pub fn new(conn: C, socket: &TcpStream) -> io::Result<Self> {
+        Ok(Self {
+            conn,
+            socket: socket.try_clone()?,
+        })
+    }
+
+    /// The socket the client's protocols read from and write to
+    ///
+    /// This is the same socket, not a copy of it: reading from it, writing to it, shutting it
+    /// down or making it nonblocking desynchronizes the client's protocols (and whoever checks
+    /// out the connection next). Only use it for operations that leave the stream untouched,
+    /// like reading or setting options
+    pub fn raw_channel_mut(&mut self) -> &mut TcpStream {
+        &mut self.socket
+    }
+
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+impl<C: ProtocolAccess> RawChannelConnection<C> {
+    /// Flush the output protocol of the client, then shut the socket down
+    ///
+    /// Dropping the connection closes the socket too, but silently: this reports whether the
+    /// pending bytes were sent. The socket is shut down even if the flush fails
+    ///
+    /// ```
+    /// # use std::io::Read;
+    /// # use std::net::{Shutdown, TcpListener};
+    /// # use thrift::protocol::{
+    /// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+    /// # };
+    /// # use thrift::transport::{
+    /// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+    /// # };
+    /// # use thrift_pool::{
+    /// #     FromProtocol, MakeThriftConnection, MakeThriftConnectionFromAddrs, ProtocolAccess,
+    /// #     RawChannelConnection,
+    /// # };
+    /// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+    /// #     i_prot: Ip,
+    /// #     o_prot: Op,
+    /// # }
+    /// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+    /// #     type InputProtocol = Ip;
+    /// #     type OutputProtocol = Op;
+    /// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+    /// #         MyThriftClient { i_prot, o_prot }
+    /// #     }
+    /// # }
+    /// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ProtocolAccess for MyThriftClient<Ip, Op> {
+    /// #     fn input_protocol_mut(&mut self) -> &mut Ip {
+    /// #         &mut self.i_prot
+    /// #     }
+    /// #     fn output_protocol_mut(&mut self) -> &mut Op {
+    /// #         &mut self.o_prot
+    /// #     }
+    /// # }
+    /// type Client = RawChannelConnection<
+    ///     MyThriftClient<
+    ///         TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+    ///         TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+    ///     >,
+    /// >;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    /// let maker = MakeThriftConnectionFromAddrs::<Client, _>::new(listener.local_addr()?);
+    ///
+    /// // the pending bytes are sent before the socket is shut down
+    /// let mut conn = maker.make_thrift_connection()?;
+    /// let (mut server, _) = listener.accept()?;
+    /// conn.output_protocol_mut().write_i32(42)?;
+    /// conn.close()?;
+    /// let mut received = Vec::new();
+    /// server.read_to_end(&mut received)?;
+    /// assert_eq!(received, 42i32.to_be_bytes());
+    ///
+    /// // a failed flush is reported
+    /// let mut conn = maker.make_thrift_connection()?;
+    /// conn.output_protocol_mut().write_i32(42)?;
+    /// conn.raw_channel_mut().shutdown(Shutdown::Write)?;
+    /// assert!(conn.close().is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the flush, or else that of the shutdown
+    pub fn close(mut self) -> thrift::Result<()> {
+        let flushed = self.conn.output_protocol_mut().flush();
+        let shut_down = match self.socket.shutdown(Shutdown::Both) {
+            // the peer may have closed it already
+            Err(e) if e.kind() == io::ErrorKind::NotConnected => Ok(()),
+            shut_down => shut_down,
+        };
+        flushed?;
+        Ok(shut_down?)
+    }
+}
+
+impl<C> Deref for RawChannelConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<C> DerefMut for RawChannelConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<C: FromProtocolWithAddr> FromProtocolWithSocket for RawChannelConnection<C> {
+    type InputProtocol = C::InputProtocol;
+
+    type OutputProtocol = C::OutputProtocol;
+
+    fn from_protocol_with_socket(
+        input_protocol: Self::InputProtocol,
+        output_protocol: Self::OutputProtocol,
+        socket: &TcpStream,
+    ) -> thrift::Result<Self> {
+        let conn = C::from_protocol_with_addr(input_protocol, output_protocol, socket.peer_addr()?);
+        Ok(Self::new(conn, socket)?)
+    }
+}
+
+impl<C: ThriftConnection> ThriftConnection for RawChannelConnection<C> {
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.conn.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.conn.has_broken()
+    }
+}
@@ -0,0 +1,89 @@
+use std::net::ToSocketAddrs;
+
+use thrift::transport::{ReadHalf, TIoChannel, TTcpChannel};
+
+use crate::{
+    FromRead, FromReadTransport, MakeThriftConnection, MakeThriftConnectionFromAddrs,
+    ThriftConnection,
+};
+
+/// A connection that can only read, holding an input protocol `IP` and no output protocol
+///
+/// This suits services that only push data to their clients: since there is no output
+/// protocol, writing to such a connection doesn't compile. The limitation is that no
+/// request can be sent either, so generated clients (which write their calls) can't be
+/// built on top of it, and the pool can't validate it with a call:
+/// [`ThriftConnection::is_valid`] always succeeds, and a connection is only dropped
+/// when it reports [`ThriftConnection::has_broken`] (never, unless the pool's
+/// broken policy says so)
+///
+/// Used as the connection type of a [`MakeThriftConnectionFromAddrs`], the maker only
+/// sets up the read half of the socket (the write half is dropped right away)
+///
+/// ```
+/// # use std::io::Write;
+/// # use std::net::TcpListener;
+/// # use thrift::protocol::{TBinaryInputProtocol, TInputProtocol};
+/// # use thrift::transport::{ReadHalf, TBufferedReadTransport, TTcpChannel};
+/// # use thrift_pool::{MakeThriftConnection, MakeThriftConnectionFromAddrs, ReadOnlyConnection};
+/// type Conn = ReadOnlyConnection<TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>>;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // a server pushing a number
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let maker = MakeThriftConnectionFromAddrs::<Conn, _>::new(listener.local_addr()?);
+///
+/// let mut conn = maker.make_thrift_connection()?;
+/// let (mut server, _) = listener.accept()?;
+/// server.write_all(&42i32.to_be_bytes())?;
+/// assert_eq!(conn.input_protocol_mut().read_i32()?, 42);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ReadOnlyConnection<IP> {
+    input_protocol: IP,
+}
+
+impl<IP> ReadOnlyConnection<IP> {
+    pub fn new(input_protocol: IP) -> Self {
+        Self { input_protocol }
+    }
+
+    pub fn input_protocol_mut(&mut self) -> &mut IP {
+        &mut self.input_protocol
+    }
+
+    pub fn into_inner(self) -> IP {
+        self.input_protocol
+    }
+}
+
+impl<IP> ThriftConnection for ReadOnlyConnection<IP> {
+    type Error = thrift::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<
+        S: ToSocketAddrs,
+        RL: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RT: FromRead<Read = RL>,
+        IP: FromReadTransport<ReadTransport = RT>,
+    > MakeThriftConnection for MakeThriftConnectionFromAddrs<ReadOnlyConnection<IP>, S>
+{
+    type Error = thrift::Error;
+
+    type Output = ReadOnlyConnection<IP>;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let channel = TTcpChannel::with_stream(self.open_stream()?);
+        let (read, _) = channel.split()?;
+        let read_transport = RT::from_read(RL::from_read(read));
+        Ok(ReadOnlyConnection::new(IP::from_read_transport(
+            read_transport,
+        )))
+    }
+}
@@ -0,0 +1,195 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::{FromProtocol, ProtocolAccess, ThriftConnection};
+
+/// The error returned by [`ResettingConnection::reset`] when a call was interrupted
+/// mid-message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterruptedCall;
+
+impl std::fmt::Display for InterruptedCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("a call was interrupted mid-message, the connection can't be reused")
+    }
+}
+
+impl std::error::Error for InterruptedCall {}
+
+impl From<InterruptedCall> for thrift::Error {
+    fn from(e: InterruptedCall) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// A [`ThriftConnection`] that tracks the calls made through [`ResettingConnection::call`],
+/// and gets discarded by the pool if one of them was interrupted
+///
+/// A call that panics, or fails with a transport or protocol error, may leave part of its
+/// request unsent or part of its reply unread: the next user of the connection would read
+/// garbage. Thrift transports can't tell how much is left, so such a connection can't be
+/// safely reset, and [`ResettingConnection::reset`] fails. Both `r2d2` and `bb8` call
+/// [`ThriftConnection::has_broken`] when a connection is returned, which resets it there
+/// and reports it as broken (so the pool drops it) if that fails.
+/// Application errors and user errors are replies read in full, and don't interrupt the call
+///
+/// Since it implements [`FromProtocol`], the wrapper can be used directly
+/// as the connection type of [`MakeThriftConnectionFromAddrs`](crate::MakeThriftConnectionFromAddrs).
+/// It derefs to `C`
+///
+/// ```
+/// # use std::panic::{catch_unwind, AssertUnwindSafe};
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::sync::Arc;
+/// # use thrift_pool::{MakeThriftConnection, ResettingConnection, ThriftConnection, ThriftConnectionManager};
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker(Arc<AtomicUsize>);
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = ResettingConnection<Conn>;
+/// #     fn make_thrift_connection(&self) -> Result<Self::Output, thrift::Error> {
+/// #         self.0.fetch_add(1, Ordering::Relaxed);
+/// #         Ok(ResettingConnection::new(Conn))
+/// #     }
+/// # }
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let connects = Arc::new(AtomicUsize::new(0));
+/// let pool = r2d2::Pool::builder()
+///     .max_size(1)
+///     .build(ThriftConnectionManager::new(Maker(connects.clone())))?;
+///
+/// // a call that completes leaves the connection reusable
+/// let mut conn = pool.get()?;
+/// conn.call(|_| Ok(()))?;
+/// assert!(conn.reset().is_ok());
+/// drop(conn);
+/// assert_eq!(pool.state().connections, 1);
+///
+/// // an interrupted call gets the connection discarded
+/// let mut conn = pool.get()?;
+/// let res = catch_unwind(AssertUnwindSafe(|| {
+///     conn.call(|_| -> thrift::Result<()> { panic!("interrupted mid-RPC") })
+/// }));
+/// assert!(res.is_err());
+/// drop(conn);
+/// assert_eq!(pool.state().connections, 0);
+///
+/// // and so does a transport error
+/// let mut conn = pool.get()?;
+/// let res = conn.call(|_| -> thrift::Result<()> {
+///     Err(std::io::Error::from(std::io::ErrorKind::TimedOut).into())
+/// });
+/// assert!(res.is_err());
+/// drop(conn);
+/// assert_eq!(pool.state().connections, 0);
+///
+/// pool.get()?;
+/// assert_eq!(connects.load(Ordering::Relaxed), 3);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ResettingConnection<C> {
+    conn: C,
+    interrupted: bool,
+}
+
+impl<C> ResettingConnection<C> {
+    pub fn new(conn: C) -> Self {
+        Self {
+            conn,
+            interrupted: false,
+        }
+    }
+
+    /// Run the call `f`, remembering if it is interrupted: if it panics,
+    /// or fails with a transport or protocol error
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `f`
+    pub fn call<F, R>(&mut self, f: F) -> thrift::Result<R>
+    where
+        F: FnOnce(&mut C) -> thrift::Result<R>,
+    {
+        // stays set if `f` panics
+        self.interrupted = true;
+        let res = f(&mut self.conn);
+        self.interrupted = matches!(
+            res,
+            Err(thrift::Error::Transport(_) | thrift::Error::Protocol(_))
+        );
+        res
+    }
+
+    /// Get the connection ready for its next user
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InterruptedCall`] if a call was interrupted, the connection should be dropped
+    pub fn reset(&mut self) -> Result<(), InterruptedCall> {
+        if self.interrupted {
+            Err(InterruptedCall)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+impl<C> Deref for ResettingConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<C> DerefMut for ResettingConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<C: FromProtocol> FromProtocol for ResettingConnection<C> {
+    type InputProtocol = C::InputProtocol;
+
+    type OutputProtocol = C::OutputProtocol;
+
+    fn from_protocol(
+        input_protocol: Self::InputProtocol,
+        output_protocol: Self::OutputProtocol,
+    ) -> Self {
+        Self::new(C::from_protocol(input_protocol, output_protocol))
+    }
+}
+
+impl<C: ProtocolAccess> ProtocolAccess for ResettingConnection<C> {
+    fn input_protocol_mut(&mut self) -> &mut Self::InputProtocol {
+        self.conn.input_protocol_mut()
+    }
+
+    fn output_protocol_mut(&mut self) -> &mut Self::OutputProtocol {
+        self.conn.output_protocol_mut()
+    }
+}
+
+impl<C: ThriftConnection> ThriftConnection for ResettingConnection<C> {
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.conn.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.reset().is_err() || self.conn.has_broken()
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    vec,
+};
+
+#[cfg(feature = "impl-bb8")]
+use std::time::Duration;
+
+#[cfg(feature = "impl-bb8")]
+use arc_swap::ArcSwap;
+
+/// A [`ToSocketAddrs`] that falls back to other addresses
+/// when the resolver `S` yields no addresses
+///
+/// [`MakeThriftConnectionFromAddrs`](crate::MakeThriftConnectionFromAddrs) resolves its
+/// addresses on every connect, so `S` can be backed by service discovery. If discovery
+/// momentarily returns an empty set, this uses instead:
+///
+/// * the last non-empty resolution, if [`ResolverFallback::with_cache_last_resolved`] is set
+/// * otherwise the [`ResolverFallback::with_fallback_addrs`] addresses
+///
+/// Resolution errors are returned as is. Clones share the cached resolution
+///
+/// ```
+/// # use std::io;
+/// # use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+/// # use std::sync::{Arc, Mutex};
+/// # use thrift_pool::{MakeThriftConnectionFromAddrs, ResolverFallback};
+/// // a service discovery client
+/// #[derive(Clone, Default)]
+/// struct Discovery(Arc<Mutex<Vec<SocketAddr>>>);
+///
+/// impl ToSocketAddrs for Discovery {
+///     type Iter = std::vec::IntoIter<SocketAddr>;
+///     fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+///         Ok(self.0.lock().unwrap().clone().into_iter())
+///     }
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let backend_listener = TcpListener::bind("127.0.0.1:0")?;
+/// let fallback_listener = TcpListener::bind("127.0.0.1:0")?;
+/// let backend = backend_listener.local_addr()?;
+/// let fallback = fallback_listener.local_addr()?;
+/// let discovery = Discovery::default();
+///
+/// let static_fallback = MakeThriftConnectionFromAddrs::<(), _>::new(
+///     ResolverFallback::new(discovery.clone()).with_fallback_addrs(vec![fallback]),
+/// );
+/// let cached = MakeThriftConnectionFromAddrs::<(), _>::new(
+///     ResolverFallback::new(discovery.clone()).with_cache_last_resolved(true),
+/// );
+///
+/// *discovery.0.lock().unwrap() = vec![backend];
+/// assert_eq!(static_fallback.open_stream()?.peer_addr()?, backend);
+/// assert_eq!(cached.open_stream()?.peer_addr()?, backend);
+///
+/// // discovery returns nothing
+/// discovery.0.lock().unwrap().clear();
+/// assert_eq!(static_fallback.open_stream()?.peer_addr()?, fallback);
+/// assert_eq!(cached.open_stream()?.peer_addr()?, backend);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResolverFallback<S> {
+    resolver: S,
+    fallback_addrs: Vec<SocketAddr>,
+    last_resolved: Option<Arc<Mutex<Vec<SocketAddr>>>>,
+}
+
+impl<S> ResolverFallback<S> {
+    pub fn new(resolver: S) -> Self {
+        Self {
+            resolver,
+            fallback_addrs: Vec::new(),
+            last_resolved: None,
+        }
+    }
+
+    /// Use `fallback_addrs` when the resolver yields no addresses (defaults to none)
+    pub fn with_fallback_addrs(mut self, fallback_addrs: Vec<SocketAddr>) -> Self {
+        self.fallback_addrs = fallback_addrs;
+        self
+    }
+
+    /// Remember the last non-empty resolution, and use it (instead of the fallback addresses)
+    /// when the resolver yields no addresses (defaults to `false`)
+    pub fn with_cache_last_resolved(mut self, cache_last_resolved: bool) -> Self {
+        self.last_resolved = cache_last_resolved.then(Arc::default);
+        self
+    }
+
+    /// The underlying resolver
+    pub fn inner(&self) -> &S {
+        &self.resolver
+    }
+}
+
+impl<S: ToSocketAddrs> ToSocketAddrs for ResolverFallback<S> {
+    type Iter = vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        let resolved: Vec<_> = self.resolver.to_socket_addrs()?.collect();
+        let Some(last_resolved) = &self.last_resolved else {
+            return Ok(if resolved.is_empty() {
+                self.fallback_addrs.clone().into_iter()
+            } else {
+                resolved.into_iter()
+            });
+        };
+        let mut last_resolved = last_resolved.lock().unwrap_or_else(|e| e.into_inner());
+        if !resolved.is_empty() {
+            last_resolved.clone_from(&resolved);
+            Ok(resolved.into_iter())
+        } else if !last_resolved.is_empty() {
+            Ok(last_resolved.clone().into_iter())
+        } else {
+            Ok(self.fallback_addrs.clone().into_iter())
+        }
+    }
+}
+
+/// Addresses resolved once, by
+/// [`MakeThriftConnectionFromAddrs::resolve_now`](crate::MakeThriftConnectionFromAddrs::resolve_now)
+///
+/// Connecting to them never resolves anything again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedAddrs(Vec<SocketAddr>);
+
+impl ResolvedAddrs {
+    /// Resolve `addrs`
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if `addrs` can't be resolved, or resolves to no address
+    pub fn resolve<S: ToSocketAddrs>(addrs: &S) -> io::Result<Self> {
+        let resolved: Vec<_> = addrs.to_socket_addrs()?.collect();
+        if resolved.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "could not resolve to any addresses",
+            ));
+        }
+        Ok(Self(resolved))
+    }
+
+    pub fn as_slice(&self) -> &[SocketAddr] {
+        &self.0
+    }
+}
+
+impl ToSocketAddrs for ResolvedAddrs {
+    type Iter = vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        Ok(self.0.clone().into_iter())
+    }
+}
+
+/// Addresses resolved off the threads of the async runtime, and refreshed in the background,
+/// by [`MakeThriftConnectionFromAddrs::resolve_in_background`](crate::MakeThriftConnectionFromAddrs::resolve_in_background)
+///
+/// Connecting to them never resolves anything: it uses the last successful resolution.
+/// Clones share the same addresses, which are refreshed until every clone is dropped
+#[cfg(feature = "impl-bb8")]
+#[derive(Debug, Clone)]
+pub struct BackgroundResolvedAddrs(Arc<ArcSwap<Vec<SocketAddr>>>);
+
+#[cfg(feature = "impl-bb8")]
+impl BackgroundResolvedAddrs {
+    /// Resolve `addrs` on a blocking thread (see [`tokio::task::spawn_blocking`]), then again
+    /// every `refresh_every` on a background task, keeping the previous addresses when
+    /// a refresh fails
+    ///
+    /// This generalizes [`tokio::net::lookup_host`] to any [`ToSocketAddrs`]
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if `addrs` can't be resolved, or resolves to no address
+    pub async fn resolve<S>(addrs: S, refresh_every: Duration) -> io::Result<Self>
+    where
+        S: ToSocketAddrs + Send + Sync + 'static,
+    {
+        let addrs = Arc::new(addrs);
+        let resolved = Arc::new(ArcSwap::from_pointee(
+            resolve_blocking(addrs.clone()).await?,
+        ));
+        let weak = Arc::downgrade(&resolved);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(refresh_every).await;
+                if weak.strong_count() == 0 {
+                    return;
+                }
+                let Ok(refreshed) = resolve_blocking(addrs.clone()).await else {
+                    continue;
+                };
+                match weak.upgrade() {
+                    Some(resolved) => resolved.store(Arc::new(refreshed)),
+                    None => return,
+                }
+            }
+        });
+        Ok(Self(resolved))
+    }
+
+    /// The addresses of the last successful resolution
+    pub fn current(&self) -> Arc<Vec<SocketAddr>> {
+        self.0.load_full()
+    }
+}
+
+#[cfg(feature = "impl-bb8")]
+async fn resolve_blocking<S>(addrs: Arc<S>) -> io::Result<Vec<SocketAddr>>
+where
+    S: ToSocketAddrs + Send + Sync + 'static,
+{
+    match tokio::task::spawn_blocking(move || ResolvedAddrs::resolve(&*addrs)).await {
+        Ok(resolved) => resolved.map(|resolved| resolved.0),
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+#[cfg(feature = "impl-bb8")]
+impl ToSocketAddrs for BackgroundResolvedAddrs {
+    type Iter = vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        Ok(self.current().to_vec().into_iter())
+    }
+}
@@ -0,0 +1,445 @@
+use std::{io, time::Duration};
+
+use thrift::{TransportError, TransportErrorKind};
+
+use crate::{close::os_error_kind, rng::Rng, MakeThriftConnection, TransportInfo, TransportKind};
+
+/// How [`get_with_retry_r2d2`] and/or [`get_with_retry_bb8`] retry checking out a connection,
+/// and how a [`RetryingMaker`] retries connecting
+///
+/// The n-th retry waits `initial_backoff * multiplier^(n - 1)`, capped at `max_backoff`,
+/// then shortened by a random fraction of up to `jitter` (so callers retrying together
+/// spread out)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryConfig {
+    /// Checkouts to attempt in total, including the first one (at least 1)
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    /// Between 0 (no jitter) and 1, NaN is taken as 0
+    pub jitter: f64,
+    /// Seed of the random number generator used for the jitter, to make it reproducible
+    /// in tests (`None` picks a random seed)
+    pub seed: Option<u64>,
+}
+
+impl Default for RetryConfig {
+    /// 3 attempts, with backoffs from 50ms doubling up to 1s, and a random jitter of 0.5
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(1),
+            multiplier: 2.0,
+            jitter: 0.5,
+            seed: None,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// The waits before each retry, in order
+    #[cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+    fn backoffs(&self) -> impl Iterator<Item = Duration> + '_ {
+        let rng = Rng::new(self.seed);
+        (1..self.max_attempts.max(1)).map(move |retry| {
+            let factor = self.multiplier.max(1.0).powi((retry - 1) as i32);
+            let backoff = self
+                .initial_backoff
+                .mul_f64(factor.min(u32::MAX.into()))
+                .min(self.max_backoff);
+            jittered(backoff, self.jitter, &rng)
+        })
+    }
+}
+
+/// `backoff` shortened by a random fraction of up to `jitter`
+fn jittered(backoff: Duration, jitter: f64, rng: &Rng) -> Duration {
+    backoff.mul_f64(1.0 - clamped(jitter, 0.0, 1.0) * rng.next_unit())
+}
+
+/// `value` clamped to `[min, max]`, or `min` if it is NaN (which `f64::clamp` keeps,
+/// and `Duration::mul_f64` panics on)
+fn clamped(value: f64, min: f64, max: f64) -> f64 {
+    if value.is_nan() {
+        min
+    } else {
+        value.clamp(min, max)
+    }
+}
+
+/// Check out a connection from `pool`, retrying with backoff if that fails
+///
+/// [`r2d2::Pool::get`] only fails when no connection became available within the pool's
+/// connection timeout (whether the pool is exhausted or connections can't be created),
+/// so every error is retried, and the last one is returned once `config.max_attempts`
+/// is reached
+///
+/// ```
+/// # use std::time::{Duration, Instant};
+/// # use thrift_pool::{get_with_retry_r2d2, MakeThriftConnection, RetryConfig, ThriftConnection, ThriftConnectionManager};
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = r2d2::Pool::builder()
+///     .max_size(1)
+///     .connection_timeout(Duration::from_millis(50))
+///     .build(ThriftConnectionManager::new(Maker))?;
+/// let config = RetryConfig {
+///     max_attempts: 10,
+///     ..RetryConfig::default()
+/// };
+///
+/// // exhausted, then available
+/// let conn = pool.get()?;
+/// let release = std::thread::spawn(move || {
+///     std::thread::sleep(Duration::from_millis(150));
+///     drop(conn);
+/// });
+/// assert!(pool.get().is_err());
+/// let conn = get_with_retry_r2d2(&pool, &config)?;
+/// release.join().unwrap();
+///
+/// // exhausted for good (and a NaN jitter is taken as none)
+/// let start = Instant::now();
+/// let config = RetryConfig {
+///     max_attempts: 3,
+///     jitter: f64::NAN,
+///     ..RetryConfig::default()
+/// };
+/// assert!(get_with_retry_r2d2(&pool, &config).is_err());
+/// assert!(start.elapsed() >= Duration::from_millis(150));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns the error of the last attempt
+#[cfg(feature = "impl-r2d2")]
+pub fn get_with_retry_r2d2<M: r2d2::ManageConnection>(
+    pool: &r2d2::Pool<M>,
+    config: &RetryConfig,
+) -> Result<r2d2::PooledConnection<M>, r2d2::Error> {
+    let mut backoffs = config.backoffs();
+    loop {
+        match pool.get() {
+            Ok(conn) => return Ok(conn),
+            Err(e) => match backoffs.next() {
+                Some(backoff) => std::thread::sleep(backoff),
+                None => return Err(e),
+            },
+        }
+    }
+}
+
+/// Check out a connection from `pool`, retrying with backoff if that times out
+///
+/// [`bb8::RunError::TimedOut`] (no connection became available within the pool's connection
+/// timeout) is retried, and returned once `config.max_attempts` is reached.
+/// [`bb8::RunError::User`] is an error of the connection itself, and is returned right away
+///
+/// ```
+/// # use std::time::{Duration, Instant};
+/// # use thrift_pool::{get_with_retry_bb8, MakeThriftConnection, RetryConfig, ThriftConnection, ThriftConnectionManager};
+/// # #[derive(Debug)]
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = bb8::Pool::builder()
+///     .max_size(1)
+///     .connection_timeout(Duration::from_millis(50))
+///     .build(ThriftConnectionManager::new(Maker))
+///     .await?;
+/// let config = RetryConfig {
+///     max_attempts: 10,
+///     ..RetryConfig::default()
+/// };
+///
+/// // exhausted, then available
+/// let conn = pool.get_owned().await?;
+/// tokio::spawn(async move {
+///     tokio::time::sleep(Duration::from_millis(150)).await;
+///     drop(conn);
+/// });
+/// let conn = get_with_retry_bb8(&pool, &config).await?;
+///
+/// // exhausted for good
+/// let start = Instant::now();
+/// let config = RetryConfig {
+///     max_attempts: 3,
+///     ..RetryConfig::default()
+/// };
+/// let err = get_with_retry_bb8(&pool, &config).await.unwrap_err();
+/// assert!(matches!(err, bb8::RunError::TimedOut));
+/// assert!(start.elapsed() >= Duration::from_millis(150));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns the error of the last attempt, or the first [`bb8::RunError::User`]
+#[cfg(feature = "impl-bb8")]
+pub async fn get_with_retry_bb8<'a, M: bb8::ManageConnection>(
+    pool: &'a bb8::Pool<M>,
+    config: &RetryConfig,
+) -> Result<bb8::PooledConnection<'a, M>, bb8::RunError<M::Error>> {
+    let mut backoffs = config.backoffs();
+    loop {
+        match pool.get().await {
+            Ok(conn) => return Ok(conn),
+            Err(bb8::RunError::TimedOut) => match backoffs.next() {
+                Some(backoff) => tokio::time::sleep(backoff).await,
+                None => return Err(bb8::RunError::TimedOut),
+            },
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Why connecting failed, as far as retrying is concerned, see [`ConnectErrorClass::of`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ConnectErrorClass {
+    /// The server actively refused the connection: it is down, or its accept backlog is full
+    Refused,
+    /// The server didn't answer in time
+    TimedOut,
+    Other,
+}
+
+impl ConnectErrorClass {
+    const ALL: [ConnectErrorClass; 3] = [
+        ConnectErrorClass::Refused,
+        ConnectErrorClass::TimedOut,
+        ConnectErrorClass::Other,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Classify `e`
+    ///
+    /// Thrift turns [`io::Error`]s into [`TransportError`]s keeping only their message,
+    /// so the kind is recovered from the OS error code at its end
+    pub fn of(e: &thrift::Error) -> Self {
+        let thrift::Error::Transport(TransportError { kind, message }) = e else {
+            return Self::Other;
+        };
+        if *kind == TransportErrorKind::TimedOut {
+            return Self::TimedOut;
+        }
+        match os_error_kind(message) {
+            Some(io::ErrorKind::ConnectionRefused) => Self::Refused,
+            Some(io::ErrorKind::TimedOut) => Self::TimedOut,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// A [`MakeThriftConnection`] retrying the inner maker `M` with backoff when connecting fails
+///
+/// Retries follow a [`RetryConfig`], except that the backoff grows by a multiplier depending
+/// on the [`ConnectErrorClass`] of the last failure (see
+/// [`RetryingMaker::with_backoff_multiplier`]): the first retry waits
+/// `config.initial_backoff`, and each following one waits the previous backoff times
+/// the multiplier of the last failure's class, capped at `config.max_backoff`.
+/// E.g. a refused connection often means the server's accept backlog is full,
+/// and is better retried after a longer pause than a timeout
+///
+/// Waits block the calling thread. Clones share the random number generator of the jitter,
+/// seeded with `config.seed`
+///
+/// ```
+/// # use std::net::TcpListener;
+/// # use std::time::{Duration, Instant};
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift::{TransportError, TransportErrorKind};
+/// # use thrift_pool::{
+/// #     ConnectErrorClass, FromProtocol, MakeThriftConnection, MakeThriftConnectionFromAddrs,
+/// #     RetryConfig, RetryingMaker, ThriftConnection,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ThriftConnection for MyThriftClient<Ip, Op> {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+///
+/// // a maker whose connects time out
+/// struct Unresponsive;
+///
+/// impl MakeThriftConnection for Unresponsive {
+///     type Error = thrift::Error;
+///     type Output = Client;
+///     fn make_thrift_connection(&self) -> Result<Client, thrift::Error> {
+///         Err(TransportError::new(TransportErrorKind::TimedOut, "connect timed out").into())
+///     }
+/// }
+///
+/// let config = RetryConfig {
+///     max_attempts: 3,
+///     initial_backoff: Duration::from_millis(20),
+///     max_backoff: Duration::from_secs(1),
+///     multiplier: 1.0,
+///     jitter: 0.0,
+///     seed: None,
+/// };
+///
+/// // nothing listens on a closed port: connecting is refused
+/// let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+/// let refused = RetryingMaker::new(MakeThriftConnectionFromAddrs::<Client, _>::new(addr), config.clone())
+///     .with_backoff_multiplier(ConnectErrorClass::Refused, 8.0);
+/// let timed_out = RetryingMaker::new(Unresponsive, config)
+///     .with_backoff_multiplier(ConnectErrorClass::Refused, 8.0)
+///     .with_backoff_multiplier(ConnectErrorClass::TimedOut, f64::NAN);
+///
+/// // waits 20ms, then 20ms * 8
+/// let start = Instant::now();
+/// let err = refused.make_thrift_connection().err().unwrap();
+/// assert_eq!(ConnectErrorClass::of(&err), ConnectErrorClass::Refused);
+/// assert!(start.elapsed() >= Duration::from_millis(180));
+///
+/// // waits 20ms, then 20ms * 1 (a NaN multiplier is taken as 1)
+/// let start = Instant::now();
+/// let err = timed_out.make_thrift_connection().err().unwrap();
+/// assert_eq!(ConnectErrorClass::of(&err), ConnectErrorClass::TimedOut);
+/// let elapsed = start.elapsed();
+/// assert!(elapsed >= Duration::from_millis(40) && elapsed < Duration::from_millis(180));
+/// ```
+pub struct RetryingMaker<M> {
+    maker: M,
+    config: RetryConfig,
+    multipliers: [f64; ConnectErrorClass::ALL.len()],
+    rng: Rng,
+}
+
+impl<M> RetryingMaker<M> {
+    pub fn new(maker: M, config: RetryConfig) -> Self {
+        Self {
+            maker,
+            multipliers: [config.multiplier; ConnectErrorClass::ALL.len()],
+            rng: Rng::new(config.seed),
+            config,
+        }
+    }
+
+    /// Grow the backoff by `multiplier` after failures of `class`
+    /// (defaults to `config.multiplier`, at least 1, and NaN is taken as 1)
+    pub fn with_backoff_multiplier(mut self, class: ConnectErrorClass, multiplier: f64) -> Self {
+        self.multipliers[class.index()] = multiplier;
+        self
+    }
+
+    pub fn backoff_multiplier(&self, class: ConnectErrorClass) -> f64 {
+        self.multipliers[class.index()]
+    }
+
+    pub fn config(&self) -> &RetryConfig {
+        &self.config
+    }
+
+    pub fn maker(&self) -> &M {
+        &self.maker
+    }
+}
+
+impl<M: Clone> Clone for RetryingMaker<M> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            config: self.config.clone(),
+            multipliers: self.multipliers,
+            rng: self.rng.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for RetryingMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryingMaker")
+            .field("maker", &self.maker)
+            .field("config", &self.config)
+            .field("multipliers", &self.multipliers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M: MakeThriftConnection<Error = thrift::Error>> MakeThriftConnection for RetryingMaker<M> {
+    type Error = thrift::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let mut backoff = self.config.initial_backoff.min(self.config.max_backoff);
+        for _ in 1..self.config.max_attempts.max(1) {
+            match self.maker.make_thrift_connection() {
+                Ok(conn) => return Ok(conn),
+                Err(e) => {
+                    std::thread::sleep(jittered(backoff, self.config.jitter, &self.rng));
+                    let multiplier = self.backoff_multiplier(ConnectErrorClass::of(&e));
+                    backoff = backoff
+                        .mul_f64(clamped(multiplier, 1.0, u32::MAX.into()))
+                        .min(self.config.max_backoff);
+                }
+            }
+        }
+        self.maker.make_thrift_connection()
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for RetryingMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,36 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// The splitmix64 random number generator behind every randomized feature
+/// (jitter, weighted picks, ...), shared by its clones
+///
+/// The features using it take a seed (e.g. [`AgingMaker::with_seed`](crate::AgingMaker::with_seed)),
+/// so that their behavior can be reproduced in tests; without one, it is seeded randomly
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(Arc<AtomicU64>);
+
+impl Rng {
+    /// Seeded with `seed`, or randomly if `None`
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(|| RandomState::new().build_hasher().finish());
+        Self(Arc::new(AtomicU64::new(seed)))
+    }
+
+    /// A random number in `[0, 1)`
+    pub(crate) fn next_unit(&self) -> f64 {
+        let mut z = self
+            .0
+            .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,179 @@
+use std::net::ToSocketAddrs;
+
+use crate::{
+    Endpoint, MakeThriftConnection, MakeThriftConnectionBalanced, MakeThriftConnectionFromAddrs,
+    RoundRobin, ThriftConnectionManager, TransportInfo, TransportKind,
+};
+
+/// A [`MakeThriftConnection`] that creates each new connection with the next of its makers,
+/// in turn
+///
+/// This spreads connections evenly across endpoints. There is a single attempt per connection:
+/// if the chosen maker fails, its error is returned, and the next connection uses the next maker.
+/// It is a [`MakeThriftConnectionBalanced`] with the [`RoundRobin`] policy, whose connections
+/// are those of the makers. Clones share the same position
+///
+/// ```
+/// # use thrift_pool::{MakeThriftConnection, RoundRobinMaker};
+/// # struct Maker(u32);
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = u32;
+/// #     fn make_thrift_connection(&self) -> Result<u32, thrift::Error> {
+/// #         Ok(self.0)
+/// #     }
+/// # }
+/// let maker = RoundRobinMaker::new(vec![Maker(0), Maker(1), Maker(2)]);
+/// let conns = (0..6)
+///     .map(|_| maker.make_thrift_connection())
+///     .collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(conns, [0, 1, 2, 0, 1, 2]);
+/// # Ok::<(), thrift::Error>(())
+/// ```
+pub struct RoundRobinMaker<M>(MakeThriftConnectionBalanced<M, RoundRobin>);
+
+impl<M> RoundRobinMaker<M> {
+    /// # Panics
+    ///
+    /// Panics if `makers` is empty
+    pub fn new(makers: Vec<M>) -> Self {
+        assert!(
+            !makers.is_empty(),
+            "RoundRobinMaker needs at least one maker"
+        );
+        Self(MakeThriftConnectionBalanced::new(
+            makers.into_iter().map(Endpoint::new).collect(),
+            RoundRobin::default(),
+        ))
+    }
+
+    pub fn makers(&self) -> impl ExactSizeIterator<Item = &M> + '_ {
+        self.0.endpoints().iter().map(|endpoint| &endpoint.maker)
+    }
+}
+
+impl<T, S> RoundRobinMaker<MakeThriftConnectionFromAddrs<T, S>> {
+    /// A [`MakeThriftConnectionFromAddrs`] per item of `addrs`, in turn
+    ///
+    /// # Panics
+    ///
+    /// Panics if `addrs` is empty
+    pub fn from_addrs(addrs: impl IntoIterator<Item = S>) -> Self {
+        Self::new(
+            addrs
+                .into_iter()
+                .map(MakeThriftConnectionFromAddrs::new)
+                .collect(),
+        )
+    }
+}
+
+impl<M> Clone for RoundRobinMaker<M> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for RoundRobinMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RoundRobinMaker")
+            .field("makers", &self.makers().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M: MakeThriftConnection> MakeThriftConnection for RoundRobinMaker<M> {
+    type Error = M::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let endpoint = self
+            .0
+            .pick()
+            .expect("round-robin picks an endpoint when there is one");
+        self.0.connect_to(endpoint)
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for RoundRobinMaker<M> {
+    /// The kind of the first maker
+    fn transport_kind(&self) -> TransportKind {
+        self.0.transport_kind()
+    }
+}
+
+impl<T, S: ToSocketAddrs>
+    ThriftConnectionManager<RoundRobinMaker<MakeThriftConnectionFromAddrs<T, S>>>
+{
+    /// A manager creating connections to each item of `addrs` in turn,
+    /// see [`RoundRobinMaker::from_addrs`]
+    ///
+    /// ```
+    /// # use std::net::TcpListener;
+    /// # use thrift::protocol::{
+    /// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+    /// # };
+    /// # use thrift::transport::{
+    /// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+    /// # };
+    /// # use thrift_pool::{
+    /// #     FromProtocol, MakeThriftConnectionFromAddrs, RoundRobinMaker, ThriftConnection,
+    /// #     ThriftConnectionManager,
+    /// # };
+    /// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+    /// #     i_prot: Ip,
+    /// #     o_prot: Op,
+    /// # }
+    /// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+    /// #     type InputProtocol = Ip;
+    /// #     type OutputProtocol = Op;
+    /// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+    /// #         MyThriftClient { i_prot, o_prot }
+    /// #     }
+    /// # }
+    /// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ThriftConnection for MyThriftClient<Ip, Op> {
+    /// #     type Error = thrift::Error;
+    /// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+    /// #         Ok(())
+    /// #     }
+    /// # }
+    /// type Client = MyThriftClient<
+    ///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+    ///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+    /// >;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let listeners = (0..3)
+    ///     .map(|_| TcpListener::bind("127.0.0.1:0"))
+    ///     .collect::<Result<Vec<_>, _>>()?;
+    /// let addrs = listeners
+    ///     .iter()
+    ///     .map(TcpListener::local_addr)
+    ///     .collect::<Result<Vec<_>, _>>()?;
+    ///
+    /// let manager: ThriftConnectionManager<RoundRobinMaker<MakeThriftConnectionFromAddrs<Client, _>>> =
+    ///     ThriftConnectionManager::round_robin(addrs);
+    /// let pool = r2d2::Pool::builder()
+    ///     .max_size(3)
+    ///     .min_idle(Some(0))
+    ///     .build(manager)?;
+    ///
+    /// let _conns = (0..3).map(|_| pool.get()).collect::<Result<Vec<_>, _>>()?;
+    /// // each endpoint got one connection
+    /// for listener in &listeners {
+    ///     listener.set_nonblocking(true)?;
+    ///     listener.accept()?;
+    ///     assert!(listener.accept().is_err());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `addrs` is empty
+    pub fn round_robin(addrs: impl IntoIterator<Item = S>) -> Self {
+        Self::new(RoundRobinMaker::from_addrs(addrs))
+    }
+}
@@ -0,0 +1,122 @@
+/// A flattened [`bb8::RunError`], telling apart why a thrift connection couldn't be used
+///
+/// With `bb8` 0.8, [`bb8::Pool::get`] itself only fails with [`bb8::RunError::TimedOut`]:
+/// connections that fail to connect, or fail [`ThriftConnection::is_valid`](crate::ThriftConnection::is_valid)
+/// on checkout, are reported to the pool's error sink and retried until the timeout.
+/// [`bb8::RunError::User`] carries the errors of the connection, from
+/// [`bb8::Pool::dedicated_connection`] or code using `?` on them, and is mapped to
+/// [`ThriftPoolRunError::Connect`]. [`ThriftPoolRunError::Validate`] is for callers validating
+/// a checked-out connection themselves
+///
+/// ```
+/// # use std::time::Duration;
+/// # use thrift_pool::{MakeThriftConnection, ThriftConnection, ThriftConnectionManager, ThriftPoolRunError};
+/// # #[derive(Debug)]
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = bb8::Pool::builder()
+///     .max_size(1)
+///     .connection_timeout(Duration::from_millis(50))
+///     .build(ThriftConnectionManager::new(Maker))
+///     .await?;
+///
+/// // the only connection is checked out
+/// let _conn = pool.get().await?;
+/// let err = ThriftPoolRunError::from(pool.get().await.unwrap_err());
+/// assert!(matches!(err, ThriftPoolRunError::TimedOut));
+/// assert!(err.is_timed_out());
+///
+/// let err = ThriftPoolRunError::from(bb8::RunError::User(thrift::Error::from("refused")));
+/// assert!(matches!(
+///     &err,
+///     ThriftPoolRunError::Connect(thrift::Error::Application(e)) if e.message == "refused"
+/// ));
+/// assert!(err.to_string().starts_with("failed to connect: "));
+///
+/// // back to a thrift::Error
+/// let err: thrift::Error = ThriftPoolRunError::<thrift::Error>::TimedOut.into();
+/// assert!(matches!(
+///     err,
+///     thrift::Error::Transport(e) if e.kind == thrift::TransportErrorKind::TimedOut
+/// ));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub enum ThriftPoolRunError<E> {
+    /// No connection was available before the pool's connection timeout
+    TimedOut,
+    /// A connection couldn't be created
+    Connect(E),
+    /// A connection failed [`ThriftConnection::is_valid`](crate::ThriftConnection::is_valid)
+    Validate(E),
+}
+
+impl<E> ThriftPoolRunError<E> {
+    pub fn is_timed_out(&self) -> bool {
+        matches!(self, Self::TimedOut)
+    }
+
+    /// The error of the connection, if it isn't a timeout
+    pub fn into_inner(self) -> Option<E> {
+        match self {
+            Self::TimedOut => None,
+            Self::Connect(e) | Self::Validate(e) => Some(e),
+        }
+    }
+}
+
+impl<E> From<bb8::RunError<E>> for ThriftPoolRunError<E> {
+    fn from(e: bb8::RunError<E>) -> Self {
+        match e {
+            bb8::RunError::User(e) => Self::Connect(e),
+            bb8::RunError::TimedOut => Self::TimedOut,
+        }
+    }
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for ThriftPoolRunError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TimedOut => f.write_str("timed out waiting for a connection"),
+            Self::Connect(e) => write!(f, "failed to connect: {e}"),
+            Self::Validate(e) => write!(f, "the connection is invalid: {e}"),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for ThriftPoolRunError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::TimedOut => None,
+            Self::Connect(e) | Self::Validate(e) => Some(e),
+        }
+    }
+}
+
+impl From<ThriftPoolRunError<thrift::Error>> for thrift::Error {
+    fn from(e: ThriftPoolRunError<thrift::Error>) -> Self {
+        match e {
+            ThriftPoolRunError::TimedOut => thrift::Error::Transport(thrift::TransportError::new(
+                thrift::TransportErrorKind::TimedOut,
+                "timed out waiting for a connection",
+            )),
+            ThriftPoolRunError::Connect(e) | ThriftPoolRunError::Validate(e) => e,
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use crate::{MakeThriftConnection, TransportInfo, TransportKind};
+
+/// A [`MakeThriftConnection`] sharing `M` behind an [`Arc`], so it is [`Clone`]
+/// without `M` being `Clone`
+///
+/// [`ThriftConnectionManager`](crate::ThriftConnectionManager), and the makers wrapping
+/// other makers, are only `Clone` when the maker inside is. A maker holding a resource that
+/// can't be duplicated (a unique auth token source, a connection budget, ...) makes the whole
+/// stack non-`Clone`, which shows up as "the trait bound `M: Clone` is not satisfied" where it
+/// gets cloned (e.g. to build several pools from one manager). Wrapping that maker in a
+/// `SharedMaker` makes every clone use the same `M`
+///
+/// ```
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use thrift_pool::{MakeThriftConnection, SharedMaker, ThriftConnectionManager};
+/// // not `Clone`: every connection takes the next token
+/// struct TokenMaker {
+///     next_token: AtomicUsize,
+/// }
+///
+/// impl MakeThriftConnection for TokenMaker {
+///     type Error = thrift::Error;
+///     type Output = usize;
+///     fn make_thrift_connection(&self) -> Result<usize, thrift::Error> {
+///         Ok(self.next_token.fetch_add(1, Ordering::Relaxed))
+///     }
+/// }
+///
+/// let maker = SharedMaker::new(TokenMaker {
+///     next_token: AtomicUsize::new(0),
+/// });
+/// let manager = ThriftConnectionManager::new(maker.clone());
+/// let other_manager = manager.clone();
+///
+/// // the clones share the same `TokenMaker`
+/// assert_eq!(maker.make_thrift_connection()?, 0);
+/// assert_eq!(maker.clone().make_thrift_connection()?, 1);
+/// assert_eq!(maker.maker().next_token.load(Ordering::Relaxed), 2);
+/// # drop((manager, other_manager));
+/// # Ok::<(), thrift::Error>(())
+/// ```
+pub struct SharedMaker<M> {
+    maker: Arc<M>,
+}
+
+impl<M> SharedMaker<M> {
+    pub fn new(maker: M) -> Self {
+        Self::from(Arc::new(maker))
+    }
+
+    pub fn maker(&self) -> &M {
+        &self.maker
+    }
+}
+
+impl<M> From<Arc<M>> for SharedMaker<M> {
+    fn from(maker: Arc<M>) -> Self {
+        Self { maker }
+    }
+}
+
+impl<M> Clone for SharedMaker<M> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for SharedMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedMaker")
+            .field("maker", &self.maker)
+            .finish()
+    }
+}
+
+impl<M: MakeThriftConnection> MakeThriftConnection for SharedMaker<M> {
+    type Error = M::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        self.maker.make_thrift_connection()
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for SharedMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{validate::evict, MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+
+tokio::task_local! {
+    /// Set while [`IdleShrinker::tick`] checks out connections to evict
+    static SHEDDING: ();
+}
+
+/// Whether the connection being checked out on this task is only going to be evicted
+pub(crate) fn is_shedding() -> bool {
+    SHEDDING.try_with(|()| ()).is_ok()
+}
+
+#[derive(Debug)]
+struct Sample {
+    at: Instant,
+    /// Checkouts of the pool so far, not counting those made to shed connections
+    checkouts: u64,
+}
+
+/// Sheds idle connections of a `bb8` pool while demand is low, to free backend resources
+/// during quiet periods
+///
+/// Every [`IdleShrinker::tick`] measures the rate of checkouts since the previous tick.
+/// Below [`IdleShrinker::new`]'s `scale_down_below` (in checkouts per second), up to
+/// [`IdleShrinker::with_step`] idle connections are evicted, never going under `min_connections`
+/// connections in total. Shrinking gradually, a tick at a time, avoids dropping connections
+/// a short lull would need right after. Scaling up needs nothing: `bb8` creates connections
+/// as checkouts wait for them. `min_connections` should be at least the `min_idle` of the pool,
+/// which would otherwise replace the evicted connections
+///
+/// ```
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::time::Duration;
+/// # use thrift_pool::{IdleShrinker, MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+/// // counts the validations on checkout
+/// static VALIDATIONS: AtomicUsize = AtomicUsize::new(0);
+///
+/// #[derive(Debug)]
+/// struct Conn;
+///
+/// impl ThriftConnection for Conn {
+///     type Error = thrift::Error;
+///     fn is_valid(&mut self) -> Result<(), Self::Error> {
+///         VALIDATIONS.fetch_add(1, Ordering::SeqCst);
+///         Ok(())
+///     }
+/// }
+///
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = bb8::Pool::builder()
+///     .max_size(4)
+///     .build(ThriftConnectionManager::new(Maker))
+///     .await?;
+/// thrift_pool::grow(&pool, 4).await?;
+///
+/// let shrinker = IdleShrinker::new(1, 100.0).with_step(2);
+/// assert_eq!(shrinker.tick(&pool).await, 0);
+///
+/// // busy: well above 100 checkouts per second
+/// for _ in 0..100 {
+///     drop(pool.get().await?);
+/// }
+/// assert_eq!(shrinker.tick(&pool).await, 0);
+/// assert_eq!(pool.state().connections, 4);
+///
+/// // the load drops, and the evicted connections aren't validated
+/// let validations = VALIDATIONS.load(Ordering::SeqCst);
+/// tokio::time::sleep(Duration::from_millis(100)).await;
+/// assert_eq!(shrinker.tick(&pool).await, 2);
+/// assert_eq!(pool.state().connections, 2);
+/// assert_eq!(VALIDATIONS.load(Ordering::SeqCst), validations);
+/// tokio::time::sleep(Duration::from_millis(100)).await;
+/// assert_eq!(shrinker.tick(&pool).await, 1);
+/// tokio::time::sleep(Duration::from_millis(100)).await;
+/// assert_eq!(shrinker.tick(&pool).await, 0);
+/// assert_eq!(pool.state().connections, 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct IdleShrinker {
+    min_connections: u32,
+    scale_down_below: f64,
+    step: u32,
+    last: Mutex<Option<Sample>>,
+    /// Checkouts made to shed connections, so far
+    own_checkouts: AtomicU64,
+}
+
+impl IdleShrinker {
+    /// Shed connections while the pool serves fewer than `scale_down_below` checkouts per
+    /// second, down to `min_connections` connections
+    pub fn new(min_connections: u32, scale_down_below: f64) -> Self {
+        Self {
+            min_connections,
+            scale_down_below,
+            step: 1,
+            last: Mutex::new(None),
+            own_checkouts: AtomicU64::new(0),
+        }
+    }
+
+    /// Evict up to `step` connections per tick (defaults to 1)
+    pub fn with_step(mut self, step: u32) -> Self {
+        self.step = step;
+        self
+    }
+
+    pub fn min_connections(&self) -> u32 {
+        self.min_connections
+    }
+
+    pub fn scale_down_below(&self) -> f64 {
+        self.scale_down_below
+    }
+
+    pub fn step(&self) -> u32 {
+        self.step
+    }
+
+    /// Measure the checkout rate of `pool` since the previous tick, and shed idle
+    /// connections if it is low, returning how many were evicted
+    ///
+    /// The first tick only takes the initial measure. The connections to evict are checked out
+    /// without validating them, and only while some are idle: a tick never waits for one
+    pub async fn tick<T>(&self, pool: &bb8::Pool<ThriftConnectionManager<T>>) -> u32
+    where
+        T: MakeThriftConnection + Send + Sync + 'static,
+        T::Error: Send + std::fmt::Debug + 'static,
+        T::Output: ThriftConnection<Error = T::Error> + Send + 'static,
+    {
+        let state = pool.state();
+        let stats = &state.statistics;
+        let at = Instant::now();
+        let checkouts = (stats.get_direct + stats.get_waited + stats.get_timed_out)
+            .saturating_sub(self.own_checkouts.load(Ordering::Relaxed));
+        let previous = self
+            .last
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .replace(Sample { at, checkouts });
+        let Some(previous) = previous else {
+            return 0;
+        };
+        let elapsed = at.duration_since(previous.at).max(Duration::from_micros(1));
+        let rate = checkouts.saturating_sub(previous.checkouts) as f64 / elapsed.as_secs_f64();
+        if rate >= self.scale_down_below {
+            return 0;
+        }
+
+        let excess = state.connections.saturating_sub(self.min_connections);
+        let shed = self.step.min(excess).min(state.idle_connections);
+        let mut conns = Vec::new();
+        for _ in 0..shed {
+            // taken by other tasks meanwhile: waiting would only evict a connection made for them
+            if pool.state().idle_connections == 0 {
+                break;
+            }
+            match tokio::time::timeout(Duration::ZERO, SHEDDING.scope((), pool.get())).await {
+                Ok(Ok(conn)) => conns.push(conn),
+                Ok(Err(_)) | Err(_) => break,
+            }
+        }
+        self.own_checkouts
+            .fetch_add(conns.len() as u64, Ordering::Relaxed);
+        let evicted = conns.len() as u32;
+        conns.into_iter().for_each(evict);
+        evicted
+    }
+
+    /// Call [`IdleShrinker::tick`] every `interval`, forever
+    pub async fn run<T>(&self, pool: &bb8::Pool<ThriftConnectionManager<T>>, interval: Duration)
+    where
+        T: MakeThriftConnection + Send + Sync + 'static,
+        T::Error: Send + std::fmt::Debug + 'static,
+        T::Output: ThriftConnection<Error = T::Error> + Send + 'static,
+    {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            self.tick(pool).await;
+        }
+    }
+}
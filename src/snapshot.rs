@@ -0,0 +1,140 @@
+use std::{io, net::SocketAddr, time::Duration};
+
+use crate::{MakeThriftConnectionFromAddrs, MakeThriftConnectionFromResolvedAddrs, ResolvedAddrs};
+
+/// A maker or [`LoadBalancer`](crate::LoadBalancer) whose state can be saved,
+/// and restored by another process
+///
+/// This lets a restarting process skip warming up: its makers connect to the addresses resolved
+/// before the restart, and its balancers start from what was learned about the endpoints
+/// (e.g. the latencies of [`LatencyAware`](crate::LatencyAware)). Neither the connections nor
+/// the random number generators are part of the state
+///
+/// ```
+/// # use std::net::TcpListener;
+/// # use std::time::Duration;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{
+/// #     Endpoint, FromProtocol, LatencyAware, MakeThriftConnection, MakeThriftConnectionBalanced,
+/// #     MakeThriftConnectionFromAddrs, MakeThriftConnectionFromResolvedAddrs, Snapshot,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+/// type Maker = MakeThriftConnectionBalanced<MakeThriftConnectionFromResolvedAddrs<Client>, LatencyAware>;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let listeners = [TcpListener::bind("127.0.0.1:0")?, TcpListener::bind("127.0.0.1:0")?];
+/// let endpoint = |listener: &TcpListener| -> std::io::Result<_> {
+///     let maker = MakeThriftConnectionFromAddrs::<Client, _>::new(listener.local_addr()?)
+///         .with_connect_timeout(Duration::from_millis(500))
+///         .resolve_now()?;
+///     Ok(Endpoint::new(maker).with_zone("eu-west-1a"))
+/// };
+/// let balancer = LatencyAware::default();
+/// balancer.record(0, Duration::from_millis(30));
+/// balancer.record(1, Duration::from_millis(2));
+/// let maker: Maker =
+///     MakeThriftConnectionBalanced::new(vec![endpoint(&listeners[0])?, endpoint(&listeners[1])?], balancer);
+///
+/// // saved before a restart
+/// let saved = serde_json::to_string(&maker.to_snapshot())?;
+///
+/// // and restored after it
+/// let restored = Maker::from_snapshot(serde_json::from_str(&saved)?)?;
+/// assert_eq!(restored.to_snapshot(), maker.to_snapshot());
+/// assert_eq!(restored.balancer().latencies(), maker.balancer().latencies());
+/// let endpoint = &restored.endpoints()[1];
+/// assert_eq!(endpoint.maker.addrs().as_slice(), [listeners[1].local_addr()?]);
+/// assert_eq!(endpoint.maker.connect_timeout(), Some(Duration::from_millis(500)));
+/// assert_eq!(endpoint.zone(), Some("eu-west-1a"));
+/// restored.make_thrift_connection()?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait Snapshot: Sized {
+    /// The saved state
+    type State: serde::Serialize + serde::de::DeserializeOwned;
+
+    fn to_snapshot(&self) -> Self::State;
+
+    /// Restore the state saved by [`Snapshot::to_snapshot`]
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if `state` is invalid
+    fn from_snapshot(state: Self::State) -> io::Result<Self>;
+}
+
+/// The state of a [`MakeThriftConnectionFromResolvedAddrs`]: its addresses and settings
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AddrsSnapshot {
+    pub addrs: Vec<SocketAddr>,
+    #[serde(default)]
+    pub nonblocking: bool,
+    #[serde(default)]
+    pub connect_timeout: Option<Duration>,
+    #[serde(default)]
+    pub read_timeout: Option<Duration>,
+    #[serde(default)]
+    pub write_timeout: Option<Duration>,
+    /// Only restored on Linux, see [`MakeThriftConnectionFromAddrs::with_tcp_user_timeout`]
+    #[serde(default)]
+    pub tcp_user_timeout: Option<Duration>,
+    #[serde(default)]
+    pub ip_tos: Option<u32>,
+}
+
+impl<T> Snapshot for MakeThriftConnectionFromResolvedAddrs<T> {
+    type State = AddrsSnapshot;
+
+    fn to_snapshot(&self) -> AddrsSnapshot {
+        AddrsSnapshot {
+            addrs: self.addrs.as_slice().to_vec(),
+            nonblocking: self.nonblocking,
+            connect_timeout: self.connect_timeout,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+            #[cfg(target_os = "linux")]
+            tcp_user_timeout: self.tcp_user_timeout,
+            #[cfg(not(target_os = "linux"))]
+            tcp_user_timeout: None,
+            ip_tos: self.ip_tos,
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns `Err` if `state` has no address
+    fn from_snapshot(state: AddrsSnapshot) -> io::Result<Self> {
+        let mut maker =
+            MakeThriftConnectionFromAddrs::new(ResolvedAddrs::resolve(&state.addrs.as_slice())?);
+        maker.nonblocking = state.nonblocking;
+        maker.connect_timeout = state.connect_timeout;
+        maker.read_timeout = state.read_timeout;
+        maker.write_timeout = state.write_timeout;
+        #[cfg(target_os = "linux")]
+        {
+            maker.tcp_user_timeout = state.tcp_user_timeout;
+        }
+        maker.ip_tos = state.ip_tos;
+        Ok(maker)
+    }
+}
@@ -0,0 +1,519 @@
+use std::{
+    marker::PhantomData,
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{mpsc, Arc},
+    time::Duration,
+};
+
+use russh::{
+    client::{self, Handle, Msg},
+    keys::{PrivateKeyWithHashAlg, PublicKey, PublicKeyOrCertificate},
+    ChannelStream,
+};
+use thrift::transport::{ReadHalf, TIoChannel, TTcpChannel, WriteHalf};
+use tokio::runtime::Runtime;
+
+use crate::{
+    ConfigError, FromProtocol, FromRead, FromReadTransport, FromWrite, FromWriteTransport,
+    MakeThriftConnection, ThriftConnectionManager, TransportInfo, TransportKind, ValidateAddrs,
+    DEFAULT_CONNECTION_TIMEOUT,
+};
+
+/// The error returned by [`MakeThriftConnectionFromSshTunnel`] when the tunnel can't be opened
+#[derive(Debug)]
+pub enum SshTunnelError {
+    /// Connecting to the SSH server, or opening the channel to the target, failed
+    Ssh(russh::Error),
+    /// The SSH server rejected the credentials
+    AuthenticationFailed,
+}
+
+impl std::fmt::Display for SshTunnelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ssh(e) => write!(f, "ssh tunnel error: {e}"),
+            Self::AuthenticationFailed => f.write_str("ssh authentication failed"),
+        }
+    }
+}
+
+impl std::error::Error for SshTunnelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Ssh(e) => Some(e),
+            Self::AuthenticationFailed => None,
+        }
+    }
+}
+
+impl From<russh::Error> for SshTunnelError {
+    fn from(e: russh::Error) -> Self {
+        Self::Ssh(e)
+    }
+}
+
+impl From<SshTunnelError> for thrift::Error {
+    fn from(e: SshTunnelError) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// How to authenticate to the SSH server
+#[derive(Clone)]
+pub enum SshAuth {
+    Password(String),
+    /// A private key file (like `~/.ssh/id_ed25519`), with its passphrase if it is encrypted
+    PrivateKey {
+        path: PathBuf,
+        passphrase: Option<String>,
+    },
+}
+
+impl std::fmt::Debug for SshAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Password(_) => f.debug_tuple("Password").finish_non_exhaustive(),
+            Self::PrivateKey { path, .. } => f
+                .debug_struct("PrivateKey")
+                .field("path", path)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+/// The SSH server (usually a bastion) to tunnel connections through
+#[derive(Debug, Clone)]
+pub struct SshParams {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub auth: SshAuth,
+    /// The key the server must present. When `None`, it is checked against
+    /// `~/.ssh/known_hosts` instead
+    pub host_key: Option<PublicKey>,
+}
+
+struct HostKeyCheck {
+    host: String,
+    port: u16,
+    host_key: Option<PublicKey>,
+}
+
+impl client::Handler for HostKeyCheck {
+    type Error = russh::Error;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &PublicKeyOrCertificate,
+    ) -> Result<bool, Self::Error> {
+        let PublicKeyOrCertificate::PublicKey { key, .. } = server_public_key else {
+            return Ok(false);
+        };
+        match &self.host_key {
+            Some(host_key) => Ok(host_key.key_data() == key.key_data()),
+            None => Ok(russh::keys::check_known_hosts(&self.host, self.port, key)?),
+        }
+    }
+}
+
+/// Owns the runtime driving the SSH sessions, and shuts it down without blocking,
+/// since the maker may be dropped from an async context (e.g. by a [`bb8::Pool`])
+struct Background(Option<Runtime>);
+
+impl std::ops::Deref for Background {
+    type Target = Runtime;
+
+    fn deref(&self) -> &Runtime {
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl Drop for Background {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+struct Tunnel {
+    ssh: SshParams,
+    target_host: String,
+    target_port: u16,
+    session: tokio::sync::Mutex<Option<Handle<HostKeyCheck>>>,
+}
+
+impl Tunnel {
+    async fn connect(&self) -> Result<Handle<HostKeyCheck>, SshTunnelError> {
+        let handler = HostKeyCheck {
+            host: self.ssh.host.clone(),
+            port: self.ssh.port,
+            host_key: self.ssh.host_key.clone(),
+        };
+        let config = Arc::new(client::Config::default());
+        let mut session =
+            client::connect(config, (self.ssh.host.as_str(), self.ssh.port), handler).await?;
+
+        let auth = match &self.ssh.auth {
+            SshAuth::Password(password) => {
+                session
+                    .authenticate_password(&self.ssh.user, password)
+                    .await?
+            }
+            SshAuth::PrivateKey { path, passphrase } => {
+                let key = russh::keys::load_secret_key(path, passphrase.as_deref())
+                    .map_err(russh::Error::from)?;
+                let hash_alg = session.best_supported_rsa_hash().await?.flatten();
+                session
+                    .authenticate_publickey(
+                        &self.ssh.user,
+                        PrivateKeyWithHashAlg::new(Arc::new(key), hash_alg),
+                    )
+                    .await?
+            }
+        };
+        if !auth.success() {
+            return Err(SshTunnelError::AuthenticationFailed);
+        }
+        Ok(session)
+    }
+
+    /// Open a `direct-tcpip` channel to the target, (re)connecting the session if needed
+    async fn open(&self) -> Result<ChannelStream<Msg>, SshTunnelError> {
+        let mut session = self.session.lock().await;
+        let session = match session.take() {
+            Some(handle) if !handle.is_closed() => session.insert(handle),
+            _ => session.insert(self.connect().await?),
+        };
+        let channel = session
+            .channel_open_direct_tcpip(
+                self.target_host.as_str(),
+                self.target_port.into(),
+                Ipv4Addr::LOCALHOST.to_string(),
+                0,
+            )
+            .await?;
+        Ok(channel.into_stream())
+    }
+}
+
+/// A [`MakeThriftConnection`] that attempts to create new connections
+/// to a target host through an SSH tunnel, and a [`FromProtocol`]
+///
+/// A single SSH session is opened (when the first connection is made, and again if it is
+/// closed), and every connection is a new `direct-tcpip` channel to the target through it.
+/// The channel is bridged to a loopback [`TcpStream`] so the rest works like
+/// [`MakeThriftConnectionFromAddrs`](crate::MakeThriftConnectionFromAddrs): the transports are
+/// created from [`ReadHalf<TTcpChannel>`] and [`WriteHalf<TTcpChannel>`].
+/// The session runs on a runtime owned by the maker (and shared by its clones), so it can be
+/// used with [`r2d2`] as well as [`bb8`]
+///
+/// This test only runs when `THRIFT_POOL_TEST_SSHD` is set, and expects an sshd on
+/// `localhost:22` that accepts the key `~/.ssh/id_ed25519` of the current user
+///
+/// ```
+/// # use std::io::{Read, Write};
+/// # use std::net::TcpListener;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{
+/// #     FromProtocol, MakeThriftConnection, MakeThriftConnectionFromSshTunnel, ProtocolAccess,
+/// #     SshAuth, SshParams,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ProtocolAccess for MyThriftClient<Ip, Op> {
+/// #     fn input_protocol_mut(&mut self) -> &mut Ip {
+/// #         &mut self.i_prot
+/// #     }
+/// #     fn output_protocol_mut(&mut self) -> &mut Op {
+/// #         &mut self.o_prot
+/// #     }
+/// # }
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// if std::env::var_os("THRIFT_POOL_TEST_SSHD").is_none() {
+///     return Ok(());
+/// }
+///
+/// // an echo server, only reachable through the tunnel in real life
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let port = listener.local_addr()?.port();
+/// std::thread::spawn(move || {
+///     let (mut stream, _) = listener.accept().unwrap();
+///     let mut buf = [0; 4];
+///     stream.read_exact(&mut buf).unwrap();
+///     stream.write_all(&buf).unwrap();
+/// });
+///
+/// let ssh = SshParams {
+///     host: "localhost".to_owned(),
+///     port: 22,
+///     user: std::env::var("USER")?,
+///     auth: SshAuth::PrivateKey {
+///         path: std::path::Path::new(&std::env::var("HOME")?).join(".ssh/id_ed25519"),
+///         passphrase: None,
+///     },
+///     host_key: None,
+/// };
+/// let maker = MakeThriftConnectionFromSshTunnel::<Client>::new(ssh, "127.0.0.1", port)?;
+///
+/// let mut conn = maker.make_thrift_connection()?;
+/// conn.output_protocol_mut().write_i32(42)?;
+/// conn.output_protocol_mut().flush()?;
+/// assert_eq!(conn.input_protocol_mut().read_i32()?, 42);
+/// # Ok(())
+/// # }
+/// ```
+pub struct MakeThriftConnectionFromSshTunnel<T> {
+    tunnel: Arc<Tunnel>,
+    runtime: Arc<Background>,
+    connect_timeout: Duration,
+    conn: PhantomData<T>,
+}
+
+impl<T> std::fmt::Debug for MakeThriftConnectionFromSshTunnel<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MakeThriftConnectionFromSshTunnel")
+            .field("ssh", &self.tunnel.ssh)
+            .field("target_host", &self.tunnel.target_host)
+            .field("target_port", &self.tunnel.target_port)
+            .field("connect_timeout", &self.connect_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Clone for MakeThriftConnectionFromSshTunnel<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tunnel: self.tunnel.clone(),
+            runtime: self.runtime.clone(),
+            connect_timeout: self.connect_timeout,
+            conn: PhantomData,
+        }
+    }
+}
+
+impl<T> MakeThriftConnectionFromSshTunnel<T> {
+    /// Tunnel connections to `target_host:target_port` (as seen from the SSH server) through `ssh`
+    ///
+    /// Nothing is connected until the first connection is made
+    ///
+    /// # Errors
+    ///
+    /// If the runtime driving the SSH session can't be created
+    pub fn new(
+        ssh: SshParams,
+        target_host: impl Into<String>,
+        target_port: u16,
+    ) -> thrift::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("thrift-pool-ssh")
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            tunnel: Arc::new(Tunnel {
+                ssh,
+                target_host: target_host.into(),
+                target_port,
+                session: tokio::sync::Mutex::new(None),
+            }),
+            runtime: Arc::new(Background(Some(runtime))),
+            connect_timeout: DEFAULT_CONNECTION_TIMEOUT,
+            conn: PhantomData,
+        })
+    }
+
+    /// Give up on opening a channel to the target after `connect_timeout`, including the time
+    /// taken to (re)connect the SSH session (defaults to [`DEFAULT_CONNECTION_TIMEOUT`])
+    ///
+    /// A connect that times out fails with [`std::io::ErrorKind::TimedOut`]
+    ///
+    /// ```
+    /// # use std::net::TcpListener;
+    /// # use std::time::Duration;
+    /// # use thrift::protocol::{
+    /// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+    /// # };
+    /// # use thrift::transport::{
+    /// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+    /// # };
+    /// # use thrift_pool::{
+    /// #     FromProtocol, MakeThriftConnection, MakeThriftConnectionFromSshTunnel, SshAuth, SshParams,
+    /// # };
+    /// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+    /// #     i_prot: Ip,
+    /// #     o_prot: Op,
+    /// # }
+    /// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+    /// #     type InputProtocol = Ip;
+    /// #     type OutputProtocol = Op;
+    /// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+    /// #         MyThriftClient { i_prot, o_prot }
+    /// #     }
+    /// # }
+    /// # type Client = MyThriftClient<
+    /// #     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+    /// #     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+    /// # >;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// // an SSH server that stalls: it accepts connections but never greets them
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    /// let ssh = SshParams {
+    ///     host: "127.0.0.1".to_owned(),
+    ///     port: listener.local_addr()?.port(),
+    ///     user: "thrift".to_owned(),
+    ///     auth: SshAuth::Password("secret".to_owned()),
+    ///     host_key: None,
+    /// };
+    /// let maker = MakeThriftConnectionFromSshTunnel::<Client>::new(ssh, "127.0.0.1", 9090)?
+    ///     .with_connect_timeout(Duration::from_millis(100));
+    ///
+    /// match maker.make_thrift_connection() {
+    ///     Err(thrift::Error::Transport(e)) => {
+    ///         assert_eq!(e.kind, thrift::TransportErrorKind::TimedOut)
+    ///     }
+    ///     _ => panic!("the connect should have timed out"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    /// The SSH server connections are tunneled through
+    pub fn ssh(&self) -> &SshParams {
+        &self.tunnel.ssh
+    }
+
+    /// Check the SSH server and target addresses and the connect timeout for invalid values,
+    /// without connecting nor resolving the hosts
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` describing the first invalid setting
+    pub fn validate_config(&self) -> Result<(), ConfigError> {
+        (self.tunnel.ssh.host.as_str(), self.tunnel.ssh.port).validate_addrs()?;
+        (self.tunnel.target_host.as_str(), self.tunnel.target_port).validate_addrs()?;
+        if self.connect_timeout.is_zero() {
+            return Err(ConfigError::ZeroConnectTimeout);
+        }
+        Ok(())
+    }
+
+    /// Open a new channel to the target, bridged to the returned loopback [`TcpStream`]
+    fn open_stream(&self) -> thrift::Result<TcpStream> {
+        // waiting on a std channel (rather than `Runtime::block_on`) works from async contexts too
+        let (sender, receiver) = mpsc::channel();
+        let tunnel = self.tunnel.clone();
+        let open = self.runtime.spawn(async move {
+            let _ = sender.send(tunnel.open().await);
+        });
+        let mut channel = match receiver.recv_timeout(self.connect_timeout) {
+            Ok(channel) => channel?,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                open.abort();
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "timed out opening the ssh tunnel",
+                )
+                .into());
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(SshTunnelError::Ssh(russh::Error::Disconnect).into())
+            }
+        };
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let stream = TcpStream::connect(listener.local_addr()?)?;
+        let (bridge, peer) = listener.accept()?;
+        if peer != stream.local_addr()? {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                "unexpected connection to the ssh tunnel bridge",
+            )
+            .into());
+        }
+        bridge.set_nonblocking(true)?;
+        self.runtime.spawn(async move {
+            if let Ok(mut bridge) = tokio::net::TcpStream::from_std(bridge) {
+                let _ = tokio::io::copy_bidirectional(&mut bridge, &mut channel).await;
+            }
+        });
+        Ok(stream)
+    }
+}
+
+impl<
+        RL: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RT: FromRead<Read = RL>,
+        IP: FromReadTransport<ReadTransport = RT>,
+        WL: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WT: FromWrite<Write = WL>,
+        OP: FromWriteTransport<WriteTransport = WT>,
+        T: FromProtocol<InputProtocol = IP, OutputProtocol = OP>,
+    > MakeThriftConnectionFromSshTunnel<T>
+{
+    pub fn into_connection_manager(self) -> ThriftConnectionManager<Self> {
+        ThriftConnectionManager::new(self)
+    }
+}
+
+impl<
+        RL: FromRead<Read = ReadHalf<TTcpChannel>>,
+        RT: FromRead<Read = RL>,
+        IP: FromReadTransport<ReadTransport = RT>,
+        WL: FromWrite<Write = WriteHalf<TTcpChannel>>,
+        WT: FromWrite<Write = WL>,
+        OP: FromWriteTransport<WriteTransport = WT>,
+        T: FromProtocol<InputProtocol = IP, OutputProtocol = OP>,
+    > MakeThriftConnection for MakeThriftConnectionFromSshTunnel<T>
+{
+    type Error = thrift::Error;
+
+    type Output = T;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let channel = TTcpChannel::with_stream(self.open_stream()?);
+        let (read, write) = channel.split()?;
+
+        let read_transport = RT::from_read(RL::from_read(read));
+        let input_protocol = IP::from_read_transport(read_transport);
+
+        let write_transport = WT::from_write(WL::from_write(write));
+        let output_protocol = OP::from_write_transport(write_transport);
+
+        Ok(T::from_protocol(input_protocol, output_protocol))
+    }
+}
+
+impl<T> TransportInfo for MakeThriftConnectionFromSshTunnel<T> {
+    fn transport_kind(&self) -> TransportKind {
+        TransportKind::SshTunnel
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use crate::{MakeThriftConnection, TransportInfo, TransportKind};
+
+/// A [`MakeThriftConnection`] whose inner maker `M` can be replaced at runtime,
+/// see [`SwappableMaker::swap`]
+///
+/// This reconfigures a live pool (new endpoints, new timeouts, ...) without recreating it:
+/// connections made after the swap use the new maker, while existing connections are kept
+/// until the pool drops them. Clones share the same maker, so a clone kept aside can swap
+/// the maker of a [`ThriftConnectionManager`](crate::ThriftConnectionManager)
+///
+/// ```
+/// # use std::io::ErrorKind;
+/// # use std::net::TcpListener;
+/// # use r2d2::ManageConnection;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{
+/// #     FromProtocol, MakeThriftConnectionFromAddrs, SwappableMaker, ThriftConnection,
+/// #     ThriftConnectionManager,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ThriftConnection for MyThriftClient<Ip, Op> {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old = TcpListener::bind("127.0.0.1:0")?;
+/// let new = TcpListener::bind("127.0.0.1:0")?;
+/// old.set_nonblocking(true)?;
+///
+/// let maker = SwappableMaker::new(MakeThriftConnectionFromAddrs::<Client, _>::new(
+///     old.local_addr()?,
+/// ));
+/// let manager = ThriftConnectionManager::new(maker.clone());
+///
+/// let _before = manager.connect()?;
+/// assert!(old.accept().is_ok());
+///
+/// maker.swap(MakeThriftConnectionFromAddrs::new(new.local_addr()?));
+/// let _after = manager.connect()?;
+/// assert!(new.accept().is_ok());
+/// assert_eq!(old.accept().unwrap_err().kind(), ErrorKind::WouldBlock);
+/// # Ok(())
+/// # }
+/// ```
+pub struct SwappableMaker<M> {
+    maker: Arc<ArcSwap<M>>,
+}
+
+impl<M> SwappableMaker<M> {
+    pub fn new(maker: M) -> Self {
+        Self {
+            maker: Arc::new(ArcSwap::from_pointee(maker)),
+        }
+    }
+
+    /// Make the next connections with `maker`, returning the previous one
+    ///
+    /// Connects already running finish with the previous maker
+    pub fn swap(&self, maker: M) -> Arc<M> {
+        self.maker.swap(Arc::new(maker))
+    }
+
+    /// The maker currently in use
+    pub fn current(&self) -> Arc<M> {
+        self.maker.load_full()
+    }
+}
+
+impl<M> Clone for SwappableMaker<M> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for SwappableMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SwappableMaker")
+            .field("maker", &self.maker.load())
+            .finish()
+    }
+}
+
+impl<M: MakeThriftConnection> MakeThriftConnection for SwappableMaker<M> {
+    type Error = M::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        // a guard would hold a debt slot (and the replaced maker) for the whole connect
+        self.maker.load_full().make_thrift_connection()
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for SwappableMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.load().transport_kind()
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Read, Write},
+    marker::PhantomData,
+};
+
+use crate::{FromRead, FromWrite};
+
+/// Which way bytes went through a [`TappingRead`] or [`TappingWrite`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WireDirection {
+    Read,
+    Written,
+}
+
+/// Receives a copy of the bytes going through a [`TappingRead`] or [`TappingWrite`]
+///
+/// Layers are created by the maker without any state, so the tap is a type
+/// rather than a value (e.g. forwarding to a static logger)
+pub trait WireTap {
+    fn tap(direction: WireDirection, bytes: &[u8]);
+}
+
+/// A [`WireTap`] printing a [`hexdump`] of every chunk to stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StderrTap;
+
+impl WireTap for StderrTap {
+    fn tap(direction: WireDirection, bytes: &[u8]) {
+        eprintln!("{direction:?} {} bytes\n{}", bytes.len(), hexdump(bytes));
+    }
+}
+
+/// Format `bytes` as lines of 16 bytes: their offset, their hex values and their ASCII
+/// (`.` for non printable bytes)
+///
+/// ```
+/// # use thrift_pool::hexdump;
+/// assert_eq!(
+///     hexdump(b"\x80\x01\x00\x01\x00\x00\x00\x04ping\x00\x00\x00\x01\x00"),
+///     "00000000  80 01 00 01 00 00 00 04 70 69 6e 67 00 00 00 01  ........ping....\n\
+///      00000010  00                                               .",
+/// );
+/// ```
+pub fn hexdump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+    for (i, line) in bytes.chunks(16).enumerate() {
+        if i > 0 {
+            dump.push('\n');
+        }
+        let _ = write!(dump, "{:08x} ", i * 16);
+        for byte in line {
+            let _ = write!(dump, " {byte:02x}");
+        }
+        dump.push_str(&"   ".repeat(16 - line.len()));
+        dump.push_str("  ");
+        dump.extend(line.iter().map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        }));
+    }
+    dump
+}
+
+/// A [`Read`] layer passing every byte read through it to the [`WireTap`] `P`,
+/// to debug what a server actually sends (e.g. framing issues)
+///
+/// It is a layer: use it between the [`ReadHalf`](thrift::transport::ReadHalf)
+/// and the read transport in the client type, to see the bytes exactly as they are on the
+/// wire, see [`TappingWrite`] for the other direction
+///
+/// ```
+/// # use std::io::{Read, Write};
+/// # use std::sync::Mutex;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{TappingRead, TappingWrite, WireDirection, WireTap};
+/// // a tap recording everything
+/// static TAPPED: Mutex<Vec<(WireDirection, Vec<u8>)>> = Mutex::new(Vec::new());
+///
+/// struct Recorder;
+///
+/// impl WireTap for Recorder {
+///     fn tap(direction: WireDirection, bytes: &[u8]) {
+///         TAPPED.lock().unwrap().push((direction, bytes.to_vec()));
+///     }
+/// }
+///
+/// // as used in the client type of a `MakeThriftConnectionFromAddrs`
+/// type InputProtocol =
+///     TBinaryInputProtocol<TBufferedReadTransport<TappingRead<ReadHalf<TTcpChannel>, Recorder>>>;
+/// type OutputProtocol =
+///     TBinaryOutputProtocol<TBufferedWriteTransport<TappingWrite<WriteHalf<TTcpChannel>, Recorder>>>;
+///
+/// let mut write = TappingWrite::<_, Recorder>::new(Vec::new());
+/// write.write_all(b"ping")?;
+/// assert_eq!(write.into_inner(), b"ping");
+///
+/// let mut read = TappingRead::<_, Recorder>::new(&b"pong"[..]);
+/// let mut buf = Vec::new();
+/// read.read_to_end(&mut buf)?;
+/// assert_eq!(buf, b"pong");
+///
+/// assert_eq!(
+///     *TAPPED.lock().unwrap(),
+///     [
+///         (WireDirection::Written, b"ping".to_vec()),
+///         (WireDirection::Read, b"pong".to_vec()),
+///     ],
+/// );
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct TappingRead<R, P = StderrTap> {
+    read: R,
+    tap: PhantomData<P>,
+}
+
+impl<R, P> TappingRead<R, P> {
+    pub fn new(read: R) -> Self {
+        Self {
+            read,
+            tap: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.read
+    }
+}
+
+impl<R: std::fmt::Debug, P> std::fmt::Debug for TappingRead<R, P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TappingRead")
+            .field("read", &self.read)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R: Read, P: WireTap> Read for TappingRead<R, P> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.read.read(buf)?;
+        if n > 0 {
+            P::tap(WireDirection::Read, &buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+impl<R: Read, P: WireTap> FromRead for TappingRead<R, P> {
+    type Read = R;
+    fn from_read(read: R) -> Self {
+        Self::new(read)
+    }
+}
+
+/// A [`Write`] layer passing every byte written through it to the [`WireTap`] `P`,
+/// see [`TappingRead`]
+pub struct TappingWrite<W, P = StderrTap> {
+    write: W,
+    tap: PhantomData<P>,
+}
+
+impl<W, P> TappingWrite<W, P> {
+    pub fn new(write: W) -> Self {
+        Self {
+            write,
+            tap: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.write
+    }
+}
+
+impl<W: std::fmt::Debug, P> std::fmt::Debug for TappingWrite<W, P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TappingWrite")
+            .field("write", &self.write)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W: Write, P: WireTap> Write for TappingWrite<W, P> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.write.write(buf)?;
+        if n > 0 {
+            P::tap(WireDirection::Written, &buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write.flush()
+    }
+}
+
+impl<W: Write, P: WireTap> FromWrite for TappingWrite<W, P> {
+    type Write = W;
+    fn from_write(write: W) -> Self {
+        Self::new(write)
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::ThriftConnectionManager;
+
+type Factory<K, M> = dyn Fn(&K) -> ThriftConnectionManager<M> + Send + Sync;
+type PoolBuilder<M> = dyn Fn() -> bb8::Builder<ThriftConnectionManager<M>> + Send + Sync;
+
+struct Entry<M: bb8::ManageConnection> {
+    pool: bb8::Pool<M>,
+    last_used: Instant,
+}
+
+/// [`bb8`] pools keyed by tenant, each created on the first [`TenantPools::get`] of its tenant
+///
+/// The manager of a tenant's pool comes from the `factory` given to [`TenantPools::new`]
+/// (e.g. to connect to the tenant's own endpoint, with its own credentials), so tenants
+/// never share connections. A pool that wasn't gotten for `idle_ttl` is dropped (along
+/// with its idle connections) by the next [`TenantPools::get`] or [`TenantPools::evict_idle`],
+/// and recreated when needed again. The time counts from the last `get`, not from the last
+/// use of the pool: a clone held by a caller outlives the eviction, and keeps working, while
+/// the next `get` creates another pool for the same tenant. So get the pool for each use,
+/// rather than holding it for longer than `idle_ttl`. Clones share the same pools
+///
+/// ```
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// # use thrift_pool::{MakeThriftConnection, TenantPools, ThriftConnectionManager};
+/// // connections that remember their tenant
+/// struct Conn(&'static str);
+/// # impl thrift_pool::ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), thrift::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// struct Maker(&'static str);
+///
+/// impl MakeThriftConnection for Maker {
+///     type Error = thrift::Error;
+///     type Output = Conn;
+///     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+///         Ok(Conn(self.0))
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let created = Arc::new(AtomicUsize::new(0));
+/// let counter = created.clone();
+/// let pools = TenantPools::new(
+///     move |tenant: &&'static str| {
+///         counter.fetch_add(1, Ordering::SeqCst);
+///         ThriftConnectionManager::new(Maker(tenant))
+///     },
+///     Duration::from_millis(100),
+/// )
+/// .with_pool_builder(|| bb8::Pool::builder().max_size(1));
+///
+/// // each tenant gets its own pool, created once
+/// let acme = pools.get(&"acme");
+/// let globex = pools.get(&"globex");
+/// assert_eq!(acme.get().await?.0, "acme");
+/// assert_eq!(globex.get().await?.0, "globex");
+/// assert_eq!(pools.get(&"acme").get().await?.0, "acme");
+/// assert_eq!(created.load(Ordering::SeqCst), 2);
+///
+/// // an exhausted tenant doesn't starve the others
+/// let _held = acme.get().await?;
+/// assert_eq!(acme.state().idle_connections, 0);
+/// assert_eq!(globex.get().await?.0, "globex");
+///
+/// // unused pools are evicted, and recreated on demand
+/// tokio::time::sleep(Duration::from_millis(100)).await;
+/// assert_eq!(pools.evict_idle(), 2);
+/// assert!(pools.is_empty());
+/// let _ = pools.get(&"globex");
+/// assert_eq!(created.load(Ordering::SeqCst), 3);
+/// # Ok(())
+/// # }
+/// ```
+pub struct TenantPools<K, M>
+where
+    ThriftConnectionManager<M>: bb8::ManageConnection,
+{
+    factory: Arc<Factory<K, M>>,
+    pool_builder: Arc<PoolBuilder<M>>,
+    idle_ttl: Duration,
+    pools: Arc<Mutex<HashMap<K, Entry<ThriftConnectionManager<M>>>>>,
+}
+
+impl<K, M> TenantPools<K, M>
+where
+    K: Eq + Hash + Clone,
+    ThriftConnectionManager<M>: bb8::ManageConnection,
+{
+    /// Create the pool of `tenant` with the manager `factory(tenant)`,
+    /// and evict pools not gotten for `idle_ttl`
+    pub fn new(
+        factory: impl Fn(&K) -> ThriftConnectionManager<M> + Send + Sync + 'static,
+        idle_ttl: Duration,
+    ) -> Self {
+        Self {
+            factory: Arc::new(factory),
+            pool_builder: Arc::new(bb8::Pool::builder),
+            idle_ttl,
+            pools: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Configure the pools with the builders returned by `pool_builder`
+    /// (defaults to [`bb8::Pool::builder`])
+    pub fn with_pool_builder(
+        mut self,
+        pool_builder: impl Fn() -> bb8::Builder<ThriftConnectionManager<M>> + Send + Sync + 'static,
+    ) -> Self {
+        self.pool_builder = Arc::new(pool_builder);
+        self
+    }
+
+    /// The pool of `tenant`, created if it doesn't exist yet
+    ///
+    /// The pool is built without waiting for its `min_idle` connections, which are
+    /// established in the background. Each call restarts the `idle_ttl` of the pool
+    ///
+    /// # Panics
+    ///
+    /// Panics outside of a Tokio runtime when the pool is created,
+    /// as [`bb8`] spawns the task reaping its connections on the current runtime
+    pub fn get(&self, tenant: &K) -> bb8::Pool<ThriftConnectionManager<M>> {
+        {
+            let mut pools = self.pools();
+            self.evict(&mut pools, Instant::now());
+            if let Some(entry) = pools.get_mut(tenant) {
+                entry.last_used = Instant::now();
+                return entry.pool.clone();
+            }
+        }
+        // built unlocked, as the factory may take a while: a concurrent `get` may build one too,
+        // and the first one inserted is kept
+        let pool = (self.pool_builder)().build_unchecked((self.factory)(tenant));
+        let mut pools = self.pools();
+        let entry = pools.entry(tenant.clone()).or_insert(Entry {
+            pool,
+            last_used: Instant::now(),
+        });
+        entry.last_used = Instant::now();
+        entry.pool.clone()
+    }
+
+    /// Drop the pools not gotten for `idle_ttl`, returning how many were
+    pub fn evict_idle(&self) -> usize {
+        self.evict(&mut self.pools(), Instant::now())
+    }
+
+    /// Drop the pool of `tenant`, if any
+    pub fn remove(&self, tenant: &K) -> Option<bb8::Pool<ThriftConnectionManager<M>>> {
+        self.pools().remove(tenant).map(|entry| entry.pool)
+    }
+
+    /// How many tenants have a pool
+    pub fn len(&self) -> usize {
+        self.pools().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools().is_empty()
+    }
+
+    pub fn idle_ttl(&self) -> Duration {
+        self.idle_ttl
+    }
+
+    fn evict(
+        &self,
+        pools: &mut HashMap<K, Entry<ThriftConnectionManager<M>>>,
+        now: Instant,
+    ) -> usize {
+        let before = pools.len();
+        pools.retain(|_, entry| now.duration_since(entry.last_used) < self.idle_ttl);
+        before - pools.len()
+    }
+
+    fn pools(&self) -> MutexGuard<'_, HashMap<K, Entry<ThriftConnectionManager<M>>>> {
+        self.pools.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<K, M> Clone for TenantPools<K, M>
+where
+    ThriftConnectionManager<M>: bb8::ManageConnection,
+{
+    fn clone(&self) -> Self {
+        Self {
+            factory: self.factory.clone(),
+            pool_builder: self.pool_builder.clone(),
+            idle_ttl: self.idle_ttl,
+            pools: self.pools.clone(),
+        }
+    }
+}
+
+impl<K, M> std::fmt::Debug for TenantPools<K, M>
+where
+    ThriftConnectionManager<M>: bb8::ManageConnection,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TenantPools")
+            .field("idle_ttl", &self.idle_ttl)
+            .finish_non_exhaustive()
+    }
+}
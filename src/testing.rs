@@ -0,0 +1,493 @@
+//! Helpers to test connection pools against a real thrift server, or without any server
+//!
+//! Only available with the `testing` feature, which is meant for tests: enable it from
+//! `[dev-dependencies]` only, so that these helpers never ship in production code.
+//! The Unix socket server is only available on Unix
+
+use std::{
+    io::{self, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+#[cfg(unix)]
+use std::{
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::atomic::AtomicUsize,
+};
+
+#[cfg(unix)]
+use thrift::{
+    protocol::{
+        TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TMessageIdentifier,
+        TMessageType, TOutputProtocol, TStructIdentifier, TType,
+    },
+    transport::{TBufferedReadTransport, TBufferedWriteTransport},
+};
+
+use crate::{MakeThriftConnection, ThriftConnection, TransportInfo, TransportKind};
+
+#[cfg(unix)]
+/// A minimal thrift server listening on a Unix socket, see [`spawn_test_server`]
+///
+/// The server is shut down and its socket file removed when this is dropped
+#[derive(Debug)]
+pub struct TestServer {
+    path: PathBuf,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+#[cfg(unix)]
+impl TestServer {
+    /// The path of the socket the server listens on
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Stop accepting connections, wait for the server thread to exit and remove the socket file
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.shutdown.store(true, Ordering::SeqCst);
+            // wake up the accept loop
+            let _ = UnixStream::connect(&self.path);
+            let _ = handle.join();
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(unix)]
+/// Spawn a thrift server on a fresh Unix socket in the temporary directory
+///
+/// The server speaks the binary protocol over a buffered transport, and answers
+/// every call with an empty reply (what a `void` method with no exceptions returns)
+/// carrying the same method name and sequence number.
+/// Each connection is served on its own thread
+///
+/// ```
+/// # use std::os::unix::net::UnixStream;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TMessageIdentifier,
+/// #     TMessageType, TOutputProtocol, TStructIdentifier,
+/// # };
+/// # use thrift::transport::{ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, WriteHalf};
+/// # use thrift_pool::{testing::spawn_test_server, FromProtocol, MakeThriftConnectionFromUnixSocket, ThriftConnection};
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// // validate connections with a `ping` call
+/// impl<Ip: TInputProtocol, Op: TOutputProtocol> ThriftConnection for MyThriftClient<Ip, Op> {
+///     type Error = thrift::Error;
+///     fn is_valid(&mut self) -> Result<(), Self::Error> {
+///         self.o_prot
+///             .write_message_begin(&TMessageIdentifier::new("ping", TMessageType::Call, 1))?;
+///         self.o_prot.write_struct_begin(&TStructIdentifier::new("ping_args"))?;
+///         self.o_prot.write_field_stop()?;
+///         self.o_prot.write_struct_end()?;
+///         self.o_prot.write_message_end()?;
+///         self.o_prot.flush()?;
+///
+///         let reply = self.i_prot.read_message_begin()?;
+///         assert_eq!(reply.message_type, TMessageType::Reply);
+///         self.i_prot.skip(thrift::protocol::TType::Struct)?;
+///         self.i_prot.read_message_end()
+///     }
+/// }
+///
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<UnixStream>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<UnixStream>>>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let server = spawn_test_server()?;
+/// let manager = MakeThriftConnectionFromUnixSocket::<Client, _>::new(server.path().to_owned())
+///     .into_connection_manager();
+///
+/// let pool = r2d2::Pool::builder()
+///     .max_size(2)
+///     .test_on_check_out(true)
+///     .build(manager)?;
+/// let mut conn = pool.get()?;
+/// conn.is_valid()?;
+///
+/// let path = server.path().to_owned();
+/// server.shutdown();
+/// assert!(!path.exists());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns `Err` if the socket can't be bound
+pub fn spawn_test_server() -> io::Result<TestServer> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let path = std::env::temp_dir().join(format!(
+        "thrift-pool-{}-{}.sock",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let handle = thread::spawn({
+        let shutdown = shutdown.clone();
+        move || {
+            for stream in listener.incoming() {
+                if shutdown.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    thread::spawn(move || serve(stream));
+                }
+            }
+        }
+    });
+
+    Ok(TestServer {
+        path,
+        shutdown,
+        handle: Some(handle),
+    })
+}
+
+#[cfg(unix)]
+fn serve(stream: UnixStream) -> thrift::Result<()> {
+    let mut i_prot =
+        TBinaryInputProtocol::new(TBufferedReadTransport::new(stream.try_clone()?), true);
+    let mut o_prot = TBinaryOutputProtocol::new(TBufferedWriteTransport::new(stream), true);
+
+    loop {
+        let call = i_prot.read_message_begin()?;
+        i_prot.skip(TType::Struct)?;
+        i_prot.read_message_end()?;
+
+        o_prot.write_message_begin(&TMessageIdentifier::new(
+            call.name,
+            TMessageType::Reply,
+            call.sequence_number,
+        ))?;
+        o_prot.write_struct_begin(&TStructIdentifier::new("result"))?;
+        o_prot.write_field_stop()?;
+        o_prot.write_struct_end()?;
+        o_prot.write_message_end()?;
+        o_prot.flush()?;
+    }
+}
+
+/// What a [`MockServer`] does with the connections it accepts, see [`MockServerBuilder`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MockBehavior {
+    /// Accept connections, then never read nor write until the server shuts down
+    AcceptAndHang,
+    /// Close connections right after accepting them
+    CloseAfterAccept,
+    /// Send `bytes` one at a time, waiting `interval` before each
+    Drip { bytes: Vec<u8>, interval: Duration },
+    /// Wait for the client to send something, answer with `bytes` (e.g. the start of a frame),
+    /// then reset the connection
+    ResetMidFrame { bytes: Vec<u8> },
+}
+
+/// Configures and spawns a [`MockServer`]
+///
+/// ```
+/// # use std::time::Duration;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TMessageIdentifier,
+/// #     TMessageType, TOutputProtocol, TStructIdentifier,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{
+/// #     testing::{MockServer, MockServerBuilder},
+/// #     FromProtocol, MakeThriftConnectionFromAddrs, ThriftConnection,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// // validate connections with a `ping` call
+/// impl<Ip: TInputProtocol, Op: TOutputProtocol> ThriftConnection for MyThriftClient<Ip, Op> {
+///     type Error = thrift::Error;
+///     fn is_valid(&mut self) -> Result<(), Self::Error> {
+///         self.o_prot
+///             .write_message_begin(&TMessageIdentifier::new("ping", TMessageType::Call, 1))?;
+///         self.o_prot.write_struct_begin(&TStructIdentifier::new("ping_args"))?;
+///         self.o_prot.write_field_stop()?;
+///         self.o_prot.write_struct_end()?;
+///         self.o_prot.write_message_end()?;
+///         self.o_prot.flush()?;
+///
+///         let reply = self.i_prot.read_message_begin()?;
+///         assert_eq!(reply.message_type, TMessageType::Reply);
+///         self.i_prot.skip(thrift::protocol::TType::Struct)?;
+///         self.i_prot.read_message_end()
+///     }
+/// }
+///
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+///
+/// // whether a pool of connections timing out reads after 50ms can check one out
+/// fn check_out(builder: MockServerBuilder) -> Result<(), r2d2::Error> {
+///     let server = builder.spawn().unwrap();
+///     let manager = MakeThriftConnectionFromAddrs::<Client, _>::new(server.addr())
+///         .with_read_timeout(Duration::from_millis(50))
+///         .into_connection_manager();
+///     let pool = r2d2::Pool::builder()
+///         .max_size(1)
+///         .connection_timeout(Duration::from_millis(300))
+///         .build_unchecked(manager);
+///     pool.get().map(drop)
+/// }
+///
+/// // the binary-encoded empty reply to `ping`
+/// let reply = [
+///     0x80, 0x01, 0x00, 0x02, 0, 0, 0, 4, b'p', b'i', b'n', b'g', 0, 0, 0, 1, 0,
+/// ];
+///
+/// assert!(check_out(MockServer::builder().drip(reply.to_vec(), Duration::from_millis(1))).is_ok());
+///
+/// assert!(check_out(MockServer::builder().accept_and_hang()).is_err());
+/// assert!(check_out(MockServer::builder().close_after_accept()).is_err());
+/// assert!(check_out(MockServer::builder().drip(reply.to_vec(), Duration::from_millis(100))).is_err());
+/// assert!(check_out(MockServer::builder().reset_mid_frame(reply[..6].to_vec())).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockServerBuilder {
+    behavior: MockBehavior,
+}
+
+impl MockServerBuilder {
+    /// See [`MockBehavior::AcceptAndHang`] (the default)
+    pub fn accept_and_hang(self) -> Self {
+        self.behavior(MockBehavior::AcceptAndHang)
+    }
+
+    /// See [`MockBehavior::CloseAfterAccept`]
+    pub fn close_after_accept(self) -> Self {
+        self.behavior(MockBehavior::CloseAfterAccept)
+    }
+
+    /// See [`MockBehavior::Drip`]
+    pub fn drip(self, bytes: Vec<u8>, interval: Duration) -> Self {
+        self.behavior(MockBehavior::Drip { bytes, interval })
+    }
+
+    /// See [`MockBehavior::ResetMidFrame`]
+    pub fn reset_mid_frame(self, bytes: Vec<u8>) -> Self {
+        self.behavior(MockBehavior::ResetMidFrame { bytes })
+    }
+
+    pub fn behavior(mut self, behavior: MockBehavior) -> Self {
+        self.behavior = behavior;
+        self
+    }
+
+    /// Spawn the server on a fresh port of `127.0.0.1`
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the socket can't be bound
+    pub fn spawn(self) -> io::Result<MockServer> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let behavior = self.behavior;
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let handle = thread::spawn({
+            let shutdown = shutdown.clone();
+            move || {
+                // kept open until the server shuts down
+                let mut hanging = Vec::new();
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    match &behavior {
+                        MockBehavior::AcceptAndHang => hanging.push(stream),
+                        MockBehavior::CloseAfterAccept => drop(stream),
+                        MockBehavior::Drip { bytes, interval } => {
+                            let (bytes, interval) = (bytes.clone(), *interval);
+                            let shutdown = shutdown.clone();
+                            thread::spawn(move || drip(stream, &bytes, interval, &shutdown));
+                        }
+                        MockBehavior::ResetMidFrame { bytes } => {
+                            let bytes = bytes.clone();
+                            thread::spawn(move || reset_mid_frame(stream, &bytes));
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(MockServer {
+            addr,
+            shutdown,
+            handle: Some(handle),
+        })
+    }
+}
+
+fn drip(
+    mut stream: TcpStream,
+    bytes: &[u8],
+    interval: Duration,
+    shutdown: &AtomicBool,
+) -> io::Result<()> {
+    for byte in bytes {
+        thread::sleep(interval);
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        stream.write_all(&[*byte])?;
+    }
+    Ok(())
+}
+
+fn reset_mid_frame(mut stream: TcpStream, bytes: &[u8]) -> io::Result<()> {
+    // leave what the client sent unread: closing a socket with unread data resets it
+    stream.peek(&mut [0])?;
+    stream.write_all(bytes)?;
+    // let `bytes` reach the client before the reset
+    thread::sleep(Duration::from_millis(10));
+    Ok(())
+}
+
+/// A TCP server misbehaving in a configurable way, to test how pools and connections handle
+/// unresponsive, closed or reset sockets. Created with [`MockServer::builder`]
+///
+/// The server is shut down when this is dropped
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder {
+            behavior: MockBehavior::AcceptAndHang,
+        }
+    }
+
+    /// The address the server listens on
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stop accepting connections, wait for the server thread to exit
+    /// and close the connections it keeps open
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.shutdown.store(true, Ordering::SeqCst);
+            // wake up the accept loop
+            let _ = TcpStream::connect(self.addr);
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// A [`ThriftConnection`] that does nothing: it is always valid and never breaks
+///
+/// Along with [`NoopMaker`], this allows measuring the overhead of a pool on its own,
+/// without a server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoopConnection;
+
+impl ThriftConnection for NoopConnection {
+    type Error = thrift::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn has_broken(&mut self) -> bool {
+        false
+    }
+}
+
+/// A [`MakeThriftConnection`] that instantly creates [`NoopConnection`]s
+///
+/// ```
+/// # use thrift_pool::{testing::NoopMaker, ThriftConnectionManager};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = r2d2::Pool::builder()
+///     .max_size(2)
+///     .build(ThriftConnectionManager::new(NoopMaker))?;
+/// let conn = pool.get()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoopMaker;
+
+impl MakeThriftConnection for NoopMaker {
+    type Error = thrift::Error;
+
+    type Output = NoopConnection;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        Ok(NoopConnection)
+    }
+}
+
+impl TransportInfo for NoopMaker {
+    fn transport_kind(&self) -> TransportKind {
+        TransportKind::Other
+    }
+}
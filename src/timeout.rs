@@ -0,0 +1,340 @@
+use std::{
+    io,
+    net::TcpStream,
+    ops::{Deref, DerefMut},
+    time::{Duration, Instant},
+};
+
+use crate::{FromProtocolWithAddr, FromProtocolWithSocket, ThriftConnection};
+
+/// A [`ThriftConnection`] that keeps a handle on its socket, so its timeouts can be
+/// tightened for a single operation with [`TimeoutConnection::with_timeouts`]
+///
+/// The default timeouts are the ones the socket had when the connection was created
+/// (see [`MakeThriftConnectionFromAddrs::with_read_timeout`](crate::MakeThriftConnectionFromAddrs::with_read_timeout)
+/// and [`MakeThriftConnectionFromAddrs::with_write_timeout`](crate::MakeThriftConnectionFromAddrs::with_write_timeout)).
+///
+/// Since it implements [`FromProtocolWithSocket`], the wrapper can be used directly
+/// as the connection type of [`MakeThriftConnectionFromAddrs`](crate::MakeThriftConnectionFromAddrs).
+/// It derefs to `C` (it can't implement [`ProtocolAccess`](crate::ProtocolAccess), which
+/// requires [`FromProtocol`](crate::FromProtocol))
+///
+/// ```
+/// # use std::net::TcpListener;
+/// # use std::panic::{catch_unwind, AssertUnwindSafe};
+/// # use std::time::Duration;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{
+/// #     FromProtocol, MakeThriftConnection, MakeThriftConnectionFromAddrs, ProtocolAccess,
+/// #     TimeoutConnection,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ProtocolAccess for MyThriftClient<Ip, Op> {
+/// #     fn input_protocol_mut(&mut self) -> &mut Ip {
+/// #         &mut self.i_prot
+/// #     }
+/// #     fn output_protocol_mut(&mut self) -> &mut Op {
+/// #         &mut self.o_prot
+/// #     }
+/// # }
+/// type Client = TimeoutConnection<
+///     MyThriftClient<
+///         TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+///         TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+///     >,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // a server that never answers
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let addr = listener.local_addr()?;
+/// std::thread::spawn(move || {
+///     let _stream = listener.accept().unwrap();
+///     std::thread::park();
+/// });
+///
+/// let default = Duration::from_secs(30);
+/// let mut conn = MakeThriftConnectionFromAddrs::<Client, _>::new(addr)
+///     .with_read_timeout(default)
+///     .with_write_timeout(default)
+///     .make_thrift_connection()?;
+///
+/// let tight = Duration::from_millis(50);
+/// conn.with_timeouts(tight, tight, |_| ())?;
+/// assert_eq!(conn.socket().read_timeout()?, Some(default));
+///
+/// // the read gives up after 50ms instead of 30s
+/// let res = conn.with_timeouts(tight, tight, |client| client.input_protocol_mut().read_i32())?;
+/// assert!(res.is_err());
+/// assert_eq!(conn.socket().read_timeout()?, Some(default));
+/// assert_eq!(conn.socket().write_timeout()?, Some(default));
+///
+/// // the defaults are restored even if the operation panics
+/// let res = catch_unwind(AssertUnwindSafe(|| conn.with_timeouts(tight, tight, |_| panic!())));
+/// assert!(res.is_err());
+/// assert_eq!(conn.socket().read_timeout()?, Some(default));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TimeoutConnection<C> {
+    conn: C,
+    socket: TcpStream,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+/// An absolute point in time by which an operation must be done,
+/// see [`TimeoutConnection::with_deadline_at`]
+///
+/// Unlike a [`Duration`], a deadline can be passed down a call stack and shared by every
+/// operation of a request, each one getting whatever time is left
+///
+/// ```
+/// # use std::time::{Duration, Instant};
+/// # use thrift_pool::Deadline;
+/// let deadline = Deadline::after(Duration::from_secs(60));
+/// assert!(!deadline.has_elapsed());
+/// assert!(deadline.remaining().unwrap() <= Duration::from_secs(60));
+///
+/// let deadline = Deadline::from(Instant::now());
+/// assert!(deadline.has_elapsed());
+/// assert_eq!(deadline.remaining(), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    pub fn at(instant: Instant) -> Self {
+        Self(instant)
+    }
+
+    /// The deadline `duration` from now
+    pub fn after(duration: Duration) -> Self {
+        Self(Instant::now() + duration)
+    }
+
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    /// The time left until the deadline, or `None` if it has elapsed
+    pub fn remaining(&self) -> Option<Duration> {
+        self.0
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    pub fn has_elapsed(&self) -> bool {
+        self.remaining().is_none()
+    }
+}
+
+impl From<Instant> for Deadline {
+    fn from(instant: Instant) -> Self {
+        Self::at(instant)
+    }
+}
+
+/// Restores the default timeouts of a socket when dropped
+struct RestoreTimeouts<'a> {
+    socket: &'a TcpStream,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl Drop for RestoreTimeouts<'_> {
+    fn drop(&mut self) {
+        // nothing else can be done when these fail
+        let _ = self.socket.set_read_timeout(self.read_timeout);
+        let _ = self.socket.set_write_timeout(self.write_timeout);
+    }
+}
+
+impl<C> TimeoutConnection<C> {
+    /// Wrap `conn`, using the current timeouts of `socket` as the defaults
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the socket can't be cloned or its timeouts can't be read
+    pub fn new(conn: C, socket: &TcpStream) -> io::Result<Self> {
+        Ok(Self {
+            conn,
+            socket: socket.try_clone()?,
+            read_timeout: socket.read_timeout()?,
+            write_timeout: socket.write_timeout()?,
+        })
+    }
+
+    /// Run `f` with the `read` and `write` timeouts applied to the socket,
+    /// then restore the defaults (even if `f` panics)
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` (without running `f`) if the timeouts can't be applied,
+    /// for example if one of them is zero
+    pub fn with_timeouts<F, R>(&mut self, read: Duration, write: Duration, f: F) -> io::Result<R>
+    where
+        F: FnOnce(&mut C) -> R,
+    {
+        let _restore = RestoreTimeouts {
+            socket: &self.socket,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+        };
+        self.socket.set_read_timeout(Some(read))?;
+        self.socket.set_write_timeout(Some(write))?;
+        Ok(f(&mut self.conn))
+    }
+
+    /// Run `f` with the time left until `deadline` as the read and write timeouts,
+    /// see [`TimeoutConnection::with_timeouts`]
+    ///
+    /// Each read or write gets the time left when `f` starts: an operation made of several
+    /// of them can overrun the deadline by up to that long
+    ///
+    /// ```
+    /// # use std::net::TcpListener;
+    /// # use std::time::{Duration, Instant};
+    /// # use thrift::protocol::{
+    /// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+    /// # };
+    /// # use thrift::transport::{
+    /// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+    /// # };
+    /// # use thrift_pool::{
+    /// #     Deadline, FromProtocol, MakeThriftConnection, MakeThriftConnectionFromAddrs,
+    /// #     ProtocolAccess, TimeoutConnection,
+    /// # };
+    /// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+    /// #     i_prot: Ip,
+    /// #     o_prot: Op,
+    /// # }
+    /// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+    /// #     type InputProtocol = Ip;
+    /// #     type OutputProtocol = Op;
+    /// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+    /// #         MyThriftClient { i_prot, o_prot }
+    /// #     }
+    /// # }
+    /// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ProtocolAccess for MyThriftClient<Ip, Op> {
+    /// #     fn input_protocol_mut(&mut self) -> &mut Ip {
+    /// #         &mut self.i_prot
+    /// #     }
+    /// #     fn output_protocol_mut(&mut self) -> &mut Op {
+    /// #         &mut self.o_prot
+    /// #     }
+    /// # }
+    /// # type Client = TimeoutConnection<
+    /// #     MyThriftClient<
+    /// #         TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>,
+    /// #         TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+    /// #     >,
+    /// # >;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// // a server that never answers
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    /// let addr = listener.local_addr()?;
+    /// std::thread::spawn(move || {
+    ///     let _stream = listener.accept().unwrap();
+    ///     std::thread::park();
+    /// });
+    /// let mut conn = MakeThriftConnectionFromAddrs::<Client, _>::new(addr).make_thrift_connection()?;
+    ///
+    /// // the read gives up at the deadline
+    /// let start = Instant::now();
+    /// let deadline = start + Duration::from_millis(50);
+    /// let res = conn.with_deadline_at(deadline, |client| client.input_protocol_mut().read_i32())?;
+    /// assert!(res.is_err());
+    /// assert!(start.elapsed() < Duration::from_secs(5));
+    /// assert_eq!(conn.socket().read_timeout()?, None);
+    ///
+    /// // an elapsed deadline fails right away, without running the operation
+    /// let err = conn
+    ///     .with_deadline_at(Deadline::at(start), |_| unreachable!())
+    ///     .unwrap_err();
+    /// assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`io::ErrorKind::TimedOut`] (without running `f` or touching the socket)
+    /// if the deadline has elapsed, or `Err` if the timeouts can't be applied
+    pub fn with_deadline_at<F, R>(&mut self, deadline: impl Into<Deadline>, f: F) -> io::Result<R>
+    where
+        F: FnOnce(&mut C) -> R,
+    {
+        let remaining = deadline
+            .into()
+            .remaining()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "the deadline has elapsed"))?;
+        self.with_timeouts(remaining, remaining, f)
+    }
+
+    /// The socket of the connection
+    pub fn socket(&self) -> &TcpStream {
+        &self.socket
+    }
+
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+impl<C> Deref for TimeoutConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<C> DerefMut for TimeoutConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<C: FromProtocolWithAddr> FromProtocolWithSocket for TimeoutConnection<C> {
+    type InputProtocol = C::InputProtocol;
+
+    type OutputProtocol = C::OutputProtocol;
+
+    fn from_protocol_with_socket(
+        input_protocol: Self::InputProtocol,
+        output_protocol: Self::OutputProtocol,
+        socket: &TcpStream,
+    ) -> thrift::Result<Self> {
+        let conn = C::from_protocol_with_addr(input_protocol, output_protocol, socket.peer_addr()?);
+        Ok(Self::new(conn, socket)?)
+    }
+}
+
+impl<C: ThriftConnection> ThriftConnection for TimeoutConnection<C> {
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.conn.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.conn.has_broken()
+    }
+}
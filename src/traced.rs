@@ -0,0 +1,149 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{FromProtocol, ProtocolAccess, ThriftConnection};
+
+/// A globally unique connection identifier, see [`TracedConnection`]
+///
+/// Displayed as 32 lowercase hex digits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConnectionId(u128);
+
+impl ConnectionId {
+    /// Generate a new identifier
+    ///
+    /// The high 64 bits are the creation time in nanoseconds, the low 64 bits combine a
+    /// per-process random seed with a process-wide counter, so identifiers are unique
+    /// within a process and (with overwhelming probability) across processes
+    pub fn generate() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        static SEED: OnceLock<u64> = OnceLock::new();
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        let seed = *SEED.get_or_init(|| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u32(std::process::id());
+            hasher.finish()
+        });
+        let sequence = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+        let high = u128::from(nanos as u64) << 64;
+        Self(high | u128::from(seed.wrapping_add(sequence)))
+    }
+
+    pub fn as_u128(self) -> u128 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for ConnectionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+/// A [`ThriftConnection`] tagged with a [`ConnectionId`] generated when it is created
+///
+/// The id can be emitted in logs to correlate events of the same connection.
+/// With the `tracing` feature, a debug event carrying the `connection_id` field is emitted
+/// on creation and [`TracedConnection::span`] creates a span carrying it
+///
+/// Since it implements [`FromProtocol`], the wrapper can be used directly
+/// as the connection type of [`MakeThriftConnectionFromAddrs`](crate::MakeThriftConnectionFromAddrs).
+/// It derefs to `C`
+///
+/// ```
+/// # use std::collections::HashSet;
+/// # use thrift_pool::TracedConnection;
+/// let ids: HashSet<_> = (0..1000)
+///     .map(|_| TracedConnection::new(()).connection_id())
+///     .collect();
+/// assert_eq!(ids.len(), 1000);
+/// ```
+#[derive(Debug)]
+pub struct TracedConnection<C> {
+    id: ConnectionId,
+    conn: C,
+}
+
+impl<C> TracedConnection<C> {
+    pub fn new(conn: C) -> Self {
+        let id = ConnectionId::generate();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(connection_id = %id, "created thrift connection");
+        Self { id, conn }
+    }
+
+    pub fn connection_id(&self) -> ConnectionId {
+        self.id
+    }
+
+    /// A span carrying the `connection_id` field, to wrap operations made on this connection
+    #[cfg(feature = "tracing")]
+    pub fn span(&self) -> tracing::Span {
+        tracing::info_span!("thrift_connection", connection_id = %self.id)
+    }
+
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+impl<C> Deref for TracedConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<C> DerefMut for TracedConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<C: FromProtocol> FromProtocol for TracedConnection<C> {
+    type InputProtocol = C::InputProtocol;
+
+    type OutputProtocol = C::OutputProtocol;
+
+    fn from_protocol(
+        input_protocol: Self::InputProtocol,
+        output_protocol: Self::OutputProtocol,
+    ) -> Self {
+        Self::new(C::from_protocol(input_protocol, output_protocol))
+    }
+}
+
+impl<C: ProtocolAccess> ProtocolAccess for TracedConnection<C> {
+    fn input_protocol_mut(&mut self) -> &mut Self::InputProtocol {
+        self.conn.input_protocol_mut()
+    }
+
+    fn output_protocol_mut(&mut self) -> &mut Self::OutputProtocol {
+        self.conn.output_protocol_mut()
+    }
+}
+
+impl<C: ThriftConnection> ThriftConnection for TracedConnection<C> {
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.conn.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.conn.has_broken()
+    }
+}
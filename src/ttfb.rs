@@ -0,0 +1,338 @@
+use std::{
+    cell::Cell,
+    io::{self, Read},
+    ops::{Deref, DerefMut},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{FromRead, MakeThriftConnection, ThriftConnection, TransportInfo, TransportKind};
+
+/// The timing of the [`TtfbConnection::record_ttfb`] running on this thread, if any
+#[derive(Debug, Clone, Copy)]
+struct Timing {
+    start: Instant,
+    first_byte: Option<Instant>,
+}
+
+thread_local! {
+    static TIMING: Cell<Option<Timing>> = const { Cell::new(None) };
+}
+
+/// Sets the timing of this thread while alive, and restores the outer one when dropped,
+/// even if the call panics
+struct Scope {
+    outer: Option<Timing>,
+}
+
+impl Scope {
+    fn enter(timing: Timing) -> Self {
+        Self {
+            outer: TIMING.with(|current| current.replace(Some(timing))),
+        }
+    }
+
+    /// The timing of the call, as it ends
+    fn exit(self) -> Option<Timing> {
+        TIMING.with(Cell::get)
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        TIMING.with(|timing| timing.set(self.outer));
+    }
+}
+
+/// A [`Read`] layer noting when the first byte of a reply arrives,
+/// for [`TtfbConnection::record_ttfb`]
+///
+/// It is a layer: use it between the [`ReadHalf`](thrift::transport::ReadHalf)
+/// and the read transport in the client type, so that the time is that of the first byte
+/// coming off the socket rather than out of a buffer. Outside of `record_ttfb`,
+/// it only forwards reads
+#[derive(Debug)]
+pub struct TtfbRead<R> {
+    read: R,
+}
+
+impl<R> TtfbRead<R> {
+    pub fn new(read: R) -> Self {
+        Self { read }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.read
+    }
+}
+
+impl<R: Read> Read for TtfbRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.read.read(buf)?;
+        if n > 0 {
+            TIMING.with(|timing| {
+                if let Some(mut current) = timing.get() {
+                    current.first_byte.get_or_insert_with(Instant::now);
+                    timing.set(Some(current));
+                }
+            });
+        }
+        Ok(n)
+    }
+}
+
+impl<R: Read> FromRead for TtfbRead<R> {
+    type Read = R;
+    fn from_read(read: R) -> Self {
+        Self::new(read)
+    }
+}
+
+/// A [`ThriftConnection`] measuring the time to first byte of calls made through
+/// [`TtfbConnection::record_ttfb`], and passing it to a callback. Created by [`TtfbMaker`]
+///
+/// The time to first byte goes from the start of the call to the first byte of the reply
+/// read off the socket: it is mostly the time the server took to process the request,
+/// plus a round-trip, while reading the rest of the reply is network time.
+/// The first byte is noted by a [`TtfbRead`] layer, which the client type must contain.
+/// The layer and the connection meet through a thread-local, as the call runs on the thread
+/// calling `record_ttfb`
+///
+/// It derefs to `C`
+pub struct TtfbConnection<C, F> {
+    conn: C,
+    on_ttfb: Arc<F>,
+}
+
+impl<C, F: Fn(Duration)> TtfbConnection<C, F> {
+    pub fn new(conn: C, on_ttfb: F) -> Self {
+        Self {
+            conn,
+            on_ttfb: Arc::new(on_ttfb),
+        }
+    }
+
+    /// Run the call `f`, passing its time to first byte to the callback once it returns
+    ///
+    /// The callback isn't called if `f` read nothing (e.g. a oneway call, or a failed write),
+    /// nor if it panics. The timing of an outer `record_ttfb` is restored afterwards
+    ///
+    /// ```
+    /// # use std::io::Read;
+    /// # use std::panic::{catch_unwind, AssertUnwindSafe};
+    /// # use std::sync::{Arc, Mutex};
+    /// # use std::time::Duration;
+    /// # use thrift_pool::{TtfbConnection, TtfbRead};
+    /// let ttfbs = Arc::new(Mutex::new(Vec::new()));
+    /// let on_ttfb = {
+    ///     let ttfbs = ttfbs.clone();
+    ///     move |ttfb: Duration| ttfbs.lock().unwrap().push(ttfb)
+    /// };
+    /// let mut outer = TtfbConnection::new(TtfbRead::new(&b"reply"[..]), on_ttfb.clone());
+    /// let mut inner = TtfbConnection::new((), on_ttfb);
+    ///
+    /// outer.record_ttfb(|read| {
+    ///     std::thread::sleep(Duration::from_millis(50));
+    ///     // a nested call panicking
+    ///     let _ = catch_unwind(AssertUnwindSafe(|| inner.record_ttfb(|_| panic!("bad reply"))));
+    ///     read.read(&mut [0; 5])
+    /// })?;
+    ///
+    /// // the outer call is timed from its own start
+    /// let ttfbs = ttfbs.lock().unwrap();
+    /// assert_eq!(ttfbs.len(), 1);
+    /// assert!(ttfbs[0] >= Duration::from_millis(50));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn record_ttfb<G, R>(&mut self, f: G) -> R
+    where
+        G: FnOnce(&mut C) -> R,
+    {
+        // restores the outer timing, for a call made from within another one
+        let scope = Scope::enter(Timing {
+            start: Instant::now(),
+            first_byte: None,
+        });
+        let res = f(&mut self.conn);
+        if let Some(Timing {
+            start,
+            first_byte: Some(first_byte),
+        }) = scope.exit()
+        {
+            (self.on_ttfb)(first_byte - start);
+        }
+        res
+    }
+}
+
+impl<C, F> TtfbConnection<C, F> {
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+impl<C: std::fmt::Debug, F> std::fmt::Debug for TtfbConnection<C, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TtfbConnection")
+            .field("conn", &self.conn)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C, F> Deref for TtfbConnection<C, F> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<C, F> DerefMut for TtfbConnection<C, F> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<C: ThriftConnection, F> ThriftConnection for TtfbConnection<C, F> {
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        self.conn.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.conn.has_broken()
+    }
+}
+
+/// A [`MakeThriftConnection`] wrapping the connections of the inner maker `M`
+/// in [`TtfbConnection`]s reporting to `on_ttfb`
+///
+/// ```
+/// # use std::io::{Read, Write};
+/// # use std::net::TcpListener;
+/// # use std::sync::{Arc, Mutex};
+/// # use std::time::Duration;
+/// # use thrift::protocol::{
+/// #     TBinaryInputProtocol, TBinaryOutputProtocol, TInputProtocol, TOutputProtocol,
+/// # };
+/// # use thrift::transport::{
+/// #     ReadHalf, TBufferedReadTransport, TBufferedWriteTransport, TTcpChannel, WriteHalf,
+/// # };
+/// # use thrift_pool::{
+/// #     FromProtocol, MakeThriftConnection, MakeThriftConnectionFromAddrs, ThriftConnection,
+/// #     TtfbMaker, TtfbRead,
+/// # };
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> ThriftConnection for MyThriftClient<Ip, Op> {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// type Client = MyThriftClient<
+///     TBinaryInputProtocol<TBufferedReadTransport<TtfbRead<ReadHalf<TTcpChannel>>>>,
+///     TBinaryOutputProtocol<TBufferedWriteTransport<WriteHalf<TTcpChannel>>>,
+/// >;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let listener = TcpListener::bind("127.0.0.1:0")?;
+/// let ttfbs = Arc::new(Mutex::new(Vec::new()));
+/// let maker = TtfbMaker::new(
+///     MakeThriftConnectionFromAddrs::<Client, _>::new(listener.local_addr()?),
+///     {
+///         let ttfbs = ttfbs.clone();
+///         move |ttfb| ttfbs.lock().unwrap().push(ttfb)
+///     },
+/// );
+/// let mut conn = maker.make_thrift_connection()?;
+///
+/// // a server taking 50ms to answer
+/// let (mut server, _) = listener.accept()?;
+/// let server = std::thread::spawn(move || {
+///     let mut request = [0; 4];
+///     server.read_exact(&mut request)?;
+///     std::thread::sleep(Duration::from_millis(50));
+///     server.write_all(&request)
+/// });
+///
+/// let reply = conn.record_ttfb(|client| {
+///     client.o_prot.write_i32(42)?;
+///     client.o_prot.flush()?;
+///     client.i_prot.read_i32()
+/// })?;
+/// assert_eq!(reply, 42);
+/// server.join().unwrap()?;
+///
+/// let ttfbs = ttfbs.lock().unwrap();
+/// assert_eq!(ttfbs.len(), 1);
+/// assert!(ttfbs[0] >= Duration::from_millis(50));
+/// # Ok(())
+/// # }
+/// ```
+pub struct TtfbMaker<M, F> {
+    maker: M,
+    on_ttfb: Arc<F>,
+}
+
+impl<M, F: Fn(Duration)> TtfbMaker<M, F> {
+    /// Pass the time to first byte of the calls recorded on its connections to `on_ttfb`
+    pub fn new(maker: M, on_ttfb: F) -> Self {
+        Self {
+            maker,
+            on_ttfb: Arc::new(on_ttfb),
+        }
+    }
+}
+
+impl<M, F> TtfbMaker<M, F> {
+    pub fn maker(&self) -> &M {
+        &self.maker
+    }
+}
+
+impl<M: Clone, F> Clone for TtfbMaker<M, F> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            on_ttfb: self.on_ttfb.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug, F> std::fmt::Debug for TtfbMaker<M, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TtfbMaker")
+            .field("maker", &self.maker)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M: MakeThriftConnection, F> MakeThriftConnection for TtfbMaker<M, F> {
+    type Error = M::Error;
+
+    type Output = TtfbConnection<M::Output, F>;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        Ok(TtfbConnection {
+            conn: self.maker.make_thrift_connection()?,
+            on_ttfb: self.on_ttfb.clone(),
+        })
+    }
+}
+
+impl<M: TransportInfo, F> TransportInfo for TtfbMaker<M, F> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,151 @@
+use std::{marker::PhantomData, os::unix::net::UnixStream, path::Path};
+
+use thrift::transport::{ReadHalf, TIoChannel, WriteHalf};
+
+use crate::{
+    ConfigError, FromProtocol, FromRead, FromReadTransport, FromWrite, FromWriteTransport,
+    MakeThriftConnection, ThriftConnectionManager, TransportInfo, TransportKind,
+};
+
+/// A [`MakeThriftConnection`] that attempts to create new connections
+/// from a Unix domain socket path and a [`FromProtocol`]
+///
+/// It works like [`MakeThriftConnectionFromAddrs`](crate::MakeThriftConnectionFromAddrs),
+/// except the channel that gets split is a [`UnixStream`], so the transports need to be created
+/// from [`ReadHalf<UnixStream>`] and [`WriteHalf<UnixStream>`]
+///
+/// ```
+/// use std::os::unix::net::UnixStream;
+///
+/// use thrift_pool::{MakeThriftConnectionFromUnixSocket, FromProtocol};
+///
+/// use thrift::{
+///     protocol::{TCompactInputProtocol, TCompactOutputProtocol, TInputProtocol, TOutputProtocol},
+///     transport::{ReadHalf, TFramedReadTransport, TFramedWriteTransport, WriteHalf},
+/// };
+///
+/// # struct MyThriftClient<Ip: TInputProtocol, Op: TOutputProtocol> {
+/// #     i_prot: Ip,
+/// #     o_prot: Op,
+/// # }
+/// # impl<Ip: TInputProtocol, Op: TOutputProtocol> FromProtocol for MyThriftClient<Ip, Op> {
+/// #     type InputProtocol = Ip;
+/// #     type OutputProtocol = Op;
+/// #     fn from_protocol(i_prot: Ip, o_prot: Op) -> Self {
+/// #         MyThriftClient { i_prot, o_prot }
+/// #     }
+/// # }
+/// type Client = MyThriftClient<
+///     TCompactInputProtocol<TFramedReadTransport<ReadHalf<UnixStream>>>,
+///     TCompactOutputProtocol<TFramedWriteTransport<WriteHalf<UnixStream>>>,
+/// >;
+///
+/// let manager = MakeThriftConnectionFromUnixSocket::<Client, _>::new("/tmp/thrift.sock")
+///     .into_connection_manager();
+/// ```
+pub struct MakeThriftConnectionFromUnixSocket<T, P> {
+    path: P,
+    conn: PhantomData<T>,
+}
+
+impl<T, P: std::fmt::Debug> std::fmt::Debug for MakeThriftConnectionFromUnixSocket<T, P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MakeThriftConnectionFromUnixSocket")
+            .field("path", &self.path)
+            .field("conn", &self.conn)
+            .finish()
+    }
+}
+
+impl<T, P: Clone> Clone for MakeThriftConnectionFromUnixSocket<T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            conn: PhantomData,
+        }
+    }
+}
+
+impl<T, P> MakeThriftConnectionFromUnixSocket<T, P> {
+    pub fn new(path: P) -> Self {
+        Self {
+            path,
+            conn: PhantomData,
+        }
+    }
+
+    /// The socket path connections are made to
+    pub fn path(&self) -> &P {
+        &self.path
+    }
+}
+
+impl<T, P: AsRef<Path>> MakeThriftConnectionFromUnixSocket<T, P> {
+    /// Check the socket path for invalid values, without connecting
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the path is empty
+    ///
+    /// ```
+    /// # use thrift_pool::{ConfigError, MakeThriftConnectionFromUnixSocket};
+    /// let maker = MakeThriftConnectionFromUnixSocket::<(), _>::new("");
+    /// assert_eq!(maker.validate_config(), Err(ConfigError::EmptyPath));
+    /// ```
+    pub fn validate_config(&self) -> Result<(), ConfigError> {
+        if self.path.as_ref().as_os_str().is_empty() {
+            return Err(ConfigError::EmptyPath);
+        }
+        Ok(())
+    }
+}
+
+impl<
+        P: AsRef<Path>,
+        RL: FromRead<Read = ReadHalf<UnixStream>>,
+        RT: FromRead<Read = RL>,
+        IP: FromReadTransport<ReadTransport = RT>,
+        WL: FromWrite<Write = WriteHalf<UnixStream>>,
+        WT: FromWrite<Write = WL>,
+        OP: FromWriteTransport<WriteTransport = WT>,
+        T: FromProtocol<InputProtocol = IP, OutputProtocol = OP>,
+    > MakeThriftConnectionFromUnixSocket<T, P>
+{
+    pub fn into_connection_manager(self) -> ThriftConnectionManager<Self> {
+        ThriftConnectionManager::new(self)
+    }
+}
+
+impl<
+        P: AsRef<Path>,
+        RL: FromRead<Read = ReadHalf<UnixStream>>,
+        RT: FromRead<Read = RL>,
+        IP: FromReadTransport<ReadTransport = RT>,
+        WL: FromWrite<Write = WriteHalf<UnixStream>>,
+        WT: FromWrite<Write = WL>,
+        OP: FromWriteTransport<WriteTransport = WT>,
+        T: FromProtocol<InputProtocol = IP, OutputProtocol = OP>,
+    > MakeThriftConnection for MakeThriftConnectionFromUnixSocket<T, P>
+{
+    type Error = thrift::Error;
+
+    type Output = T;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let (read, write) = UnixStream::connect(&self.path)?.split()?;
+
+        let read_transport = RT::from_read(RL::from_read(read));
+        let input_protocol = IP::from_read_transport(read_transport);
+
+        let write_transport = WT::from_write(WL::from_write(write));
+        let output_protocol = OP::from_write_transport(write_transport);
+
+        Ok(T::from_protocol(input_protocol, output_protocol))
+    }
+}
+
+impl<T, P> TransportInfo for MakeThriftConnectionFromUnixSocket<T, P> {
+    fn transport_kind(&self) -> TransportKind {
+        TransportKind::Unix
+    }
+}
@@ -0,0 +1,185 @@
+use std::cell::Cell;
+
+use crate::{MakeThriftConnection, ThriftConnection, ThriftConnectionManager};
+
+thread_local! {
+    static EVICTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether the connection being returned to the pool on this thread should be dropped
+pub(crate) fn is_evicting() -> bool {
+    EVICTING.with(Cell::get)
+}
+
+/// Return `conn` to its pool, making [`ThriftConnectionManager`] report it as broken
+/// so the pool drops it
+pub(crate) fn evict<P>(conn: P) {
+    EVICTING.with(|evicting| evicting.set(true));
+    drop(conn);
+    EVICTING.with(|evicting| evicting.set(false));
+}
+
+/// The outcome of [`validate_all_r2d2`] and/or [`validate_all_bb8`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Connections that passed [`ThriftConnection::is_valid`] and went back to the pool
+    pub passed: usize,
+    /// Connections that failed [`ThriftConnection::is_valid`] and were evicted
+    pub failed: usize,
+}
+
+fn validate<P, C>(conns: Vec<P>) -> ValidationReport
+where
+    P: std::ops::DerefMut<Target = C>,
+    C: ThriftConnection,
+{
+    let mut report = ValidationReport::default();
+    for mut conn in conns {
+        if conn.is_valid().is_ok() {
+            report.passed += 1;
+        } else {
+            report.failed += 1;
+            evict(conn);
+        }
+    }
+    report
+}
+
+/// Validate every idle connection of `pool`, evicting the ones that fail
+/// [`ThriftConnection::is_valid`]
+///
+/// As many connections as there are idle ones are checked out with
+/// [`r2d2::Pool::try_get`] and held until all of them are validated, so each one is seen once.
+/// Meanwhile they're unavailable to other threads, which have to wait for, or create, other
+/// connections; and if they check out idle connections first, those aren't validated.
+///
+/// If the pool tests connections on checkout (the default), it already evicts the invalid
+/// ones when they're checked out here, without them being counted in the report.
+/// Build it with `test_on_check_out(false)` to see them as `failed`
+///
+/// ```
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use thrift_pool::{validate_all_r2d2, MakeThriftConnection, ThriftConnection, ThriftConnectionManager, ValidationReport};
+/// # struct Conn {
+/// #     valid: bool,
+/// # }
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         if self.valid {
+/// #             Ok(())
+/// #         } else {
+/// #             Err(thrift::Error::from(std::io::Error::from(std::io::ErrorKind::BrokenPipe)))
+/// #         }
+/// #     }
+/// # }
+/// // every other connection is invalid
+/// # #[derive(Default)]
+/// # struct Maker(AtomicUsize);
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         let valid = self.0.fetch_add(1, Ordering::SeqCst) % 2 == 0;
+/// #         Ok(Conn { valid })
+/// #     }
+/// # }
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = r2d2::Pool::builder()
+///     .max_size(4)
+///     .min_idle(Some(0))
+///     .test_on_check_out(false)
+///     .build(ThriftConnectionManager::new(Maker::default()))?;
+/// // fill the pool
+/// drop((0..4).map(|_| pool.get()).collect::<Result<Vec<_>, _>>()?);
+///
+/// let report = validate_all_r2d2(&pool);
+/// assert_eq!(report, ValidationReport { passed: 2, failed: 2 });
+/// assert_eq!(pool.state().connections, 2);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "impl-r2d2")]
+pub fn validate_all_r2d2<T>(pool: &r2d2::Pool<ThriftConnectionManager<T>>) -> ValidationReport
+where
+    T: MakeThriftConnection + Send + Sync + 'static,
+    T::Error: std::error::Error + 'static,
+    T::Output: ThriftConnection<Error = T::Error> + Send + 'static,
+{
+    let idle = pool.state().idle_connections;
+    let conns = (0..idle).map_while(|_| pool.try_get()).collect();
+    validate(conns)
+}
+
+/// Validate every idle connection of `pool`, evicting the ones that fail
+/// [`ThriftConnection::is_valid`]
+///
+/// This works like [`validate_all_r2d2`], except that connections are checked out with
+/// [`bb8::Pool::get`]: if other tasks take idle connections in the meantime, this waits
+/// for (or creates) other ones, up to the pool's connection timeout
+///
+/// ```
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use thrift_pool::{validate_all_bb8, MakeThriftConnection, ThriftConnection, ThriftConnectionManager, ValidationReport};
+/// # #[derive(Debug)]
+/// # struct Conn {
+/// #     valid: bool,
+/// # }
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         if self.valid {
+/// #             Ok(())
+/// #         } else {
+/// #             Err(thrift::Error::from(std::io::Error::from(std::io::ErrorKind::BrokenPipe)))
+/// #         }
+/// #     }
+/// # }
+/// // every other connection is invalid
+/// # #[derive(Default)]
+/// # struct Maker(AtomicUsize);
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         let valid = self.0.fetch_add(1, Ordering::SeqCst) % 2 == 0;
+/// #         Ok(Conn { valid })
+/// #     }
+/// # }
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = bb8::Pool::builder()
+///     .max_size(4)
+///     .test_on_check_out(false)
+///     .build(ThriftConnectionManager::new(Maker::default()))
+///     .await?;
+/// // fill the pool
+/// let mut conns = Vec::new();
+/// for _ in 0..4 {
+///     conns.push(pool.get().await?);
+/// }
+/// drop(conns);
+///
+/// let report = validate_all_bb8(&pool).await;
+/// assert_eq!(report, ValidationReport { passed: 2, failed: 2 });
+/// assert_eq!(pool.state().connections, 2);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "impl-bb8")]
+pub async fn validate_all_bb8<T>(pool: &bb8::Pool<ThriftConnectionManager<T>>) -> ValidationReport
+where
+    T: MakeThriftConnection + Send + Sync + 'static,
+    T::Error: Send + std::fmt::Debug + 'static,
+    T::Output: ThriftConnection<Error = T::Error> + Send + 'static,
+{
+    let idle = pool.state().idle_connections;
+    let mut conns = Vec::new();
+    for _ in 0..idle {
+        match pool.get().await {
+            Ok(conn) => conns.push(conn),
+            Err(_) => break,
+        }
+    }
+    validate(conns)
+}
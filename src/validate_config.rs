@@ -0,0 +1,229 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    time::Duration,
+};
+
+use crate::{MakeThriftConnectionFromAddrs, ResolvedAddrs};
+
+#[cfg(feature = "impl-bb8")]
+use crate::BackgroundResolvedAddrs;
+
+/// An invalid setting of a maker, found by its `validate_config` method without connecting
+/// (or when building one from a `ThriftPoolConfig`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// There is no address to connect to
+    NoAddrs,
+    /// An address isn't of the form `host:port`
+    InvalidAddr(String),
+    /// An address has port 0, which can't be connected to
+    ZeroPort(String),
+    /// The path of a Unix socket is empty
+    EmptyPath,
+    /// The connect timeout is 0, which [`std::net::TcpStream::connect_timeout`] rejects
+    ZeroConnectTimeout,
+    /// The read timeout is 0, which [`std::net::TcpStream::set_read_timeout`] rejects
+    ZeroReadTimeout,
+    /// The write timeout is 0, which [`std::net::TcpStream::set_write_timeout`] rejects
+    ZeroWriteTimeout,
+    /// TLS is enabled, but there is no TLS maker to build
+    TlsUnsupported,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoAddrs => write!(f, "there must be at least one address"),
+            Self::InvalidAddr(addr) => write!(f, "`{addr}` is not a `host:port` address"),
+            Self::ZeroPort(addr) => write!(f, "`{addr}` has port 0"),
+            Self::EmptyPath => write!(f, "the socket path must not be empty"),
+            Self::ZeroConnectTimeout => write!(f, "the connect timeout must be greater than 0"),
+            Self::ZeroReadTimeout => write!(f, "the read timeout must be greater than 0"),
+            Self::ZeroWriteTimeout => write!(f, "the write timeout must be greater than 0"),
+            Self::TlsUnsupported => write!(f, "TLS is enabled but not supported"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Addresses that can be checked without resolving them,
+/// see [`MakeThriftConnectionFromAddrs::validate_config`]
+///
+/// It is implemented for the [`ToSocketAddrs`](std::net::ToSocketAddrs) types of the standard
+/// library and of this crate. A string is only checked for being of the form `host:port`:
+/// whether the host resolves is only found out when connecting
+pub trait ValidateAddrs {
+    /// # Errors
+    ///
+    /// Returns `Err` describing the first invalid address
+    fn validate_addrs(&self) -> Result<(), ConfigError>;
+}
+
+fn check_port(port: u16, addr: impl FnOnce() -> String) -> Result<(), ConfigError> {
+    if port == 0 {
+        return Err(ConfigError::ZeroPort(addr()));
+    }
+    Ok(())
+}
+
+impl ValidateAddrs for str {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        // split like `ToSocketAddrs` does for a `str` (so IPv6 hosts may be bracketed or not)
+        match self.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() => match port.parse::<u16>() {
+                Ok(port) => check_port(port, || self.to_owned()),
+                Err(_) => Err(ConfigError::InvalidAddr(self.to_owned())),
+            },
+            _ => Err(ConfigError::InvalidAddr(self.to_owned())),
+        }
+    }
+}
+
+impl ValidateAddrs for String {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        self.as_str().validate_addrs()
+    }
+}
+
+impl ValidateAddrs for (&str, u16) {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        let (host, port) = *self;
+        if host.is_empty() {
+            return Err(ConfigError::InvalidAddr(format!("{host}:{port}")));
+        }
+        check_port(port, || format!("{host}:{port}"))
+    }
+}
+
+impl ValidateAddrs for (String, u16) {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        (self.0.as_str(), self.1).validate_addrs()
+    }
+}
+
+impl ValidateAddrs for SocketAddr {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        check_port(self.port(), || self.to_string())
+    }
+}
+
+impl ValidateAddrs for SocketAddrV4 {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        check_port(self.port(), || self.to_string())
+    }
+}
+
+impl ValidateAddrs for SocketAddrV6 {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        check_port(self.port(), || self.to_string())
+    }
+}
+
+impl ValidateAddrs for (IpAddr, u16) {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        SocketAddr::from(*self).validate_addrs()
+    }
+}
+
+impl ValidateAddrs for (Ipv4Addr, u16) {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        SocketAddr::from(*self).validate_addrs()
+    }
+}
+
+impl ValidateAddrs for (Ipv6Addr, u16) {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        SocketAddr::from(*self).validate_addrs()
+    }
+}
+
+impl<A: ValidateAddrs> ValidateAddrs for [A] {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        if self.is_empty() {
+            return Err(ConfigError::NoAddrs);
+        }
+        self.iter().try_for_each(A::validate_addrs)
+    }
+}
+
+impl<A: ValidateAddrs> ValidateAddrs for Vec<A> {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        self.as_slice().validate_addrs()
+    }
+}
+
+impl<A: ValidateAddrs, const N: usize> ValidateAddrs for [A; N] {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        self.as_slice().validate_addrs()
+    }
+}
+
+impl<A: ValidateAddrs + ?Sized> ValidateAddrs for &A {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        (**self).validate_addrs()
+    }
+}
+
+impl ValidateAddrs for ResolvedAddrs {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        self.as_slice().validate_addrs()
+    }
+}
+
+#[cfg(feature = "impl-bb8")]
+impl ValidateAddrs for BackgroundResolvedAddrs {
+    fn validate_addrs(&self) -> Result<(), ConfigError> {
+        self.current().validate_addrs()
+    }
+}
+
+impl<T, S: ValidateAddrs> MakeThriftConnectionFromAddrs<T, S> {
+    /// Check the addresses and the timeouts for values the socket would reject, without
+    /// connecting nor resolving the addresses
+    ///
+    /// An address that doesn't resolve or a backend that is down is only found out
+    /// when connecting, so a valid configuration can still fail to connect
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` describing the first invalid setting
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use thrift_pool::{ConfigError, MakeThriftConnectionFromAddrs};
+    /// let maker = MakeThriftConnectionFromAddrs::<(), _>::new("localhost");
+    /// assert_eq!(
+    ///     maker.validate_config(),
+    ///     Err(ConfigError::InvalidAddr("localhost".to_owned()))
+    /// );
+    ///
+    /// let maker = MakeThriftConnectionFromAddrs::<(), _>::new(["localhost:9090", "localhost:0"]);
+    /// assert_eq!(
+    ///     maker.validate_config(),
+    ///     Err(ConfigError::ZeroPort("localhost:0".to_owned()))
+    /// );
+    ///
+    /// let maker = MakeThriftConnectionFromAddrs::<(), _>::new("localhost:9090")
+    ///     .with_read_timeout(Duration::ZERO);
+    /// assert_eq!(maker.validate_config(), Err(ConfigError::ZeroReadTimeout));
+    ///
+    /// // nothing listens on port 1, but the configuration itself is valid
+    /// let maker = MakeThriftConnectionFromAddrs::<(), _>::new("127.0.0.1:1");
+    /// assert_eq!(maker.validate_config(), Ok(()));
+    /// assert!(maker.open_stream().is_err());
+    /// ```
+    pub fn validate_config(&self) -> Result<(), ConfigError> {
+        self.addrs().validate_addrs()?;
+        if self.connect_timeout() == Some(Duration::ZERO) {
+            return Err(ConfigError::ZeroConnectTimeout);
+        }
+        if self.read_timeout() == Some(Duration::ZERO) {
+            return Err(ConfigError::ZeroReadTimeout);
+        }
+        if self.write_timeout() == Some(Duration::ZERO) {
+            return Err(ConfigError::ZeroWriteTimeout);
+        }
+        Ok(())
+    }
+}
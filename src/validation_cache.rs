@@ -0,0 +1,172 @@
+use std::{
+    ops::{Deref, DerefMut},
+    time::{Duration, Instant},
+};
+
+use crate::{MakeThriftConnection, ThriftConnection, TransportInfo, TransportKind};
+
+/// A [`ThriftConnection`] that skips [`ThriftConnection::is_valid`] if it passed it less than
+/// `validated_within` ago. Created by [`CachedValidationMaker`]
+///
+/// A failed validation is not cached: the next check validates again.
+/// It derefs to `C`
+#[derive(Debug)]
+pub struct CachedValidationConnection<C> {
+    conn: C,
+    validated_within: Duration,
+    last_validated: Option<Instant>,
+}
+
+impl<C> CachedValidationConnection<C> {
+    pub fn new(conn: C, validated_within: Duration) -> Self {
+        Self {
+            conn,
+            validated_within,
+            last_validated: None,
+        }
+    }
+
+    /// When the connection last passed the `is_valid` of `C`, if it ever did
+    pub fn last_validated(&self) -> Option<Instant> {
+        self.last_validated
+    }
+
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+impl<C> Deref for CachedValidationConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<C> DerefMut for CachedValidationConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<C: ThriftConnection> ThriftConnection for CachedValidationConnection<C> {
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        if self
+            .last_validated
+            .is_some_and(|at| at.elapsed() < self.validated_within)
+        {
+            return Ok(());
+        }
+        self.last_validated = None;
+        self.conn.is_valid()?;
+        self.last_validated = Some(Instant::now());
+        Ok(())
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.conn.has_broken()
+    }
+}
+
+/// A [`MakeThriftConnection`] wrapping the connections of the inner maker `M`
+/// in [`CachedValidationConnection`]s, which are validated at most once per
+/// `validated_within`
+///
+/// This saves the round-trip of validating a connection checked out again right after
+/// being returned, at the cost of not noticing a peer that died in between
+/// (which still shows up as failed calls)
+///
+/// ```
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// # use thrift_pool::{
+/// #     CachedValidationMaker, MakeThriftConnection, ThriftConnection, ThriftConnectionManager,
+/// # };
+/// // connections counting their validations
+/// struct Conn(Arc<AtomicUsize>);
+///
+/// impl ThriftConnection for Conn {
+///     type Error = thrift::Error;
+///     fn is_valid(&mut self) -> Result<(), Self::Error> {
+///         self.0.fetch_add(1, Ordering::SeqCst);
+///         Ok(())
+///     }
+/// }
+///
+/// struct Maker(Arc<AtomicUsize>);
+///
+/// impl MakeThriftConnection for Maker {
+///     type Error = thrift::Error;
+///     type Output = Conn;
+///     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+///         Ok(Conn(self.0.clone()))
+///     }
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let validations = Arc::new(AtomicUsize::new(0));
+/// let maker = CachedValidationMaker::new(Maker(validations.clone()), Duration::from_millis(200));
+/// let pool = r2d2::Pool::builder()
+///     .max_size(1)
+///     .build(ThriftConnectionManager::new(maker))?;
+///
+/// drop(pool.get()?);
+/// assert_eq!(validations.load(Ordering::SeqCst), 1);
+///
+/// // checked out again within the window: not validated
+/// drop(pool.get()?);
+/// assert_eq!(validations.load(Ordering::SeqCst), 1);
+///
+/// // once the window has passed, validated again
+/// std::thread::sleep(Duration::from_millis(250));
+/// drop(pool.get()?);
+/// assert_eq!(validations.load(Ordering::SeqCst), 2);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CachedValidationMaker<M> {
+    maker: M,
+    validated_within: Duration,
+}
+
+impl<M> CachedValidationMaker<M> {
+    /// Skip the validation of connections that passed one less than `validated_within` ago
+    pub fn new(maker: M, validated_within: Duration) -> Self {
+        Self {
+            maker,
+            validated_within,
+        }
+    }
+
+    pub fn validated_within(&self) -> Duration {
+        self.validated_within
+    }
+
+    pub fn maker(&self) -> &M {
+        &self.maker
+    }
+}
+
+impl<M: MakeThriftConnection> MakeThriftConnection for CachedValidationMaker<M> {
+    type Error = M::Error;
+
+    type Output = CachedValidationConnection<M::Output>;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        Ok(CachedValidationConnection::new(
+            self.maker.make_thrift_connection()?,
+            self.validated_within,
+        ))
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for CachedValidationMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
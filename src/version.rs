@@ -0,0 +1,150 @@
+use std::ops::RangeInclusive;
+
+use crate::{MakeThriftConnection, TransportInfo, TransportKind};
+
+/// The error returned by [`VersionCheckMaker`] when the server's version
+/// is outside of the expected range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionMismatch<V> {
+    /// The version the server reported
+    pub version: V,
+    /// The versions the client supports
+    pub expected: RangeInclusive<V>,
+}
+
+impl<V: std::fmt::Display> std::fmt::Display for VersionMismatch<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "server version {} is not in the supported range {}..={}",
+            self.version,
+            self.expected.start(),
+            self.expected.end()
+        )
+    }
+}
+
+impl<V: std::fmt::Debug + std::fmt::Display> std::error::Error for VersionMismatch<V> {}
+
+impl<V> From<VersionMismatch<V>> for thrift::Error
+where
+    V: std::fmt::Debug + std::fmt::Display + Send + Sync + 'static,
+{
+    fn from(e: VersionMismatch<V>) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// A [`MakeThriftConnection`] that asks every new connection of the inner maker `M`
+/// for the server's version, and rejects it if it isn't in `expected`
+///
+/// `probe` issues whatever call the service exposes for that (e.g. a `getVersion` RPC).
+/// If it fails, its error is returned; if the version is unsupported, the connection
+/// is dropped and a [`VersionMismatch`] is returned, so mismatched clients fail
+/// when connecting instead of exchanging garbage with the server later.
+/// See [`PreflightMaker`](crate::PreflightMaker) for checks that don't fit this shape
+///
+/// ```
+/// # use thrift_pool::{MakeThriftConnection, VersionCheckMaker, VersionMismatch};
+/// // a connection to a server at version 3
+/// # struct Conn {
+/// #     server_version: u32,
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn { server_version: 3 })
+/// #     }
+/// # }
+/// let probe = |conn: &mut Conn| Ok(conn.server_version);
+///
+/// let maker = VersionCheckMaker::new(Maker, probe, 2..=4);
+/// assert!(maker.make_thrift_connection().is_ok());
+///
+/// let maker = VersionCheckMaker::new(Maker, probe, 1..=2);
+/// let err = maker.make_thrift_connection().err().unwrap();
+/// assert_eq!(
+///     err.to_string(),
+///     "server version 3 is not in the supported range 1..=2"
+/// );
+/// match err {
+///     thrift::Error::User(e) => {
+///         let e = e.downcast::<VersionMismatch<u32>>().unwrap();
+///         assert_eq!(e.version, 3);
+///     }
+///     e => panic!("unexpected error: {e}"),
+/// }
+/// ```
+pub struct VersionCheckMaker<M, F, V> {
+    maker: M,
+    probe: F,
+    expected: RangeInclusive<V>,
+}
+
+impl<M, F, V> VersionCheckMaker<M, F, V> {
+    pub fn new(maker: M, probe: F, expected: RangeInclusive<V>) -> Self {
+        Self {
+            maker,
+            probe,
+            expected,
+        }
+    }
+
+    /// The versions connections are accepted for
+    pub fn expected(&self) -> &RangeInclusive<V> {
+        &self.expected
+    }
+}
+
+impl<M: Clone, F: Clone, V: Clone> Clone for VersionCheckMaker<M, F, V> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            probe: self.probe.clone(),
+            expected: self.expected.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug, F, V: std::fmt::Debug> std::fmt::Debug for VersionCheckMaker<M, F, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VersionCheckMaker")
+            .field("maker", &self.maker)
+            .field("expected", &self.expected)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M, F, V> MakeThriftConnection for VersionCheckMaker<M, F, V>
+where
+    M: MakeThriftConnection,
+    M::Error: From<VersionMismatch<V>>,
+    F: Fn(&mut M::Output) -> Result<V, M::Error>,
+    V: PartialOrd + Clone,
+{
+    type Error = M::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let mut conn = self.maker.make_thrift_connection()?;
+        let version = (self.probe)(&mut conn)?;
+        if self.expected.contains(&version) {
+            Ok(conn)
+        } else {
+            Err(VersionMismatch {
+                version,
+                expected: self.expected.clone(),
+            }
+            .into())
+        }
+    }
+}
+
+impl<M: TransportInfo, F, V> TransportInfo for VersionCheckMaker<M, F, V> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    ops::{Deref, DerefMut},
+    time::{Duration, Instant},
+};
+
+use crate::{MakeThriftConnection, ThriftConnection, TransportInfo, TransportKind};
+
+/// The error returned by [`WarmupConnection::is_valid`](ThriftConnection::is_valid)
+/// while the connection is warming up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarmingUp {
+    /// How long until the connection can be used
+    pub remaining: Duration,
+}
+
+impl std::fmt::Display for WarmingUp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the connection is warming up for another {:?}",
+            self.remaining
+        )
+    }
+}
+
+impl std::error::Error for WarmingUp {}
+
+impl From<WarmingUp> for thrift::Error {
+    fn from(e: WarmingUp) -> Self {
+        thrift::Error::User(Box::new(e))
+    }
+}
+
+/// A [`ThriftConnection`] that fails [`ThriftConnection::is_valid`] with [`WarmingUp`]
+/// until its warmup period has passed. Created by [`WarmupMaker`]
+///
+/// It derefs to `C`
+#[derive(Debug)]
+pub struct WarmupConnection<C> {
+    conn: C,
+    ready_at: Instant,
+}
+
+impl<C> WarmupConnection<C> {
+    pub fn new(conn: C, ready_at: Instant) -> Self {
+        Self { conn, ready_at }
+    }
+
+    /// When the connection can be used
+    pub fn ready_at(&self) -> Instant {
+        self.ready_at
+    }
+
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+impl<C> Deref for WarmupConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl<C> DerefMut for WarmupConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+impl<C> ThriftConnection for WarmupConnection<C>
+where
+    C: ThriftConnection,
+    C::Error: From<WarmingUp>,
+{
+    type Error = C::Error;
+
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        let remaining = self.ready_at.saturating_duration_since(Instant::now());
+        if !remaining.is_zero() {
+            return Err(WarmingUp { remaining }.into());
+        }
+        self.conn.is_valid()
+    }
+
+    fn has_broken(&mut self) -> bool {
+        self.conn.has_broken()
+    }
+}
+
+/// A [`MakeThriftConnection`] wrapping the connections of the inner maker `M`
+/// in [`WarmupConnection`]s, that can't be checked out before `warmup` has passed
+///
+/// This ramps up the traffic sent to a backend that was just (re)started: whatever the
+/// demand, new connections only become usable gradually. Pools validate connections on
+/// checkout (unless built with `test_on_check_out(false)`), where a connection that is still
+/// warming up fails and is dropped, so a checkout during the warmup costs a connection.
+/// Keep `warmup` short, and the pool's connection timeout long enough to wait it out
+///
+/// ```
+/// # use std::time::Duration;
+/// # use thrift_pool::{MakeThriftConnection, ThriftConnection, WarmingUp, WarmupMaker};
+/// # struct Conn;
+/// # impl ThriftConnection for Conn {
+/// #     type Error = thrift::Error;
+/// #     fn is_valid(&mut self) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Maker;
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = Conn;
+/// #     fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+/// #         Ok(Conn)
+/// #     }
+/// # }
+/// let maker = WarmupMaker::new(Maker, Duration::from_millis(50));
+/// let mut conn = maker.make_thrift_connection()?;
+///
+/// // not usable right away
+/// match conn.is_valid() {
+///     Err(thrift::Error::User(e)) => assert!(e.downcast_ref::<WarmingUp>().is_some()),
+///     _ => panic!("expected the connection to be warming up"),
+/// }
+///
+/// // but after the warmup
+/// std::thread::sleep(Duration::from_millis(50));
+/// assert!(conn.is_valid().is_ok());
+/// # Ok::<(), thrift::Error>(())
+/// ```
+pub struct WarmupMaker<M> {
+    maker: M,
+    warmup: Duration,
+}
+
+impl<M> WarmupMaker<M> {
+    pub fn new(maker: M, warmup: Duration) -> Self {
+        Self { maker, warmup }
+    }
+
+    pub fn warmup(&self) -> Duration {
+        self.warmup
+    }
+}
+
+impl<M: Clone> Clone for WarmupMaker<M> {
+    fn clone(&self) -> Self {
+        Self {
+            maker: self.maker.clone(),
+            warmup: self.warmup,
+        }
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for WarmupMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WarmupMaker")
+            .field("maker", &self.maker)
+            .field("warmup", &self.warmup)
+            .finish()
+    }
+}
+
+impl<M: MakeThriftConnection> MakeThriftConnection for WarmupMaker<M> {
+    type Error = M::Error;
+
+    type Output = WarmupConnection<M::Output>;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let conn = self.maker.make_thrift_connection()?;
+        Ok(WarmupConnection::new(conn, Instant::now() + self.warmup))
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for WarmupMaker<M> {
+    fn transport_kind(&self) -> TransportKind {
+        self.maker.transport_kind()
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    rng::Rng, Endpoint, MakeThriftConnection, MakeThriftConnectionBalanced,
+    MakeThriftConnectionFromAddrs, TransportInfo, TransportKind, WeightedRandom,
+};
+
+/// A [`MakeThriftConnection`] that creates each new connection with one of its makers,
+/// picked at random in proportion to its weight
+///
+/// E.g. weights of 95 and 5 send about 5% of the connections to a canary backend.
+/// A maker of weight 0 is never picked. Like [`RoundRobinMaker`](crate::RoundRobinMaker),
+/// there is a single attempt per connection. It is a [`MakeThriftConnectionBalanced`] with the
+/// [`WeightedRandom`] policy, whose connections are those of the makers. Clones share the same
+/// random number generator, which can be seeded with [`WeightedRandomMaker::with_seed`]
+/// for reproducible picks
+///
+/// ```
+/// # use thrift_pool::{MakeThriftConnection, WeightedRandomMaker};
+/// # struct Maker(usize);
+/// # impl MakeThriftConnection for Maker {
+/// #     type Error = thrift::Error;
+/// #     type Output = usize;
+/// #     fn make_thrift_connection(&self) -> Result<usize, thrift::Error> {
+/// #         Ok(self.0)
+/// #     }
+/// # }
+/// let maker = WeightedRandomMaker::new(vec![(Maker(0), 90), (Maker(1), 10), (Maker(2), 0)])
+///     .with_seed(42);
+///
+/// let mut picks = [0; 3];
+/// for _ in 0..10_000 {
+///     picks[maker.make_thrift_connection()?] += 1;
+/// }
+/// assert!((8_800..=9_200).contains(&picks[0]), "{picks:?}");
+/// assert!((800..=1_200).contains(&picks[1]), "{picks:?}");
+/// assert_eq!(picks[2], 0);
+///
+/// // makers seeded alike pick alike
+/// let picks = |maker: &WeightedRandomMaker<Maker>| {
+///     (0..100)
+///         .map(|_| maker.make_thrift_connection())
+///         .collect::<Result<Vec<_>, _>>()
+/// };
+/// let weights = || vec![(Maker(0), 1), (Maker(1), 1), (Maker(2), 1)];
+/// let a = WeightedRandomMaker::new(weights()).with_seed(7);
+/// let b = WeightedRandomMaker::new(weights()).with_seed(7);
+/// assert_eq!(picks(&a)?, picks(&b)?);
+/// # Ok::<(), thrift::Error>(())
+/// ```
+pub struct WeightedRandomMaker<M>(MakeThriftConnectionBalanced<M, WeightedRandom>);
+
+impl<M> WeightedRandomMaker<M> {
+    /// # Panics
+    ///
+    /// Panics if no maker has a positive weight
+    pub fn new(makers: Vec<(M, u32)>) -> Self {
+        assert!(
+            makers.iter().any(|(_, weight)| *weight > 0),
+            "WeightedRandomMaker needs at least one maker with a positive weight"
+        );
+        let (endpoints, weights) = makers
+            .into_iter()
+            .map(|(maker, weight)| (Endpoint::new(maker), weight))
+            .unzip();
+        Self(MakeThriftConnectionBalanced::new(
+            endpoints,
+            WeightedRandom::new(weights),
+        ))
+    }
+
+    /// Seed the random number generator used to pick makers (defaults to a random seed)
+    ///
+    /// This is meant for tests
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        let balancer = self.0.balancer_mut();
+        *balancer = balancer.clone().with_seed(seed);
+        self
+    }
+
+    /// The makers, with their weights
+    pub fn makers(&self) -> impl ExactSizeIterator<Item = (&M, u32)> + '_ {
+        let weights = self.0.balancer().weights();
+        self.0
+            .endpoints()
+            .iter()
+            .zip(weights)
+            .map(|(endpoint, weight)| (&endpoint.maker, *weight))
+    }
+}
+
+/// The index of an item of `weights` picked at random in proportion to its weight,
+/// `None` if no weight is positive
+pub(crate) fn pick_weighted(
+    rng: &Rng,
+    weights: impl Iterator<Item = u32> + Clone,
+) -> Option<usize> {
+    let total_weight: u64 = weights.clone().map(u64::from).sum();
+    if total_weight == 0 {
+        return None;
+    }
+    let mut target = (rng.next_unit() * total_weight as f64) as u64;
+    for (i, weight) in weights.clone().enumerate() {
+        match target.checked_sub(u64::from(weight)) {
+            Some(rest) => target = rest,
+            None => return Some(i),
+        }
+    }
+    // only reached through rounding: the last item of positive weight
+    weights
+        .enumerate()
+        .filter(|(_, weight)| *weight > 0)
+        .last()
+        .map(|(i, _)| i)
+}
+
+impl<T, S> WeightedRandomMaker<MakeThriftConnectionFromAddrs<T, S>> {
+    /// A [`MakeThriftConnectionFromAddrs`] per item of `addrs`, with its weight
+    ///
+    /// # Panics
+    ///
+    /// Panics if no item has a positive weight
+    pub fn from_addrs(addrs: impl IntoIterator<Item = (S, u32)>) -> Self {
+        Self::new(
+            addrs
+                .into_iter()
+                .map(|(addrs, weight)| (MakeThriftConnectionFromAddrs::new(addrs), weight))
+                .collect(),
+        )
+    }
+}
+
+impl<M> Clone for WeightedRandomMaker<M> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for WeightedRandomMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeightedRandomMaker")
+            .field("makers", &self.makers().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M: MakeThriftConnection> MakeThriftConnection for WeightedRandomMaker<M> {
+    type Error = M::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let endpoint = self.0.pick().expect("the total weight is positive");
+        self.0.connect_to(endpoint)
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for WeightedRandomMaker<M> {
+    /// The kind of the first maker
+    fn transport_kind(&self) -> TransportKind {
+        self.0.transport_kind()
+    }
+}
@@ -0,0 +1,202 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{MakeThriftConnection, RoundRobin, TransportInfo, TransportKind};
+
+/// The label holding the availability zone of an [`Endpoint`], see [`ZoneAwareMaker`]
+pub const ZONE_LABEL: &str = "zone";
+
+/// A maker `M` reaching one backend, with metadata labels describing it
+/// (e.g. its availability zone, see [`ZONE_LABEL`])
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Endpoint<M> {
+    pub maker: M,
+    pub labels: BTreeMap<String, String>,
+}
+
+impl<M> Endpoint<M> {
+    pub fn new(maker: M) -> Self {
+        Self {
+            maker,
+            labels: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set the [`ZONE_LABEL`]
+    pub fn with_zone(self, zone: impl Into<String>) -> Self {
+        self.with_label(ZONE_LABEL, zone)
+    }
+
+    pub fn label(&self, key: &str) -> Option<&str> {
+        self.labels.get(key).map(String::as_str)
+    }
+
+    pub fn zone(&self) -> Option<&str> {
+        self.label(ZONE_LABEL)
+    }
+}
+
+/// A [`MakeThriftConnection`] that prefers the endpoints in the client's own availability
+/// zone, to avoid the latency and cost of cross-zone traffic
+///
+/// Each connection is attempted with the endpoints of `zone` first (taken in turn, to spread
+/// connections among them), then, only if they all fail, with the endpoints of other zones
+/// (or without a zone). If every endpoint fails, the error of the last one is returned.
+/// Clones share the same position
+///
+/// ```
+/// # use std::sync::atomic::{AtomicBool, Ordering};
+/// # use std::sync::Arc;
+/// # use thrift_pool::{Endpoint, MakeThriftConnection, ZoneAwareMaker};
+/// // a backend that can go down
+/// struct Backend {
+///     name: &'static str,
+///     up: Arc<AtomicBool>,
+/// }
+///
+/// impl MakeThriftConnection for Backend {
+///     type Error = thrift::Error;
+///     type Output = &'static str;
+///     fn make_thrift_connection(&self) -> Result<&'static str, thrift::Error> {
+///         if self.up.load(Ordering::SeqCst) {
+///             Ok(self.name)
+///         } else {
+///             Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into())
+///         }
+///     }
+/// }
+///
+/// let up = [(); 3].map(|_| Arc::new(AtomicBool::new(true)));
+/// let backend = |name, i: usize| Backend { name, up: up[i].clone() };
+/// let maker = ZoneAwareMaker::new(
+///     "eu-west-1a",
+///     vec![
+///         Endpoint::new(backend("remote", 0)).with_zone("eu-west-1b"),
+///         Endpoint::new(backend("local 1", 1)).with_zone("eu-west-1a"),
+///         Endpoint::new(backend("local 2", 2)).with_zone("eu-west-1a"),
+///     ],
+/// );
+///
+/// let conns = (0..4)
+///     .map(|_| maker.make_thrift_connection())
+///     .collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(conns, ["local 1", "local 2", "local 1", "local 2"]);
+///
+/// // the other zone is only used when the local one is down
+/// up[1].store(false, Ordering::SeqCst);
+/// assert_eq!(maker.make_thrift_connection()?, "local 2");
+/// up[2].store(false, Ordering::SeqCst);
+/// assert_eq!(maker.make_thrift_connection()?, "remote");
+///
+/// up[0].store(false, Ordering::SeqCst);
+/// assert!(maker.make_thrift_connection().is_err());
+/// # Ok::<(), thrift::Error>(())
+/// ```
+pub struct ZoneAwareMaker<M> {
+    zone: String,
+    /// The endpoints of `zone`, then the others
+    endpoints: Arc<[Endpoint<M>]>,
+    local: usize,
+    /// Where the endpoints of each zone start from, in turn
+    next: Arc<RoundRobin>,
+}
+
+impl<M> ZoneAwareMaker<M> {
+    /// # Panics
+    ///
+    /// Panics if `endpoints` is empty
+    pub fn new(zone: impl Into<String>, endpoints: Vec<Endpoint<M>>) -> Self {
+        assert!(
+            !endpoints.is_empty(),
+            "ZoneAwareMaker needs at least one endpoint"
+        );
+        let zone = zone.into();
+        let (mut endpoints, remote): (Vec<_>, Vec<_>) = endpoints
+            .into_iter()
+            .partition(|endpoint| endpoint.zone() == Some(zone.as_str()));
+        let local = endpoints.len();
+        endpoints.extend(remote);
+        Self {
+            zone,
+            endpoints: endpoints.into(),
+            local,
+            next: Arc::default(),
+        }
+    }
+
+    /// The zone of the client
+    pub fn zone(&self) -> &str {
+        &self.zone
+    }
+
+    /// The endpoints of the client's zone
+    pub fn local_endpoints(&self) -> &[Endpoint<M>] {
+        &self.endpoints[..self.local]
+    }
+
+    /// The endpoints of other zones, or without a zone
+    pub fn remote_endpoints(&self) -> &[Endpoint<M>] {
+        &self.endpoints[self.local..]
+    }
+
+    /// The endpoints in the order to try them for the next connection
+    fn attempts(&self) -> impl Iterator<Item = &Endpoint<M>> {
+        let start = self.next.advance();
+        rotated(self.local_endpoints(), start).chain(rotated(self.remote_endpoints(), start))
+    }
+}
+
+/// `items`, starting from the `start`-th (modulo their count)
+fn rotated<T>(items: &[T], start: usize) -> impl Iterator<Item = &T> {
+    let (before, after) = items.split_at(start.checked_rem(items.len()).unwrap_or(0));
+    after.iter().chain(before)
+}
+
+impl<M> Clone for ZoneAwareMaker<M> {
+    fn clone(&self) -> Self {
+        Self {
+            zone: self.zone.clone(),
+            endpoints: self.endpoints.clone(),
+            local: self.local,
+            next: self.next.clone(),
+        }
+    }
+}
+
+impl<M: std::fmt::Debug> std::fmt::Debug for ZoneAwareMaker<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZoneAwareMaker")
+            .field("zone", &self.zone)
+            .field("endpoints", &self.endpoints)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M: MakeThriftConnection> MakeThriftConnection for ZoneAwareMaker<M> {
+    type Error = M::Error;
+
+    type Output = M::Output;
+
+    fn make_thrift_connection(&self) -> Result<Self::Output, Self::Error> {
+        let mut last_err = None;
+        for endpoint in self.attempts() {
+            match endpoint.maker.make_thrift_connection() {
+                Ok(conn) => return Ok(conn),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.expect("there is at least one endpoint"))
+    }
+}
+
+impl<M: TransportInfo> TransportInfo for ZoneAwareMaker<M> {
+    /// The kind of the first endpoint of the client's zone, or of the first endpoint
+    fn transport_kind(&self) -> TransportKind {
+        self.endpoints[0].maker.transport_kind()
+    }
+}
@@ -0,0 +1,12 @@
+//! The managers rejected by `ensure_r2d2_compatible` and `ensure_bb8_compatible`,
+//! along with the errors the compiler reports for them
+#![cfg(any(feature = "impl-r2d2", feature = "impl-bb8"))]
+
+#[test]
+fn incompatible_managers() {
+    let t = trybuild::TestCases::new();
+    #[cfg(feature = "impl-r2d2")]
+    t.compile_fail("tests/ui/r2d2_error_not_std_error.rs");
+    #[cfg(feature = "impl-bb8")]
+    t.compile_fail("tests/ui/bb8_connection_not_send.rs");
+}
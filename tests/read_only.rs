@@ -0,0 +1,8 @@
+//! The writes rejected on a `ReadOnlyConnection`,
+//! along with the error the compiler reports for them
+
+#[test]
+fn read_only_connection_writes() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/read_only_write.rs");
+}
@@ -0,0 +1,29 @@
+use std::rc::Rc;
+
+use thrift_pool::{
+    ensure_bb8_compatible, MakeThriftConnection, ThriftConnection, ThriftConnectionManager,
+};
+
+// bb8 moves connections across threads
+struct Conn(Rc<()>);
+
+impl ThriftConnection for Conn {
+    type Error = thrift::Error;
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+struct Maker;
+
+impl MakeThriftConnection for Maker {
+    type Error = thrift::Error;
+    type Output = Conn;
+    fn make_thrift_connection(&self) -> Result<Conn, thrift::Error> {
+        Ok(Conn(Rc::new(())))
+    }
+}
+
+fn main() {
+    ensure_bb8_compatible(ThriftConnectionManager::new(Maker));
+}
@@ -0,0 +1,30 @@
+use thrift_pool::{
+    ensure_r2d2_compatible, MakeThriftConnection, ThriftConnection, ThriftConnectionManager,
+};
+
+// r2d2 needs the error to implement `std::error::Error`
+#[derive(Debug)]
+struct MyError;
+
+struct Conn;
+
+impl ThriftConnection for Conn {
+    type Error = MyError;
+    fn is_valid(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+struct Maker;
+
+impl MakeThriftConnection for Maker {
+    type Error = MyError;
+    type Output = Conn;
+    fn make_thrift_connection(&self) -> Result<Conn, MyError> {
+        Ok(Conn)
+    }
+}
+
+fn main() {
+    ensure_r2d2_compatible(ThriftConnectionManager::new(Maker));
+}
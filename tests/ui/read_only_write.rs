@@ -0,0 +1,12 @@
+use thrift::protocol::{TBinaryInputProtocol, TOutputProtocol};
+use thrift::transport::{ReadHalf, TBufferedReadTransport, TTcpChannel};
+use thrift_pool::{ProtocolAccess, ReadOnlyConnection};
+
+type Conn = ReadOnlyConnection<TBinaryInputProtocol<TBufferedReadTransport<ReadHalf<TTcpChannel>>>>;
+
+// there is no output protocol to write with
+fn ping(conn: &mut Conn) -> thrift::Result<()> {
+    conn.output_protocol_mut().write_i32(1)
+}
+
+fn main() {}